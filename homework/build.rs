@@ -0,0 +1,18 @@
+//! Exports the enabled features to the crate and its tests as `CS492_FEATURES`, a sorted
+//! comma-separated list, so that `tests/feature_matrix.rs` can report and check the
+//! configuration it runs under. `scripts/feature-matrix.sh` runs that test for each combination.
+
+use std::env;
+
+fn main() {
+    let mut features = env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .collect::<Vec<_>>();
+    features.sort();
+
+    println!("cargo:rustc-env=CS492_FEATURES={}", features.join(","));
+    println!("cargo:rerun-if-changed=build.rs");
+}
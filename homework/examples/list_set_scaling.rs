@@ -0,0 +1,158 @@
+//! Measures the throughput of the list-based sets across thread counts and read ratios, and
+//! writes the results as CSV, e.g. for plotting the scalability curves from the literature.
+//!
+//! ```text
+//! cargo run --release --example list_set_scaling -- [DURATION_MS] > scaling.csv
+//! ```
+//!
+//! Compared implementations:
+//!
+//! - `lock-coupling`: `OrderedListSet` (hand-over-hand locking)
+//! - `harris`, `harris-michael`, `harris-herlihy-shavit`: `lockfree::list::List` with the
+//!   respective search procedures
+//!
+//! Optimistic and lazy lists are not part of this crate, so they are not measured.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use crossbeam_epoch::pin;
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::OrderedListSet;
+use lockfree::list::List;
+use rand::prelude::*;
+use std::env;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const THREADS: [usize; 7] = [1, 2, 4, 8, 16, 32, 64];
+/// Percentage of `contains` operations. The rest is split evenly between `insert` and `remove`.
+const READ_RATIOS: [usize; 3] = [50, 90, 99];
+const KEY_RANGE: usize = 1024;
+
+/// Common interface of the measured sets.
+trait ListSet: Sync {
+    fn new() -> Self;
+    fn contains(&self, key: usize) -> bool;
+    fn insert(&self, key: usize) -> bool;
+    fn remove(&self, key: usize) -> bool;
+}
+
+impl ListSet for OrderedListSet<usize> {
+    fn new() -> Self {
+        OrderedListSet::new()
+    }
+
+    fn contains(&self, key: usize) -> bool {
+        OrderedListSet::contains(self, &key)
+    }
+
+    fn insert(&self, key: usize) -> bool {
+        OrderedListSet::insert(self, key).is_ok()
+    }
+
+    fn remove(&self, key: usize) -> bool {
+        OrderedListSet::remove(self, &key).is_ok()
+    }
+}
+
+macro_rules! harris_set {
+    ($name:ident, $lookup:ident, $insert:ident, $delete:ident) => {
+        struct $name(List<usize, ()>);
+
+        impl ListSet for $name {
+            fn new() -> Self {
+                $name(List::new())
+            }
+
+            fn contains(&self, key: usize) -> bool {
+                self.0.$lookup(&key, &pin()).is_some()
+            }
+
+            fn insert(&self, key: usize) -> bool {
+                self.0.$insert(key, (), &pin())
+            }
+
+            fn remove(&self, key: usize) -> bool {
+                self.0.$delete(&key, &pin()).is_some()
+            }
+        }
+    };
+}
+
+harris_set!(Harris, harris_lookup, harris_insert, harris_delete);
+harris_set!(
+    HarrisMichael,
+    harris_michael_lookup,
+    harris_michael_insert,
+    harris_michael_delete
+);
+harris_set!(
+    HarrisHerlihyShavit,
+    harris_herlihy_shavit_lookup,
+    harris_herlihy_shavit_insert,
+    harris_herlihy_shavit_delete
+);
+
+/// Runs the workload for `duration` and returns the throughput in operations per second.
+fn measure<S: ListSet>(threads: usize, read_ratio: usize, duration: Duration) -> f64 {
+    let set = S::new();
+    let mut rng = thread_rng();
+    for _ in 0..KEY_RANGE / 2 {
+        let _ = set.insert(rng.gen_range(0, KEY_RANGE));
+    }
+
+    let stop = AtomicBool::new(false);
+    let (set, stop) = (&set, &stop);
+    let start = Instant::now();
+    let ops: usize = scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(move |_| {
+                    let mut rng = thread_rng();
+                    let mut ops = 0;
+                    while !stop.load(Ordering::Relaxed) {
+                        let key = rng.gen_range(0, KEY_RANGE);
+                        let op = rng.gen_range(0, 100);
+                        if op < read_ratio {
+                            let _ = set.contains(key);
+                        } else if op % 2 == 0 {
+                            let _ = set.insert(key);
+                        } else {
+                            let _ = set.remove(key);
+                        }
+                        ops += 1;
+                    }
+                    ops
+                })
+            })
+            .collect();
+
+        thread::sleep(duration);
+        stop.store(true, Ordering::Relaxed);
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+    .unwrap();
+
+    ops as f64 / start.elapsed().as_secs_f64()
+}
+
+fn run<S: ListSet>(name: &str, duration: Duration) {
+    for &read_ratio in READ_RATIOS.iter() {
+        for &threads in THREADS.iter() {
+            let throughput = measure::<S>(threads, read_ratio, duration);
+            println!("{},{},{},{:.0}", name, threads, read_ratio, throughput);
+        }
+    }
+}
+
+fn main() {
+    let duration = env::args()
+        .nth(1)
+        .map(|ms| Duration::from_millis(ms.parse().expect("DURATION_MS must be an integer")))
+        .unwrap_or_else(|| Duration::from_secs(1));
+
+    println!("impl,threads,read_ratio,ops_per_sec");
+    run::<OrderedListSet<usize>>("lock-coupling", duration);
+    run::<Harris>("harris", duration);
+    run::<HarrisMichael>("harris-michael", duration);
+    run::<HarrisHerlihyShavit>("harris-herlihy-shavit", duration);
+}
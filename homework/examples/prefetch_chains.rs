@@ -0,0 +1,93 @@
+//! Measures the lookup throughput of `SplitOrderedList` across chain lengths, and writes the
+//! results as CSV. Run it with and without the `prefetch` feature and compare the two outputs:
+//! the prefetch only pays off on the long chains, whose nodes are mostly out of the cache.
+//!
+//! ```text
+//! cargo run --release --example prefetch_chains -- [DURATION_MS] > off.csv
+//! cargo run --release --example prefetch_chains --features prefetch -- [DURATION_MS] > on.csv
+//! ```
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::reclaim::pin;
+use cs492_concur_homework::{NonblockingMap, SplitOrderedList};
+use rand::prelude::*;
+use std::env;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Load factors of the measured lists, i.e. the average length of their bucket chains.
+const CHAIN_LENGTHS: [usize; 5] = [2, 8, 32, 128, 512];
+const THREADS: [usize; 3] = [1, 4, 16];
+/// Large enough that the nodes don't fit in the last-level cache.
+const KEYS: usize = 1 << 22;
+
+/// Creates a list of `KEYS` entries with chains of about `chain_length` nodes. The keys are
+/// inserted in random order so that the nodes adjacent in the list are scattered in memory.
+fn build(chain_length: usize) -> SplitOrderedList<usize> {
+    let list = SplitOrderedList::builder()
+        .load_factor(chain_length)
+        .build();
+    let mut keys: Vec<usize> = (0..KEYS).collect();
+    keys.shuffle(&mut thread_rng());
+    let guard = pin();
+    for key in keys {
+        let _ = list.insert(&key, key, &guard);
+    }
+    list
+}
+
+/// Runs lookups of random keys for `duration` and returns the throughput in operations per
+/// second.
+fn measure(list: &SplitOrderedList<usize>, threads: usize, duration: Duration) -> f64 {
+    let stop = AtomicBool::new(false);
+    let stop = &stop;
+    let start = Instant::now();
+    let ops: usize = scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(move |_| {
+                    let mut rng = thread_rng();
+                    let mut ops = 0;
+                    while !stop.load(Ordering::Relaxed) {
+                        let key = rng.gen_range(0, KEYS);
+                        assert!(list.lookup(&key, &pin()).is_some());
+                        ops += 1;
+                    }
+                    ops
+                })
+            })
+            .collect();
+
+        thread::sleep(duration);
+        stop.store(true, Ordering::Relaxed);
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+    .unwrap();
+
+    ops as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let duration = env::args()
+        .nth(1)
+        .map(|ms| Duration::from_millis(ms.parse().expect("DURATION_MS must be an integer")))
+        .unwrap_or_else(|| Duration::from_secs(1));
+
+    let prefetch = if cfg!(feature = "prefetch") {
+        "on"
+    } else {
+        "off"
+    };
+    println!("prefetch,chain_length,threads,ops_per_sec");
+    for &chain_length in CHAIN_LENGTHS.iter() {
+        let list = build(chain_length);
+        for &threads in THREADS.iter() {
+            let throughput = measure(&list, threads, duration);
+            println!(
+                "{},{},{},{:.0}",
+                prefetch, chain_length, threads, throughput
+            );
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Measures the throughput of `SplitOrderedList` (epoch-based reclamation) and
+//! `HpSplitOrderedList` (hazard pointers) across thread counts and read ratios, and writes the
+//! results as CSV, e.g. for comparing the costs of the reclamation schemes.
+//!
+//! ```text
+//! cargo run --release --example reclamation_scaling -- [DURATION_MS] > reclamation.csv
+//! ```
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::reclaim::pin;
+use cs492_concur_homework::{HpSplitOrderedList, NonblockingMap, SplitOrderedList};
+use rand::prelude::*;
+use std::env;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const THREADS: [usize; 6] = [1, 2, 4, 8, 16, 32];
+/// Percentage of lookups. The rest is split evenly between insertions and deletions.
+const READ_RATIOS: [usize; 3] = [50, 90, 99];
+const KEY_RANGE: usize = 1 << 16;
+
+/// Common interface of the measured maps.
+trait Map: Sync {
+    fn new() -> Self;
+    fn lookup(&self, key: usize) -> bool;
+    fn insert(&self, key: usize) -> bool;
+    fn delete(&self, key: usize) -> bool;
+}
+
+impl Map for SplitOrderedList<usize> {
+    fn new() -> Self {
+        SplitOrderedList::new()
+    }
+
+    fn lookup(&self, key: usize) -> bool {
+        NonblockingMap::lookup(self, &key, &pin()).is_some()
+    }
+
+    fn insert(&self, key: usize) -> bool {
+        NonblockingMap::insert(self, &key, key, &pin()).is_ok()
+    }
+
+    fn delete(&self, key: usize) -> bool {
+        NonblockingMap::delete(self, &key, &pin()).is_ok()
+    }
+}
+
+impl Map for HpSplitOrderedList<usize> {
+    fn new() -> Self {
+        HpSplitOrderedList::new()
+    }
+
+    fn lookup(&self, key: usize) -> bool {
+        HpSplitOrderedList::lookup(self, key).is_some()
+    }
+
+    fn insert(&self, key: usize) -> bool {
+        HpSplitOrderedList::insert(self, key, key).is_ok()
+    }
+
+    fn delete(&self, key: usize) -> bool {
+        HpSplitOrderedList::delete(self, key).is_ok()
+    }
+}
+
+/// Runs the workload for `duration` and returns the throughput in operations per second.
+fn measure<M: Map>(threads: usize, read_ratio: usize, duration: Duration) -> f64 {
+    let map = M::new();
+    let mut rng = thread_rng();
+    for _ in 0..KEY_RANGE / 2 {
+        let _ = map.insert(rng.gen_range(0, KEY_RANGE));
+    }
+
+    let stop = AtomicBool::new(false);
+    let (map, stop) = (&map, &stop);
+    let start = Instant::now();
+    let ops: usize = scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(move |_| {
+                    let mut rng = thread_rng();
+                    let mut ops = 0;
+                    while !stop.load(Ordering::Relaxed) {
+                        let key = rng.gen_range(0, KEY_RANGE);
+                        let op = rng.gen_range(0, 100);
+                        if op < read_ratio {
+                            let _ = map.lookup(key);
+                        } else if op % 2 == 0 {
+                            let _ = map.insert(key);
+                        } else {
+                            let _ = map.delete(key);
+                        }
+                        ops += 1;
+                    }
+                    ops
+                })
+            })
+            .collect();
+
+        thread::sleep(duration);
+        stop.store(true, Ordering::Relaxed);
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+    .unwrap();
+
+    ops as f64 / start.elapsed().as_secs_f64()
+}
+
+fn run<M: Map>(name: &str, duration: Duration) {
+    for &read_ratio in READ_RATIOS.iter() {
+        for &threads in THREADS.iter() {
+            let throughput = measure::<M>(threads, read_ratio, duration);
+            println!("{},{},{},{:.0}", name, threads, read_ratio, throughput);
+        }
+    }
+}
+
+fn main() {
+    let duration = env::args()
+        .nth(1)
+        .map(|ms| Duration::from_millis(ms.parse().expect("DURATION_MS must be an integer")))
+        .unwrap_or_else(|| Duration::from_secs(1));
+
+    println!("impl,threads,read_ratio,ops_per_sec");
+    run::<SplitOrderedList<usize>>("epoch", duration);
+    run::<HpSplitOrderedList<usize>>("hazard-pointer", duration);
+}
@@ -0,0 +1,34 @@
+#!/usr/bin/env bash
+# Runs `tests/feature_matrix.rs` with `std` off and on, alone and with each of the other features
+# that change the hash table and its supporting modules, and then with all of them.
+# `check-loom` is left out: it switches to the loom runtime.
+set -euo pipefail
+IFS=$'\n\t'
+
+cd "$(dirname "$0")/.."
+
+FEATURES=(
+    "teaching"
+    "pmem"
+    "sim"
+    "cooperative"
+    "access-time"
+    "small-segments"
+    "instrument"
+    "serde"
+    "rayon"
+    "prefetch"
+)
+
+run() {
+    echo "Testing features [$1]..."
+    cargo test --no-default-features --features "$1" --test feature_matrix
+}
+
+for STD in "" "std"; do
+    run "$STD"
+    for FEATURE in "${FEATURES[@]}"; do
+        run "${STD:+$STD,}$FEATURE"
+    done
+done
+run "std,$(IFS=,; echo "${FEATURES[*]}")"
@@ -0,0 +1,79 @@
+//! Append-only log.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::hash_table::GrowableArray;
+use crate::reclaim::{unprotected, Guard, Owned};
+
+/// Lock-free append-only log of `T`.
+///
+/// An append reserves the next index with a `fetch_add` and stores the entry in the slot of a
+/// `GrowableArray`. Entries are never removed, and they are deallocated with the log.
+///
+/// Since reserving an index and storing the entry are separate steps, a concurrent reader may
+/// observe a hole at an index whose append is still in progress. [`iter`](AppendLog::iter) only
+/// yields the entries before the first hole.
+#[derive(Debug)]
+pub struct AppendLog<T> {
+    entries: GrowableArray<T>,
+    /// number of reserved indices
+    len: AtomicUsize,
+}
+
+impl<T> Default for AppendLog<T> {
+    fn default() -> Self {
+        Self {
+            entries: GrowableArray::new(),
+            len: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<T> AppendLog<T> {
+    /// Creates a new empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an entry and returns its index.
+    pub fn append(&self, entry: T, guard: &Guard) -> usize {
+        let index = self.len.fetch_add(1, Ordering::Relaxed);
+        self.entries
+            .store(index, Owned::new(entry), Ordering::Release, guard);
+        index
+    }
+
+    /// Returns the number of reserved indices, including the appends in progress.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if nothing was appended.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the entry at `index`, or `None` if its append is not complete.
+    pub fn get<'g>(&'g self, index: usize, guard: &'g Guard) -> Option<&'g T> {
+        let slot = self.entries.try_get(index, guard)?;
+        unsafe { slot.load(Ordering::Acquire, guard).as_ref() }
+    }
+
+    /// Iterates the entries in append order, up to the first append that is not complete.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> impl Iterator<Item = &'g T> + 'g {
+        (0..self.len()).scan((), move |_, index| self.get(index, guard))
+    }
+}
+
+impl<T> Drop for AppendLog<T> {
+    fn drop(&mut self) {
+        let guard = unsafe { unprotected() };
+        for index in 0..*self.len.get_mut() {
+            let slot = some_or!(self.entries.try_get(index, guard), continue);
+            let entry = slot.load(Ordering::Relaxed, guard);
+            if !entry.is_null() {
+                drop(unsafe { entry.into_owned() });
+            }
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! Bounded collections.
+
+/// Fixed-capacity collection that never allocates after construction.
+///
+/// Allocation-free code can be generic over which bounded container (array queue, bounded stack,
+/// SPSC ring, ...) it is given.
+pub trait BoundedCollection<T> {
+    /// Returns the maximum number of elements.
+    fn capacity(&self) -> usize;
+
+    /// Tries to push a value. Returns the value back in `Err` if the collection is full.
+    fn try_push(&self, value: T) -> Result<(), T>;
+
+    /// Tries to pop a value. Returns `None` if the collection is empty.
+    fn try_pop(&self) -> Option<T>;
+
+    /// Returns `true` if the collection is full.
+    fn is_full(&self) -> bool;
+}
@@ -8,12 +8,13 @@
 use core::cmp;
 use core::mem::{self, ManuallyDrop};
 use core::sync::atomic::Ordering;
-use crossbeam_epoch::{unprotected, Atomic, Guard, Owned, Shared};
+use crossbeam_epoch::{Atomic, Owned, Shared};
 use lock::seqlock::{ReadGuard, SeqLock};
 
 mod base;
 
 use crate::map::ConcurrentMap;
+use crate::reclaim::Guard;
 pub use base::Bst;
 use base::{AtomicRW, Cursor, Dir, Node, NodeInner};
 
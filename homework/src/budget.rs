@@ -0,0 +1,78 @@
+//! Memory budgets shared across data structures.
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Error returned when an allocation doesn't fit in a [`MemoryBudget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfBudget;
+
+#[derive(Debug)]
+struct Inner {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+/// Handle to a memory budget, shared by cloning.
+///
+/// Structures created with a budget account for the bytes they allocate (segments, nodes, ...).
+/// Allocations that can fail (e.g. the node of an insertion) are rejected with `OutOfBudget` once
+/// the limit would be exceeded; allocations that can't fail (e.g. internal segments) are charged
+/// unconditionally and may overshoot the limit.
+#[derive(Debug, Clone)]
+pub struct MemoryBudget {
+    inner: Arc<Inner>,
+}
+
+impl MemoryBudget {
+    /// Creates a new budget of `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                limit,
+                used: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Returns the limit in bytes.
+    pub fn limit(&self) -> usize {
+        self.inner.limit
+    }
+
+    /// Returns the number of bytes currently accounted.
+    pub fn used(&self) -> usize {
+        self.inner.used.load(Ordering::Relaxed)
+    }
+
+    /// Reserves `bytes`, or fails if that would exceed the limit.
+    pub fn try_reserve(&self, bytes: usize) -> Result<(), OutOfBudget> {
+        let mut used = self.inner.used.load(Ordering::Relaxed);
+        loop {
+            let new = used.checked_add(bytes).ok_or(OutOfBudget)?;
+            if new > self.inner.limit {
+                return Err(OutOfBudget);
+            }
+
+            match self.inner.used.compare_exchange_weak(
+                used,
+                new,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Ok(()),
+                Err(current) => used = current,
+            }
+        }
+    }
+
+    /// Accounts `bytes` regardless of the limit.
+    pub fn charge(&self, bytes: usize) {
+        self.inner.used.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Returns `bytes` to the budget.
+    pub fn release(&self, bytes: usize) {
+        self.inner.used.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
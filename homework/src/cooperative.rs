@@ -0,0 +1,64 @@
+//! Cooperative yield points for tail-latency control.
+//!
+//! Long internal loops (the recursive initialization of parent buckets, scans over the nodes of a
+//! `SplitOrderedList` or the segments of a `GrowableArray`) call the yield hook every `every`
+//! steps, so that a latency-sensitive runtime can run other work in the middle of a large
+//! operation, e.g. by yielding the thread or polling a local task queue.
+//!
+//! # Example
+//!
+//! ```
+//! use cs492_concur_homework::cooperative;
+//!
+//! cooperative::set_yield_hook(std::thread::yield_now, 256);
+//! ```
+
+use core::mem;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The hook as `fn()` cast to `usize`, or 0 if none.
+static HOOK: AtomicUsize = AtomicUsize::new(0);
+static EVERY: AtomicUsize = AtomicUsize::new(usize::max_value());
+
+/// Installs `hook`, called by the long internal loops every `every` steps. Replaces the
+/// previously installed hook.
+pub fn set_yield_hook(hook: fn(), every: usize) {
+    assert!(every > 0, "yield interval must be positive");
+    HOOK.store(0, Ordering::Release);
+    EVERY.store(every, Ordering::Relaxed);
+    HOOK.store(hook as usize, Ordering::Release);
+}
+
+/// Uninstalls the yield hook.
+pub fn clear_yield_hook() {
+    HOOK.store(0, Ordering::Release);
+}
+
+/// Counts the steps of a loop and calls the yield hook every `every` steps.
+#[derive(Debug, Default)]
+pub(crate) struct YieldPoint {
+    steps: usize,
+}
+
+impl YieldPoint {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a step of the loop.
+    #[inline]
+    pub(crate) fn step(&mut self) {
+        let hook = HOOK.load(Ordering::Acquire);
+        if hook == 0 {
+            return;
+        }
+
+        self.steps += 1;
+        if self.steps >= EVERY.load(Ordering::Relaxed) {
+            self.steps = 0;
+            let hook: fn() = unsafe { mem::transmute(hook) };
+            hook();
+        }
+    }
+}
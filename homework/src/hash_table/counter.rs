@@ -0,0 +1,78 @@
+//! Striped counter.
+
+use alloc::boxed::Box;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_utils::CachePadded;
+
+/// Number of cells of a `StripedCounter`, a power of two.
+const STRIPES: usize = 16;
+
+/// Counter whose updates are spread over cache-padded cells, so that concurrent updates with
+/// different hints don't contend on a single word.
+///
+/// The cells wrap around, so a cell may go "below zero" when a decrement lands there before the
+/// matching increment; only the wrapping sum of all the cells is meaningful.
+#[derive(Debug)]
+pub struct StripedCounter {
+    cells: Box<[CachePadded<AtomicUsize>]>,
+}
+
+impl Default for StripedCounter {
+    fn default() -> Self {
+        Self {
+            cells: (0..STRIPES)
+                .map(|_| CachePadded::new(AtomicUsize::new(0)))
+                .collect(),
+        }
+    }
+}
+
+impl StripedCounter {
+    /// Creates a new counter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the cell of `hint`, picked by Fibonacci hashing so that regular sequences of hints,
+    /// e.g. the even keys, still spread over all the cells.
+    fn cell(&self, hint: usize) -> &AtomicUsize {
+        let hash = hint.wrapping_mul(0x9E37_79B9_7F4A_7C15_u64 as usize);
+        let bits = core::mem::size_of::<usize>() * 8;
+        &self.cells[hash >> (bits - STRIPES.trailing_zeros() as usize)]
+    }
+
+    /// Adds 1 to the cell of `hint`. Returns the estimate of the sum after the update, scaled from
+    /// the cell.
+    pub fn increment(&self, hint: usize) -> usize {
+        let cell = self
+            .cell(hint)
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_add(1);
+        cell.wrapping_mul(STRIPES)
+    }
+
+    /// Subtracts 1 from the cell of `hint`. Returns the estimate of the sum after the update,
+    /// scaled from the cell.
+    pub fn decrement(&self, hint: usize) -> usize {
+        let cell = self
+            .cell(hint)
+            .fetch_sub(1, Ordering::Relaxed)
+            .wrapping_sub(1);
+        cell.wrapping_mul(STRIPES)
+    }
+
+    /// Returns the estimate of the sum scaled from the cell of `hint`. It only reads one cell, and
+    /// is accurate as long as the updates are spread evenly over the cells.
+    pub fn estimate(&self, hint: usize) -> usize {
+        self.cell(hint)
+            .load(Ordering::Relaxed)
+            .wrapping_mul(STRIPES)
+    }
+
+    /// Returns the wrapping sum of the cells. It's exact once the concurrent updates complete.
+    pub fn sum(&self) -> usize {
+        self.cells.iter().fold(0, |sum, cell| {
+            sum.wrapping_add(cell.load(Ordering::Relaxed))
+        })
+    }
+}
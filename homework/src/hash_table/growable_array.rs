@@ -1,12 +1,24 @@
 //! Growable array.
 
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+#[cfg(any(miri, feature = "small-segments"))]
+use arr_macro::arr;
 use core::fmt::Debug;
 use core::marker::PhantomData;
 use core::mem;
-use core::ops::{Deref, DerefMut};
+use core::ops::{Deref, DerefMut, Range};
+use core::ptr;
 use core::sync::atomic::{AtomicUsize, Ordering};
-use crossbeam_epoch::{unprotected, Atomic, Guard, Owned, Pointer, Shared};
-use rand::seq::index::IndexVec::USize;
+use crossbeam_epoch::{unprotected, Atomic, Owned, Pointer as _, Shared};
+#[cfg(feature = "std")]
+use crossbeam_utils::thread::scope;
+use static_assertions::const_assert;
+
+use crate::budget::MemoryBudget;
+use crate::reclaim::{self, unprotected as unprotected_guard, BackendGuard, Epoch, Reclaimer};
+use crate::utils::{Watchdog, YieldPoint};
 
 /// Growable array of `Atomic<T>`.
 ///
@@ -130,23 +142,253 @@ use rand::seq::index::IndexVec::USize;
 /// Instead, it should be handled by the container that the elements actually belong to. For
 /// example in `SplitOrderedList`, destruction of elements are handled by `List`.
 ///
-#[derive(Debug)]
-pub struct GrowableArray<T> {
+pub struct GrowableArray<T, R = Epoch> {
     root: Atomic<Segment>,
-    _marker: PhantomData<T>,
+    /// budget charged for the segments
+    budget: Option<MemoryBudget>,
+    /// unused segments, shared with the deferred retirements
+    pool: Arc<SegmentPool>,
+    counters: Counters,
+    _marker: PhantomData<(T, R)>,
 }
 
+/// Log2 of the number of slots of a segment.
+#[cfg(not(any(miri, feature = "small-segments")))]
 const SEGMENT_LOGSIZE: usize = 10;
+/// Tiny segments for Miri and model checking, where the 8 KiB segments are impractically slow to
+/// initialize and the trees stay too shallow to exercise the growth and shrinking paths.
+#[cfg(any(miri, feature = "small-segments"))]
+const SEGMENT_LOGSIZE: usize = 3;
+
+/// Memory usage statistics of a `GrowableArray`, returned by [`GrowableArray::stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrowableArrayStats {
+    /// Height of the segment tree.
+    pub height: usize,
+    /// Number of allocated segments per level, from the root (index 0) down to the leaves.
+    pub segments_per_level: Vec<usize>,
+    /// Total bytes of segment storage.
+    pub segment_bytes: usize,
+    /// Number of element slots holding a non-null pointer or a nonzero tag.
+    pub occupied_slots: usize,
+}
+
+/// Statistics of the segment pool of a `GrowableArray`, returned by
+/// [`GrowableArray::pool_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentPoolStats {
+    /// Number of segment allocations served from the pool.
+    pub hits: usize,
+    /// Number of segment allocations that went to the allocator.
+    pub misses: usize,
+    /// Number of segments currently in the pool.
+    pub pooled: usize,
+}
+
+impl SegmentPoolStats {
+    /// Returns the fraction of segment allocations served from the pool, or 0 if there was none.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// Counters of the hot paths of a `GrowableArray`, returned by [`GrowableArray::metrics`].
+#[cfg(feature = "instrument")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GrowableArrayMetrics {
+    /// Number of segments allocated by `get` and root growth, including the ones that lost the
+    /// race to be installed.
+    pub segment_allocations: usize,
+    /// Number of failed CASes of the root while growing the tree.
+    pub root_cas_failures: usize,
+    /// Number of failed CASes while installing a child segment.
+    pub child_cas_failures: usize,
+    /// Number of walks from the root to a leaf by `get`.
+    pub traversals: usize,
+    /// Average number of segments visited per walk, including the restarts.
+    pub average_depth: f64,
+}
 
+/// Counters of the hot paths. Without the `instrument` feature, they are empty and updating them
+/// is a no-op.
+#[derive(Debug, Default)]
+struct Counters {
+    #[cfg(feature = "instrument")]
+    segment_allocations: AtomicUsize,
+    #[cfg(feature = "instrument")]
+    root_cas_failures: AtomicUsize,
+    #[cfg(feature = "instrument")]
+    child_cas_failures: AtomicUsize,
+    #[cfg(feature = "instrument")]
+    traversals: AtomicUsize,
+    #[cfg(feature = "instrument")]
+    depth: AtomicUsize,
+}
+
+#[cfg(feature = "instrument")]
+impl Counters {
+    #[inline]
+    fn segment_allocation(&self) {
+        self.segment_allocations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn root_cas_failure(&self) {
+        self.root_cas_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn child_cas_failure(&self) {
+        self.child_cas_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn traversal(&self, depth: usize) {
+        self.traversals.fetch_add(1, Ordering::Relaxed);
+        self.depth.fetch_add(depth, Ordering::Relaxed);
+    }
+
+    fn metrics(&self) -> GrowableArrayMetrics {
+        let traversals = self.traversals.load(Ordering::Relaxed);
+        let depth = self.depth.load(Ordering::Relaxed);
+        GrowableArrayMetrics {
+            segment_allocations: self.segment_allocations.load(Ordering::Relaxed),
+            root_cas_failures: self.root_cas_failures.load(Ordering::Relaxed),
+            child_cas_failures: self.child_cas_failures.load(Ordering::Relaxed),
+            traversals,
+            average_depth: match traversals {
+                0 => 0.0,
+                _ => depth as f64 / traversals as f64,
+            },
+        }
+    }
+}
+
+#[cfg(not(feature = "instrument"))]
+impl Counters {
+    #[inline]
+    fn segment_allocation(&self) {}
+
+    #[inline]
+    fn root_cas_failure(&self) {}
+
+    #[inline]
+    fn child_cas_failure(&self) {}
+
+    #[inline]
+    fn traversal(&self, _depth: usize) {}
+}
+
+/// Aligned to 8 bytes so that the root tag can hold heights up to `MAX_HEIGHT` on 32-bit
+/// targets, too, and to 32 bytes for the taller trees of small segments. `repr(C)` keeps `inner`
+/// at the start of the segment, which `get_many` relies on.
+#[cfg_attr(not(any(miri, feature = "small-segments")), repr(C, align(8)))]
+#[cfg_attr(any(miri, feature = "small-segments"), repr(C, align(32)))]
 struct Segment {
     /// `AtomicUsize` here means `Atomic<T>` or `Atomic<Segment>`.
     inner: [AtomicUsize; 1 << SEGMENT_LOGSIZE],
+    /// Occupancy bitmap for fast scans of sparse segments. Bit `i` is set if slot `i` may be
+    /// non-null: it is set before a slot is handed out or a child segment is installed, and is
+    /// never cleared, so scans must still check the slots.
+    bitmap: [AtomicUsize; BITMAP_WORDS],
+    /// Number of non-null slots. Exact for internal segments, whose slots are only written by the
+    /// array. For leaves, only the updates through the helpers (`store`, `compare_exchange`, ...)
+    /// are counted, and updates through the `Atomic`s handed out by `get` are not, so a zero
+    /// count must be confirmed by a scan.
+    occupied: AtomicUsize,
 }
 
+/// Number of words of the occupancy bitmap of a segment.
+const BITMAP_WORDS: usize = ((1 << SEGMENT_LOGSIZE) + USIZE_SIZE - 1) / USIZE_SIZE;
+
 impl Segment {
+    #[cfg(not(any(miri, feature = "small-segments")))]
     fn new() -> Self {
         Self {
             inner: unsafe { mem::zeroed() },
+            bitmap: unsafe { mem::zeroed() },
+            occupied: AtomicUsize::new(0),
+        }
+    }
+
+    /// Initializes the slots one by one instead of transmuting zeroed memory, so that Miri checks
+    /// the initialization.
+    #[cfg(any(miri, feature = "small-segments"))]
+    fn new() -> Self {
+        Self {
+            inner: arr![AtomicUsize::new(0); 8],
+            bitmap: arr![AtomicUsize::new(0); 1],
+            occupied: AtomicUsize::new(0),
+        }
+    }
+
+    /// Resets the segment to the state of `Segment::new`.
+    fn reset(&mut self) {
+        // SAFETY: all fields are atomic integers, for which all-zero is a valid value.
+        unsafe { ptr::write_bytes(self as *mut Self, 0, 1) };
+    }
+
+    /// Sets the occupancy bit of slot `index`.
+    fn mark(&self, index: usize) {
+        let word = &self.bitmap[index / USIZE_SIZE];
+        let bit = 1 << (index % USIZE_SIZE);
+        if word.load(Ordering::Relaxed) & bit == 0 {
+            word.fetch_or(bit, Ordering::Release);
+        }
+    }
+
+    /// Iterates the slots whose occupancy bit is set, with their indices.
+    fn marked(&self) -> impl Iterator<Item = (usize, &AtomicUsize)> + '_ {
+        self.bitmap.iter().enumerate().flat_map(move |(w, word)| {
+            let mut bits = word.load(Ordering::Acquire);
+            core::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let index = w * USIZE_SIZE + bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some((index, &self.inner[index]))
+            })
+        })
+    }
+
+    /// Returns the number of non-null slots.
+    fn occupied(&self) -> usize {
+        self.marked()
+            .filter(|(_, slot)| slot.load(Ordering::Acquire) != 0)
+            .count()
+    }
+
+    /// Returns `true` if all slots are null. O(1) unless the segment is a leaf with a zero count.
+    ///
+    /// A leaf whose element was stored through a helper but cleared through its `Atomic` is
+    /// reported as non-empty.
+    fn is_empty(&self, height: usize) -> bool {
+        match self.occupied.load(Ordering::Acquire) {
+            0 if height == 1 => self
+                .marked()
+                .all(|(_, slot)| slot.load(Ordering::Acquire) == 0),
+            0 => true,
+            _ => false,
+        }
+    }
+
+    /// Updates the count for a slot changing from `old` to `new`.
+    fn count_update(&self, old: usize, new: usize) {
+        match (old == 0, new == 0) {
+            (true, false) => {
+                self.occupied.fetch_add(1, Ordering::AcqRel);
+            }
+            (false, true) => {
+                // Saturates: the slot may have been filled through its `Atomic`.
+                let _ = self
+                    .occupied
+                    .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1));
+            }
+            _ => {}
         }
     }
 }
@@ -167,138 +409,404 @@ impl DerefMut for Segment {
 
 impl Debug for Segment {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "Segment")
+        f.debug_struct("Segment")
+            .field("occupied", &self.occupied())
+            .finish()
     }
 }
 
-impl<T> Drop for GrowableArray<T> {
-    /// Deallocate segments, but not the individual elements.
-    fn drop(&mut self) {
-        let max_key = (1usize << SEGMENT_LOGSIZE) - 1;
-        let mut stack = vec![];
-        let guard = unsafe { unprotected() };
-        let root =
-            unsafe { self.root.load(Ordering::Relaxed, guard).into_owned() };
+/// Number of segments kept by the pool of a `GrowableArray`.
+const POOL_CAPACITY: usize = 8;
 
-        let root_height  = root.tag();
-        stack.push((root, root_height));
+/// Pool of unused segments: those that lost the race to be installed, and those retired by
+/// `shrink` and `collapse_root`. Allocations are served from the pool before the allocator.
+///
+/// The pool is a fixed array of slots rather than a Treiber stack, so that taking a segment is a
+/// single `swap` that can't suffer from ABA. Segments that don't fit are deallocated.
+struct SegmentPool {
+    /// `Owned<Segment>` as `usize`, or 0 if empty
+    slots: [AtomicUsize; POOL_CAPACITY],
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
 
-        while !stack.is_empty() {
-            let (mut node, height) = stack.pop().unwrap();
+impl SegmentPool {
+    fn new() -> Self {
+        Self {
+            slots: unsafe { mem::zeroed() },
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
 
-            if height == 1 {
-                drop(node);
+    /// Returns an empty segment, from the pool if possible.
+    fn alloc(&self) -> Owned<Segment> {
+        for slot in self.slots.iter() {
+            if slot.load(Ordering::Relaxed) == 0 {
                 continue;
             }
 
-            for x in 0..max_key {
-                let ptr = unsafe {
-                    let ptr = mem::take(&mut node.inner[x]);
-                    Shared::from_usize(ptr.into_inner())
-                };
+            let segment = slot.swap(0, Ordering::Acquire);
+            if segment != 0 {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                let mut segment: Owned<Segment> = unsafe { Owned::from_usize(segment) };
+                segment.reset();
+                return segment;
+            }
+        }
 
-                if !ptr.is_null() {
-                    stack.push((unsafe { ptr.into_owned() }, height - 1));
-                }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        Owned::new(Segment::new())
+    }
+
+    /// Puts a segment that no thread can access back into the pool, or deallocates it if the
+    /// pool is full.
+    fn recycle(&self, segment: Owned<Segment>) {
+        let segment = segment.with_tag(0).into_usize();
+        for slot in self.slots.iter() {
+            if slot.load(Ordering::Relaxed) == 0
+                && slot.compare_and_swap(0, segment, Ordering::Release) == 0
+            {
+                return;
             }
+        }
+
+        drop(unsafe { Owned::<Segment>::from_usize(segment) });
+    }
+
+    fn stats(&self) -> SegmentPoolStats {
+        SegmentPoolStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            pooled: self
+                .slots
+                .iter()
+                .filter(|slot| slot.load(Ordering::Relaxed) != 0)
+                .count(),
+        }
+    }
+}
+
+impl Drop for SegmentPool {
+    fn drop(&mut self) {
+        for slot in self.slots.iter_mut() {
+            let segment = *slot.get_mut();
+            if segment != 0 {
+                drop(unsafe { Owned::<Segment>::from_usize(segment) });
+            }
+        }
+    }
+}
+
+/// Deallocates the segment tree under `root`, whose tag is its height, but not the individual
+/// elements. Returns the number of deallocated segments.
+///
+/// # Safety
+///
+/// No other thread may access the tree.
+unsafe fn destroy_tree(root: Owned<Segment>) -> usize {
+    let mut stack = vec![];
+    let mut freed = 0;
+
+    let root_height  = root.tag();
+    stack.push((root, root_height));
 
+    while !stack.is_empty() {
+        let (node, height) = stack.pop().unwrap();
+
+        freed += 1;
+        if height == 1 {
             drop(node);
+            continue;
+        }
+
+        for (_, slot) in node.marked() {
+            let ptr: Shared<'_, Segment> = Shared::from_usize(slot.load(Ordering::Relaxed));
+
+            if !ptr.is_null() {
+                stack.push((ptr.into_owned(), height - 1));
+            }
+        }
+
+        drop(node);
+    }
+
+    freed
+}
+
+impl<T, R> Drop for GrowableArray<T, R> {
+    /// Deallocate segments, but not the individual elements.
+    fn drop(&mut self) {
+        let guard = unsafe { unprotected() };
+        let root = self.root.load(Ordering::Relaxed, guard);
+        if root.is_null() {
+            return;
         }
+
+        let freed = unsafe { destroy_tree(root.into_owned()) };
+        self.release_segments(freed);
     }
 }
 
-impl<T> Default for GrowableArray<T> {
+impl<T, R: Reclaimer> Debug for GrowableArray<T, R> {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // SAFETY: the segments are only reclaimed by the methods taking `&mut self`, so the dump
+        // is safe under any scheme.
+        let guard = unsafe { R::pin() };
+        writeln!(f, "GrowableArray")?;
+        f.write_str(&self.dump_tree(&guard))
+    }
+
+    /// Without `std` there is no way to pin here; use `dump_tree` instead.
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("GrowableArray { .. }")
+    }
+}
+
+impl<T, R: Reclaimer> Default for GrowableArray<T, R> {
     fn default() -> Self {
-        Self::new()
+        Self::with_reclaimer(None)
     }
 }
 
 // usize::BITS is nightly-only API
 const USIZE_SIZE: usize = mem::size_of::<usize>() * 8;
 
+/// Height of a tree that can represent every `usize` index. The height is stored in the tag of
+/// the root pointer, so it must fit in the alignment bits of `Segment`.
+const MAX_HEIGHT: usize = (USIZE_SIZE + SEGMENT_LOGSIZE - 1) / SEGMENT_LOGSIZE;
+
+const_assert!(MAX_HEIGHT < mem::align_of::<Segment>());
+#[cfg(any(miri, feature = "small-segments"))]
+const_assert!(1 << SEGMENT_LOGSIZE == 8 && BITMAP_WORDS == 1);
+
+/// Returns the largest index representable by a tree of the given height.
+fn max_index(height: usize) -> usize {
+    let bits = SEGMENT_LOGSIZE * height;
+    if bits >= USIZE_SIZE {
+        usize::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+/// Returns the number of slots of a segment at the given height that map to `usize` indices. Only
+/// the root of a tree of height `MAX_HEIGHT` has slots beyond the `usize` range.
+fn slot_count(height: usize) -> usize {
+    let shift = (height - 1) * SEGMENT_LOGSIZE;
+    if shift + SEGMENT_LOGSIZE > USIZE_SIZE {
+        1 << (USIZE_SIZE - shift)
+    } else {
+        1 << SEGMENT_LOGSIZE
+    }
+}
+
+/// Returns the position of `index` in a segment at the given height.
+fn slot_index(index: usize, height: usize) -> usize {
+    (index >> ((height - 1) * SEGMENT_LOGSIZE)) & ((1 << SEGMENT_LOGSIZE) - 1)
+}
+
 impl<T> GrowableArray<T> {
     /// Create a new growable array.
     pub fn new() -> Self {
+        Self::with_reclaimer(None)
+    }
+
+    /// Create a new growable array whose segments are charged to the given budget. Segment
+    /// allocations never fail, so the budget may be overshot.
+    pub fn with_budget(budget: MemoryBudget) -> Self {
+        Self::with_reclaimer(Some(budget))
+    }
+
+    /// Create a new growable array whose tree already has the given height, i.e. that holds the
+    /// indices below `2^(10 * height)` (`2^(3 * height)` with small segments) without replacing
+    /// the root. Only the chain of root segments is allocated (one segment per level); see
+    /// [`GrowableArray::reserve_range`] to also allocate the leaves.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `height` exceeds the height needed for the full `usize` range.
+    pub fn with_height(height: usize) -> Self {
+        assert!(
+            height <= MAX_HEIGHT,
+            "height {} exceeds {}",
+            height,
+            MAX_HEIGHT
+        );
+        let array = Self::new();
+        if height > 0 {
+            // SAFETY: the array is not shared yet.
+            array.reserve(max_index(height), unsafe { unprotected_guard() });
+        }
+        array
+    }
+}
+
+impl<T, R: Reclaimer> GrowableArray<T, R> {
+    /// Create a new growable array under the reclamation scheme `R`, e.g.
+    /// `GrowableArray::<T, Unprotected>::with_reclaimer(None)`, optionally charging the segments
+    /// to a budget.
+    pub fn with_reclaimer(budget: Option<MemoryBudget>) -> Self {
         Self {
             root: Atomic::null(),
+            budget,
+            pool: Arc::new(SegmentPool::new()),
+            counters: Counters::default(),
             _marker: PhantomData,
         }
     }
 
-    /// Returns the reference to the `Atomic` pointer at `index`. Allocates new segments if
-    /// necessary.
-    pub fn get(&self, mut index: usize, guard: &Guard) -> &Atomic<T> {
-        // Ensure tree height
-        let (root, root_height) = loop {
-            let root = self.root.load(Ordering::Acquire, guard);
+    fn charge_segments(&self, count: usize) {
+        if let Some(budget) = &self.budget {
+            budget.charge(count * mem::size_of::<Segment>());
+        }
+    }
+
+    fn release_segments(&self, count: usize) {
+        if let Some(budget) = &self.budget {
+            budget.release(count * mem::size_of::<Segment>());
+        }
+    }
+
+    /// Returns the current height of the segment tree. The empty array has height 0.
+    pub fn height(&self, guard: &R::Guard) -> usize {
+        self.root.load(Ordering::Acquire, guard.backend()).tag()
+    }
+
+    /// Returns the largest index representable without growing the tree, or `None` if the tree
+    /// is empty.
+    pub fn capacity(&self, guard: &R::Guard) -> Option<usize> {
+        match self.height(guard) {
+            0 => None,
+            height => Some(max_index(height)),
+        }
+    }
+
+    /// Grows the tree until `index` is representable. Returns the root and its height.
+    fn grow_root<'g>(&self, index: usize, guard: &'g R::Guard) -> (Shared<'g, Segment>, usize) {
+        let mut watchdog = Watchdog::new("GrowableArray::grow_root");
+        loop {
+            let root = self.root.load(Ordering::Acquire, guard.backend());
             let root_height = root.tag();
-            let max_key =
-                if root_height > 0
-                {
-                    let sr_count = std::cmp::min(
-                        USIZE_SIZE, SEGMENT_LOGSIZE * root_height
-                    );
-                    usize::MAX >> USIZE_SIZE - sr_count
-                } else
-                { 0 };
-
-            if index < max_key {
-                break (root, root_height);
+            if root_height > 0 && index <= max_index(root_height) {
+                return (root, root_height);
             }
+            debug_assert!(root_height < MAX_HEIGHT);
 
-            let mut new_node = Segment::new();
-            new_node.inner[0] = AtomicUsize::new(root.into_usize());
-
-            let owned_ptr = Owned::new(new_node);
+            let mut new_node = self.pool.alloc();
+            self.counters.segment_allocation();
+            // Child pointers are untagged.
+            new_node.inner[0] = AtomicUsize::new(root.with_tag(0).into_usize());
+            new_node.mark(0);
+            if !root.is_null() {
+                new_node.occupied = AtomicUsize::new(1);
+            }
 
             match self.root.compare_and_set(
                 root,
-                owned_ptr.with_tag(root_height + 1),
+                new_node.with_tag(root_height + 1),
                 Ordering::Release,
-                guard
+                guard.backend()
             ) {
                 Err(err) => {
-                    drop(err.new);
+                    self.counters.root_cas_failure();
+                    self.pool.recycle(err.new);
                 }
-                _ => ()
+                _ => self.charge_segments(1),
             };
 
-            continue;
-        };
+            watchdog.tick(|| {
+                format!(
+                    "growing root of height {} for index {:#x}",
+                    root_height, index
+                )
+            });
+        }
+    }
+
+    /// Grows the tree up front so that indices up to `max_index` are representable, so that the
+    /// root doesn't need to be grown by later `get`s. Only the new root segments are allocated; see
+    /// [`GrowableArray::reserve_range`] to also allocate the paths to the indices.
+    pub fn reserve(&self, max_index: usize, guard: &R::Guard) {
+        self.grow_root(max_index, guard);
+    }
+
+    /// Allocates up front all segments on the paths to the indices in `range`, so that later
+    /// `get`s in the range never allocate.
+    pub fn reserve_range(&self, range: Range<usize>, guard: &R::Guard) {
+        if range.start >= range.end {
+            return;
+        }
+
+        self.reserve(range.end - 1, guard);
+        let leaf_mask = (1 << SEGMENT_LOGSIZE) - 1;
+        let mut index = range.start;
+        loop {
+            let _ = self.get(index, guard);
+            index = match (index & !leaf_mask).checked_add(1 << SEGMENT_LOGSIZE) {
+                Some(next) if next < range.end => next,
+                _ => break,
+            };
+        }
+    }
+
+    /// Returns the reference to the `Atomic` pointer at `index`. Allocates new segments if
+    /// necessary.
+    pub fn get(&self, mut index: usize, guard: &R::Guard) -> &reclaim::Atomic<T> {
+        let mut watchdog = Watchdog::new("GrowableArray::get");
+        let mask = (1 << SEGMENT_LOGSIZE) - 1;
+        let mut depth = 0;
+
+        let (root, root_height) = self.grow_root(index, guard);
 
         // Find node
         let mut current_height = root_height;
         let mut node: Atomic<Segment> = Atomic::from(root);
-        let mask = (1 << SEGMENT_LOGSIZE) - 1;
 
         loop {
+            depth += 1;
             let current_index = (index >> ((current_height - 1) * SEGMENT_LOGSIZE)) & mask;
-            let next_node = unsafe {
-                node.load(Ordering::Acquire, guard).deref().get_unchecked(current_index)
-            };
+            let segment = unsafe { node.load(Ordering::Acquire, guard.backend()).deref() };
+            let next_node = unsafe { segment.get_unchecked(current_index) };
 
             let next_usize = next_node.load(Ordering::Acquire);
             let next_ptr = unsafe { Shared::from_usize(next_usize) };
 
             if current_height == 1 {
-                return unsafe { &*(next_node as *const _ as *const Atomic<T>) };
+                segment.mark(current_index);
+                self.counters.traversal(depth);
+                return unsafe { &*(next_node as *const _ as *const reclaim::Atomic<T>) };
             }
 
             if next_ptr.is_null() {
-                let mut new_node = Segment::new();
-                let owned_ptr = Owned::new(new_node);
-                let new_usize = owned_ptr.into_usize();
+                let new_usize = self.pool.alloc().into_usize();
+                self.counters.segment_allocation();
 
+                // Counted before the installation, so that `shrink` never sees a populated
+                // segment as empty.
+                segment.mark(current_index);
+                segment.occupied.fetch_add(1, Ordering::AcqRel);
                 if next_node.compare_and_swap(
                     next_usize,
                     new_usize,
                     Ordering::Release
                 ) == next_usize {
+                    self.charge_segments(1);
                     current_height -= 1;
                     node = unsafe { Atomic::from(Shared::from_usize(new_usize)) };
                 } else {
+                    segment.occupied.fetch_sub(1, Ordering::AcqRel);
+                    self.counters.child_cas_failure();
                     let owned: Owned<Segment> = unsafe { Owned::from_usize(new_usize) };
-                    drop(owned);
+                    self.pool.recycle(owned);
+                    watchdog.tick(|| {
+                        format!(
+                            "installing segment at height {} for index {:#x}",
+                            current_height, index
+                        )
+                    });
                 }
 
                 continue;
@@ -308,4 +816,755 @@ impl<T> GrowableArray<T> {
             node = Atomic::from(next_ptr);
         }
     }
+
+    /// Returns the references to the `Atomic` pointers at `indices`, in input order. Allocates new
+    /// segments if necessary.
+    ///
+    /// The indices are processed in sorted order, and indices in the same leaf segment share a
+    /// single walk from the root.
+    pub fn get_many(&self, indices: &[usize], guard: &R::Guard) -> Vec<&reclaim::Atomic<T>> {
+        let mask = (1 << SEGMENT_LOGSIZE) - 1;
+        let mut order: Vec<usize> = (0..indices.len()).collect();
+        order.sort_unstable_by_key(|&i| indices[i]);
+
+        let mut result: Vec<Option<&reclaim::Atomic<T>>> = vec![None; indices.len()];
+        // (leaf segment number, pointer to the first slot of the leaf segment)
+        let mut leaf: Option<(usize, *const reclaim::Atomic<T>)> = None;
+
+        for i in order {
+            let index = indices[i];
+            let base = match leaf {
+                Some((segment, base)) if segment == index >> SEGMENT_LOGSIZE => base,
+                _ => {
+                    let slot: *const reclaim::Atomic<T> = self.get(index, guard);
+                    let base = unsafe { slot.sub(index & mask) };
+                    leaf = Some((index >> SEGMENT_LOGSIZE, base));
+                    base
+                }
+            };
+            // `get` only marked the slot it walked to.
+            unsafe { (*(base as *const Segment)).mark(index & mask) };
+            result[i] = Some(unsafe { &*base.add(index & mask) });
+        }
+
+        result.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// Stores `new` at `index` like `Atomic::store`. Allocates new segments if necessary.
+    pub fn store<P: reclaim::Pointer<T>>(
+        &self,
+        index: usize,
+        new: P,
+        ord: Ordering,
+        guard: &R::Guard,
+    ) {
+        let slot = self.get(index, guard).as_backend();
+        let new = reclaim::pointer_into_usize(new);
+        let old = slot.swap(
+            unsafe { Shared::<T>::from_usize(new) },
+            ord,
+            guard.backend(),
+        );
+        Self::leaf_of(slot, index).count_update(old.into_usize(), new);
+    }
+
+    /// Returns the leaf segment of the slot at `index` returned by `get`.
+    fn leaf_of(slot: &Atomic<T>, index: usize) -> &Segment {
+        let base = unsafe { (slot as *const Atomic<T>).sub(slot_index(index, 1)) };
+        unsafe { &*(base as *const Segment) }
+    }
+
+    /// Stores `new` at `index` if the current value is the same as `current`, like
+    /// `Atomic::compare_and_set` with the given success and failure orderings. Allocates new
+    /// segments if necessary.
+    pub fn compare_exchange<'g, P: reclaim::Pointer<T>>(
+        &self,
+        index: usize,
+        current: reclaim::Shared<'_, T>,
+        new: P,
+        success: Ordering,
+        failure: Ordering,
+        guard: &'g R::Guard,
+    ) -> Result<reclaim::Shared<'g, T>, reclaim::CompareAndSetError<'g, T, P>> {
+        let slot = self.get(index, guard);
+        let result = slot.compare_and_set(current, new, (success, failure), guard);
+        if let Ok(new) = &result {
+            Self::leaf_of(slot.as_backend(), index).count_update(
+                current.into_backend().into_usize(),
+                new.into_backend().into_usize(),
+            );
+        }
+        result
+    }
+
+    /// Sets the bits of `tag` in the tag of the pointer at `index`, like `Atomic::fetch_or`, and
+    /// returns the previous pointer. Allocates new segments if necessary.
+    ///
+    /// The tag must fit in the unused low bits of `T`'s alignment. A slot holding a null pointer
+    /// with a nonzero tag counts as occupied, so e.g. `shrink` never discards a tag.
+    pub fn fetch_or_tag<'g>(
+        &self,
+        index: usize,
+        tag: usize,
+        ord: Ordering,
+        guard: &'g R::Guard,
+    ) -> reclaim::Shared<'g, T> {
+        debug_assert_eq!(
+            tag & !Self::TAG_MASK,
+            0,
+            "tag doesn't fit in the alignment of T"
+        );
+        let slot = self.get(index, guard);
+        let old = slot.fetch_or(tag, ord, guard);
+        let old_usize = old.into_backend().into_usize();
+        let new = old_usize | (tag & Self::TAG_MASK);
+        Self::leaf_of(slot.as_backend(), index).count_update(old_usize, new);
+        old
+    }
+
+    /// Clears the bits of `tag` in the tag of the pointer at `index`, like `Atomic::fetch_and`,
+    /// and returns the previous pointer. Allocates new segments if necessary.
+    pub fn fetch_clear_tag<'g>(
+        &self,
+        index: usize,
+        tag: usize,
+        ord: Ordering,
+        guard: &'g R::Guard,
+    ) -> reclaim::Shared<'g, T> {
+        debug_assert_eq!(
+            tag & !Self::TAG_MASK,
+            0,
+            "tag doesn't fit in the alignment of T"
+        );
+        let slot = self.get(index, guard);
+        let old = slot.fetch_and(!tag, ord, guard);
+        let old_usize = old.into_backend().into_usize();
+        let new = old_usize & !(tag & Self::TAG_MASK);
+        Self::leaf_of(slot.as_backend(), index).count_update(old_usize, new);
+        old
+    }
+
+    /// Bits available for tags in the element pointers.
+    const TAG_MASK: usize = mem::align_of::<T>() - 1;
+
+    /// Returns the reference to the `Atomic` pointer at `index`, or `None` if a segment on the
+    /// path to it is not allocated. Unlike `get`, never allocates.
+    pub fn try_get(&self, index: usize, guard: &R::Guard) -> Option<&reclaim::Atomic<T>> {
+        let root = self.root.load(Ordering::Acquire, guard.backend());
+        let mut height = root.tag();
+        if index > max_index(height) {
+            return None;
+        }
+
+        let mut node = unsafe { root.as_ref() }?;
+        loop {
+            let slot = unsafe { node.get_unchecked(slot_index(index, height)) };
+            if height == 1 {
+                // The caller may store into the slot.
+                node.mark(slot_index(index, height));
+                return Some(unsafe { &*(slot as *const _ as *const reclaim::Atomic<T>) });
+            }
+
+            let next: Shared<'_, Segment> =
+                unsafe { Shared::from_usize(slot.load(Ordering::Acquire)) };
+            node = unsafe { next.as_ref() }?;
+            height -= 1;
+        }
+    }
+
+    /// Resets the array to the empty state.
+    ///
+    /// The old segment tree is destroyed through the epoch GC. It takes the array exclusively,
+    /// like `shrink`: a slot reference returned by `get` could otherwise outlive its segment. As
+    /// with `drop`, the elements are not deallocated.
+    pub fn clear(&mut self, guard: &R::Guard) {
+        let root = self
+            .root
+            .swap(Shared::null(), Ordering::AcqRel, guard.backend());
+        if root.is_null() {
+            return;
+        }
+
+        let root = root.into_usize();
+        let budget = self.budget.clone();
+        unsafe {
+            R::defer(guard, move || {
+                let freed = destroy_tree(Owned::from_usize(root));
+                if let Some(budget) = budget {
+                    budget.release(freed * mem::size_of::<Segment>());
+                }
+            });
+        }
+    }
+
+    /// Consumes the array and deallocates its segments using up to `num_threads` threads.
+    ///
+    /// The children of the root segment are split evenly across the threads, and each thread
+    /// tears down its subtrees independently. As with `drop`, the elements are not deallocated.
+    #[cfg(feature = "std")]
+    pub fn into_par_drop(self, num_threads: usize) {
+        let guard = unsafe { unprotected() };
+        let root = self.root.swap(Shared::null(), Ordering::Relaxed, guard);
+        if root.is_null() {
+            return;
+        }
+
+        let root = unsafe { root.into_owned() };
+        let height = root.tag();
+        if height == 1 || num_threads <= 1 {
+            let freed = unsafe { destroy_tree(root) };
+            self.release_segments(freed);
+            return;
+        }
+
+        // Detach the children, tagging each with the height of its subtree.
+        let mut children: Vec<_> = root
+            .marked()
+            .filter_map(|(_, slot)| {
+                let ptr: Shared<'_, Segment> = Shared::from_usize(slot.load(Ordering::Relaxed));
+                if ptr.is_null() {
+                    None
+                } else {
+                    Some(Some(unsafe { ptr.into_owned() }.with_tag(height - 1)))
+                }
+            })
+            .collect();
+        drop(root);
+
+        let per_thread = ((children.len() + num_threads - 1) / num_threads).max(1);
+        let freed = scope(|s| {
+            let handles: Vec<_> = children
+                .chunks_mut(per_thread)
+                .map(|chunk| {
+                    s.spawn(move |_| {
+                        chunk
+                            .iter_mut()
+                            .map(|child| unsafe { destroy_tree(child.take().unwrap()) })
+                            .sum::<usize>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .sum::<usize>()
+        })
+        .unwrap();
+
+        self.release_segments(freed + 1);
+    }
+
+    /// Returns the smallest index at or after `from` whose slot holds a non-null pointer or a
+    /// nonzero tag, or `None` if there is none. Unallocated subtrees and the slots that were never
+    /// handed out are skipped without being visited.
+    ///
+    /// As with any search over concurrently modified slots, the result may be outdated as soon as
+    /// it is returned.
+    pub fn first_occupied(&self, from: usize, guard: &R::Guard) -> Option<usize> {
+        let root = self.root.load(Ordering::Acquire, guard.backend());
+        let height = root.tag();
+        if from > max_index(height) {
+            return None;
+        }
+        let root_ref = unsafe { root.as_ref() }?;
+        Self::first_occupied_in(root_ref, height, 0, from)
+    }
+
+    fn first_occupied_in(
+        segment: &Segment,
+        height: usize,
+        base: usize,
+        from: usize,
+    ) -> Option<usize> {
+        let shift = (height - 1) * SEGMENT_LOGSIZE;
+        let count = slot_count(height);
+        for (i, slot) in segment.marked().take_while(|(i, _)| *i < count) {
+            let lo = base | i << shift;
+            let hi = lo | ((1 << shift) - 1);
+            if hi < from {
+                continue;
+            }
+
+            let ptr = slot.load(Ordering::Acquire);
+            if height == 1 {
+                if ptr != 0 {
+                    return Some(lo);
+                }
+                continue;
+            }
+
+            let child: Shared<'_, Segment> = unsafe { Shared::from_usize(ptr) };
+            let child_ref = some_or!(unsafe { child.as_ref() }, continue);
+            if let Some(index) = Self::first_occupied_in(child_ref, height - 1, lo, from) {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    /// Returns the smallest index at or after `from` whose slot is null (and untagged), or `None`
+    /// if all slots from `from` up to `usize::MAX` are occupied. Unallocated subtrees are
+    /// reported without being visited, so the array can serve as an index allocator.
+    ///
+    /// As with any search over concurrently modified slots, the result may be outdated as soon as
+    /// it is returned, e.g. an allocator must still claim the slot with `compare_exchange`.
+    pub fn first_null(&self, from: usize, guard: &R::Guard) -> Option<usize> {
+        let root = self.root.load(Ordering::Acquire, guard.backend());
+        let height = root.tag();
+        if from > max_index(height) {
+            return Some(from);
+        }
+        let root_ref = some_or!(unsafe { root.as_ref() }, return Some(from));
+        Self::first_null_in(root_ref, height, 0, from).or_else(|| max_index(height).checked_add(1))
+    }
+
+    fn first_null_in(segment: &Segment, height: usize, base: usize, from: usize) -> Option<usize> {
+        let shift = (height - 1) * SEGMENT_LOGSIZE;
+        // `from` is in the subtree of `segment`.
+        let start = if from > base {
+            slot_index(from, height)
+        } else {
+            0
+        };
+        for i in start..slot_count(height) {
+            let lo = base | i << shift;
+            let ptr = segment[i].load(Ordering::Acquire);
+            if height == 1 {
+                if ptr == 0 {
+                    return Some(lo);
+                }
+                continue;
+            }
+
+            let child: Shared<'_, Segment> = unsafe { Shared::from_usize(ptr) };
+            let child_ref = some_or!(unsafe { child.as_ref() }, return Some(lo.max(from)));
+            if let Some(index) = Self::first_null_in(child_ref, height - 1, lo, from) {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    /// Returns the counters of the hot paths since the array was created.
+    #[cfg(feature = "instrument")]
+    pub fn metrics(&self) -> GrowableArrayMetrics {
+        self.counters.metrics()
+    }
+
+    /// Returns the statistics of the pool of unused segments. Pooled segments are not counted by
+    /// [`GrowableArray::stats`] nor charged to the budget.
+    pub fn pool_stats(&self) -> SegmentPoolStats {
+        self.pool.stats()
+    }
+
+    /// Collects memory usage statistics by walking the segment tree. The result is not a
+    /// consistent snapshot if the array is modified concurrently.
+    pub fn stats(&self, guard: &R::Guard) -> GrowableArrayStats {
+        let root = self.root.load(Ordering::Acquire, guard.backend());
+        let mut stats = GrowableArrayStats {
+            height: root.tag(),
+            segments_per_level: vec![0; root.tag()],
+            segment_bytes: 0,
+            occupied_slots: 0,
+        };
+
+        if let Some(root_ref) = unsafe { root.as_ref() } {
+            Self::stats_segment(&mut stats, root_ref, root.tag());
+        }
+        stats
+    }
+
+    fn stats_segment(stats: &mut GrowableArrayStats, segment: &Segment, height: usize) {
+        stats.segments_per_level[stats.height - height] += 1;
+        stats.segment_bytes += mem::size_of::<Segment>();
+        if height > 1 && segment.is_empty(height) {
+            return;
+        }
+
+        for (_, slot) in segment.marked() {
+            let ptr = slot.load(Ordering::Acquire);
+            if height == 1 {
+                if ptr != 0 {
+                    stats.occupied_slots += 1;
+                }
+                continue;
+            }
+
+            let child: Shared<'_, Segment> = unsafe { Shared::from_usize(ptr) };
+            let child_ref = some_or!(unsafe { child.as_ref() }, continue);
+            Self::stats_segment(stats, child_ref, height - 1);
+        }
+    }
+
+    /// Collects the non-null elements with their indices, in increasing order of index, so that
+    /// the result can be post-processed without holding a guard. The tags of the element pointers
+    /// are stripped; see `snapshot_tagged` to keep them. The result is not a consistent snapshot
+    /// if the array is modified concurrently.
+    ///
+    /// The pointers are only valid as long as the elements are: the array doesn't own them, so
+    /// it's up to the caller to keep them alive (e.g. by not removing them meanwhile).
+    pub fn snapshot(&self, guard: &R::Guard) -> Vec<(usize, *const T)> {
+        self.snapshot_tagged(guard)
+            .into_iter()
+            .filter(|(_, ptr, _)| !ptr.is_null())
+            .map(|(index, ptr, _)| (index, ptr))
+            .collect()
+    }
+
+    /// Like `snapshot`, but collects the occupied slots as `(index, pointer, tag)`, including the
+    /// slots holding a null pointer with a nonzero tag.
+    pub fn snapshot_tagged(&self, guard: &R::Guard) -> Vec<(usize, *const T, usize)> {
+        let root = self.root.load(Ordering::Acquire, guard.backend());
+        let mut out = Vec::new();
+        if let Some(root_ref) = unsafe { root.as_ref() } {
+            Self::snapshot_segment(&mut out, root_ref, root.tag(), 0, &mut YieldPoint::new());
+        }
+        out
+    }
+
+    fn snapshot_segment(
+        out: &mut Vec<(usize, *const T, usize)>,
+        segment: &Segment,
+        height: usize,
+        base: usize,
+        yield_point: &mut YieldPoint,
+    ) {
+        yield_point.step();
+        for (i, slot) in segment.marked() {
+            let ptr = slot.load(Ordering::Acquire);
+            let index = base | i << ((height - 1) * SEGMENT_LOGSIZE);
+            if height == 1 {
+                if ptr != 0 {
+                    let elem: Shared<'_, T> = unsafe { Shared::from_usize(ptr) };
+                    out.push((index, elem.as_raw(), elem.tag()));
+                }
+                continue;
+            }
+
+            let child: Shared<'_, Segment> = unsafe { Shared::from_usize(ptr) };
+            let child_ref = some_or!(unsafe { child.as_ref() }, continue);
+            Self::snapshot_segment(out, child_ref, height - 1, index, yield_point);
+        }
+    }
+
+    /// Copies the segment tree into a new array, mapping each non-null (or tagged) element pointer
+    /// through `f`, e.g. to share the elements between the arrays or to copy them. The new array
+    /// charges its segments to the same budget as this one.
+    ///
+    /// Slots for which `f` returns null (and untagged) are left out, but their segments are still
+    /// copied. The copy is not a consistent snapshot if the array is modified concurrently.
+    pub fn clone_with<'g, F>(&self, guard: &'g R::Guard, mut f: F) -> Self
+    where
+        F: FnMut(reclaim::Shared<'g, T>) -> reclaim::Shared<'g, T>,
+    {
+        let clone = Self::with_reclaimer(self.budget.clone());
+        let root = self.root.load(Ordering::Acquire, guard.backend());
+        if let Some(root_ref) = unsafe { root.as_ref() } {
+            let mut copied = 0;
+            let new_root = Self::clone_segment(root_ref, root.tag(), &mut f, &mut copied);
+            clone.charge_segments(copied);
+            clone
+                .root
+                .store(new_root.with_tag(root.tag()), Ordering::Relaxed);
+        }
+        clone
+    }
+
+    fn clone_segment<'g, F>(
+        segment: &Segment,
+        height: usize,
+        f: &mut F,
+        copied: &mut usize,
+    ) -> Owned<Segment>
+    where
+        F: FnMut(reclaim::Shared<'g, T>) -> reclaim::Shared<'g, T>,
+    {
+        let mut new_segment = Owned::new(Segment::new());
+        *copied += 1;
+        for (i, slot) in segment.marked() {
+            let ptr = slot.load(Ordering::Acquire);
+            let new_ptr = if height == 1 {
+                if ptr == 0 {
+                    continue;
+                }
+                let elem = reclaim::Shared::from_backend(unsafe { Shared::from_usize(ptr) });
+                f(elem).into_backend().into_usize()
+            } else {
+                let child: Shared<'_, Segment> = unsafe { Shared::from_usize(ptr) };
+                let child_ref = some_or!(unsafe { child.as_ref() }, continue);
+                Self::clone_segment(child_ref, height - 1, f, copied).into_usize()
+            };
+
+            if new_ptr != 0 {
+                new_segment.mark(i);
+                new_segment.inner[i] = AtomicUsize::new(new_ptr);
+                *new_segment.occupied.get_mut() += 1;
+            }
+        }
+        new_segment
+    }
+
+    /// Iterates the slots holding a non-null pointer or a nonzero tag whose indices are in
+    /// `range`, in increasing order of index. The subtrees outside the range are skipped without
+    /// being visited.
+    ///
+    /// The slots are collected up front, so the result is not a consistent snapshot if the array
+    /// is modified concurrently.
+    pub fn range<'g>(
+        &'g self,
+        range: Range<usize>,
+        guard: &'g R::Guard,
+    ) -> impl Iterator<Item = (usize, reclaim::Shared<'g, T>)> + 'g {
+        let root = self.root.load(Ordering::Acquire, guard.backend());
+        let mut out = Vec::new();
+        if range.start < range.end {
+            if let Some(root_ref) = unsafe { root.as_ref() } {
+                Self::range_segment(&mut out, root_ref, root.tag(), 0, &range);
+            }
+        }
+        out.into_iter()
+    }
+
+    fn range_segment<'g>(
+        out: &mut Vec<(usize, reclaim::Shared<'g, T>)>,
+        segment: &Segment,
+        height: usize,
+        base: usize,
+        range: &Range<usize>,
+    ) {
+        let shift = (height - 1) * SEGMENT_LOGSIZE;
+        let count = slot_count(height);
+        for (i, slot) in segment.marked().take_while(|(i, _)| *i < count) {
+            let lo = base | i << shift;
+            let hi = lo | ((1 << shift) - 1);
+            if hi < range.start {
+                continue;
+            }
+            if lo >= range.end {
+                break;
+            }
+
+            let ptr = slot.load(Ordering::Acquire);
+            if height == 1 {
+                if ptr != 0 {
+                    let elem = unsafe { Shared::from_usize(ptr) };
+                    out.push((lo, reclaim::Shared::from_backend(elem)));
+                }
+                continue;
+            }
+
+            let child: Shared<'_, Segment> = unsafe { Shared::from_usize(ptr) };
+            let child_ref = some_or!(unsafe { child.as_ref() }, continue);
+            Self::range_segment(out, child_ref, height - 1, lo, range);
+        }
+    }
+
+    /// Renders the structure of the segment tree for debugging, one line per segment. Each line
+    /// shows the height of the segment, the number of occupied slots, and the index of the slot
+    /// of the parent segment the segment hangs from (in binary, as in the documentation of
+    /// [`GrowableArray`]). The result is not a consistent snapshot if the array is modified
+    /// concurrently.
+    ///
+    /// ```text
+    /// height: 2
+    /// root: height 2, 2/1024 occupied
+    ///   [0000000000]: height 1, 1/1024 occupied
+    ///   [0000000011]: height 1, 5/1024 occupied
+    /// ```
+    pub fn dump_tree(&self, guard: &R::Guard) -> String {
+        use core::fmt::Write;
+
+        let root = self.root.load(Ordering::Acquire, guard.backend());
+        let mut out = String::new();
+        writeln!(out, "height: {}", root.tag()).unwrap();
+
+        if let Some(root_ref) = unsafe { root.as_ref() } {
+            write!(out, "root").unwrap();
+            Self::dump_segment(&mut out, root_ref, root.tag(), 0);
+        }
+        out
+    }
+
+    fn dump_segment(out: &mut String, segment: &Segment, height: usize, depth: usize) {
+        use core::fmt::Write;
+
+        writeln!(
+            out,
+            ": height {}, {}/{} occupied",
+            height,
+            segment.occupied(),
+            segment.len()
+        )
+        .unwrap();
+        if height == 1 {
+            return;
+        }
+
+        for (i, slot) in segment.marked() {
+            let child: Shared<'_, Segment> =
+                unsafe { Shared::from_usize(slot.load(Ordering::Acquire)) };
+            let child_ref = some_or!(unsafe { child.as_ref() }, continue);
+
+            let indent = "  ".repeat(depth + 1);
+            write!(out, "{}[{:0width$b}]", indent, i, width = SEGMENT_LOGSIZE).unwrap();
+            Self::dump_segment(out, child_ref, height - 1, depth + 1);
+        }
+    }
+
+    /// Lowers the tree by one level if the root segment has no populated child other than the one
+    /// at slot 0, i.e. if only the indices of the lower level are in use. Returns `true` if the
+    /// root was collapsed. Call it repeatedly to lower the tree by several levels.
+    ///
+    /// It takes the array exclusively, like `shrink`: a slot reference returned by `get` could
+    /// otherwise outlive the destroyed root segment.
+    pub fn collapse_root(&mut self, guard: &R::Guard) -> bool {
+        let root = self.root.load(Ordering::Acquire, guard.backend());
+        let height = root.tag();
+        let root_ref = some_or!(unsafe { root.as_ref() }, return false);
+        if height <= 1 {
+            return false;
+        }
+
+        if root_ref
+            .marked()
+            .any(|(i, slot)| i != 0 && slot.load(Ordering::Acquire) != 0)
+        {
+            return false;
+        }
+
+        let child: Shared<'_, Segment> =
+            unsafe { Shared::from_usize(root_ref[0].load(Ordering::Acquire)) };
+        if child.is_null() {
+            return false;
+        }
+        self.root
+            .store(child.with_tag(height - 1), Ordering::Release);
+
+        // Only the root segment itself is destroyed: the child is now the root.
+        let root = root.into_usize();
+        let pool = self.pool.clone();
+        unsafe { R::defer(guard, move || pool.recycle(Owned::from_usize(root))) };
+        self.release_segments(1);
+        true
+    }
+
+    /// Unlinks the segments whose slots are all null and retires them through the epoch GC.
+    /// Returns the number of retired segments. The root segment is never retired. A slot holding
+    /// a null pointer with a nonzero tag is not null, so its segment is kept.
+    ///
+    /// It takes the array exclusively: a slot reference returned by `get` before the unlink
+    /// could otherwise still be stored into, and the store would be lost with its segment.
+    pub fn shrink(&mut self, guard: &R::Guard) -> usize {
+        let root = self.root.load(Ordering::Acquire, guard.backend());
+        let root_ref = some_or!(unsafe { root.as_ref() }, return 0);
+        let retired = Self::shrink_segment(
+            root_ref,
+            root.tag(),
+            &self.pool,
+            &mut YieldPoint::new(),
+            guard,
+        );
+        self.release_segments(retired);
+        retired
+    }
+
+    fn shrink_segment(
+        segment: &Segment,
+        height: usize,
+        pool: &Arc<SegmentPool>,
+        yield_point: &mut YieldPoint,
+        guard: &R::Guard,
+    ) -> usize {
+        yield_point.step();
+        if height == 1 {
+            return 0;
+        }
+
+        let mut retired = 0;
+        for (_, slot) in segment.marked() {
+            let child_usize = slot.load(Ordering::Acquire);
+            let child: Shared<'_, Segment> = unsafe { Shared::from_usize(child_usize) };
+            let child_ref = some_or!(unsafe { child.as_ref() }, continue);
+
+            retired += Self::shrink_segment(child_ref, height - 1, pool, yield_point, guard);
+            if !child_ref.is_empty(height - 1) {
+                continue;
+            }
+
+            slot.store(0, Ordering::Release);
+            segment.occupied.fetch_sub(1, Ordering::AcqRel);
+            let pool = pool.clone();
+            unsafe { R::defer(guard, move || pool.recycle(Owned::from_usize(child_usize))) };
+            retired += 1;
+        }
+
+        retired
+    }
+}
+
+#[cfg(feature = "teaching")]
+impl<T, R: Reclaimer> GrowableArray<T, R> {
+    /// Renders the current shape of the segment tree as ASCII art, in the style of the diagrams
+    /// in the documentation of [`GrowableArray`]. Only the occupied slots are drawn.
+    pub fn visualize(&self, guard: &R::Guard) -> String {
+        use core::fmt::Write;
+
+        let root = self.root.load(Ordering::Acquire, guard.backend());
+        let mut out = String::new();
+        writeln!(out, "+----+").unwrap();
+        writeln!(out, "|root|").unwrap();
+        writeln!(out, "+----+").unwrap();
+
+        let root_ref = some_or!(unsafe { root.as_ref() }, return out);
+        writeln!(out, "  | height: {}", root.tag()).unwrap();
+        writeln!(out, "  v").unwrap();
+        Self::visualize_segment(&mut out, root_ref, root.tag(), 0);
+        out
+    }
+
+    fn visualize_segment(out: &mut String, segment: &Segment, height: usize, depth: usize) {
+        use core::fmt::Write;
+
+        let indent = "    ".repeat(depth);
+        writeln!(
+            out,
+            "{}segment ({}/{} occupied)",
+            indent,
+            segment.occupied(),
+            segment.len()
+        )
+        .unwrap();
+
+        for (i, slot) in segment.marked() {
+            let ptr = slot.load(Ordering::Acquire);
+            if ptr == 0 {
+                continue;
+            }
+
+            if height == 1 {
+                write!(
+                    out,
+                    "{}|{:0width$b}| -> ",
+                    indent,
+                    i,
+                    width = SEGMENT_LOGSIZE
+                )
+                .unwrap();
+                writeln!(out, "{:#x}", ptr).unwrap();
+            } else {
+                let child: Shared<'_, Segment> = unsafe { Shared::from_usize(ptr) };
+                let child_ref = some_or!(unsafe { child.as_ref() }, continue);
+                write!(
+                    out,
+                    "{}|{:0width$b}| -> ",
+                    indent,
+                    i,
+                    width = SEGMENT_LOGSIZE
+                )
+                .unwrap();
+                writeln!(out).unwrap();
+                Self::visualize_segment(out, child_ref, height - 1, depth + 1);
+            }
+        }
+    }
 }
@@ -0,0 +1,381 @@
+//! Split-ordered list protected by hazard pointers.
+
+use core::cmp::Ordering::{Equal, Greater, Less};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::cell::Cell;
+
+use super::counter::StripedCounter;
+use super::growable_array::GrowableArray;
+use super::split_ordered_list::SoKey;
+use crate::hazard_pointer::{self, Atomic, Owned, Shared, Shield};
+use crate::reclaim::{self, Unprotected, UnprotectedGuard};
+
+thread_local! {
+    /// Number of nodes the current thread retired since it last collected.
+    static RETIRED: Cell<usize> = Cell::new(0);
+}
+
+/// Lock-free map from `usize` to `V` like [`SplitOrderedList`](crate::SplitOrderedList), but
+/// protected by the hazard pointers of [`hazard_pointer`](crate::hazard_pointer) instead of
+/// epochs, for comparing the reclamation schemes.
+///
+/// The list is Michael's variant of Harris's list: a traversal unlinks each marked node it meets
+/// before moving on, since it can't protect a chain of marked nodes with a bounded number of
+/// hazard pointers. The buckets are a `GrowableArray` without reclamation, as the sentinel nodes
+/// are never removed.
+///
+/// The operations return clones of the values: a reference would only be valid while the hazard
+/// pointer protecting the node is held.
+///
+/// The deleted nodes are freed by the thread that unlinked them, when it collects its retired
+/// pointers, which may be after the list is dropped. Hence `V: 'static`.
+///
+/// # Panics
+///
+/// Each operation protects up to two nodes at a time with the current thread's hazard pointers,
+/// of which a thread has 8. The operations panic if the thread holds more than 6 shields of its
+/// own, e.g. by calling them while traversing another hazard-pointer structure.
+#[derive(Debug)]
+pub struct HpSplitOrderedList<V: 'static> {
+    /// pointers to the sentinel nodes. The sentinel of bucket 0 is the head of the list.
+    buckets: GrowableArray<Node<V>, Unprotected>,
+    /// number of buckets, doubled when `count > size * LOAD_FACTOR`
+    size: AtomicUsize,
+    /// number of items, striped by key
+    count: StripedCounter,
+}
+
+#[derive(Debug)]
+struct Node<V> {
+    /// Mark: tag 1
+    next: Atomic<Node<V>>,
+    key: SoKey,
+    /// `None` for the sentinel nodes
+    value: Option<V>,
+}
+
+/// Position of a key in the list, found by `HpSplitOrderedList::find`.
+struct Position<V> {
+    found: bool,
+    /// `next` field of the predecessor of `curr`, protected by `_prev_shield` unless it's a
+    /// sentinel
+    prev: *const Atomic<Node<V>>,
+    /// first node whose key is at least the searched key, protected by `_curr_shield` unless it's
+    /// null
+    curr: Shared<Node<V>>,
+    _prev_shield: Option<Shield<'static, Node<V>>>,
+    _curr_shield: Option<Shield<'static, Node<V>>>,
+}
+
+impl<V: 'static> Default for HpSplitOrderedList<V> {
+    fn default() -> Self {
+        let list = Self {
+            buckets: GrowableArray::with_reclaimer(None),
+            size: AtomicUsize::new(Self::DEFAULT_BUCKETS),
+            count: StripedCounter::new(),
+        };
+        let head = Owned::new(Node::new(SoKey::sentinel(0), None)).into_shared();
+        list.buckets.store(
+            0,
+            Self::to_bucket(head),
+            Ordering::Relaxed,
+            &Self::buckets_guard(),
+        );
+        list
+    }
+}
+
+impl<V: 'static> Drop for HpSplitOrderedList<V> {
+    fn drop(&mut self) {
+        // The unlinked nodes are in the retired lists, and the rest are linked from the head.
+        let mut curr = self.bucket(0).unwrap();
+        while !curr.is_null() {
+            let next = unsafe { curr.deref() }.next.load(Ordering::Relaxed);
+            drop(unsafe { curr.with_tag(0).into_owned() });
+            curr = next.with_tag(0);
+        }
+
+        // Frees the nodes this thread retired that are no longer protected. Those retired by the
+        // other threads are freed when they collect.
+        hazard_pointer::collect();
+    }
+}
+
+impl<V> Node<V> {
+    fn new(key: SoKey, value: Option<V>) -> Self {
+        Self {
+            next: Atomic::null(),
+            key,
+            value,
+        }
+    }
+}
+
+impl<V: 'static> HpSplitOrderedList<V> {
+    const LOAD_FACTOR: usize = 2;
+    const DEFAULT_BUCKETS: usize = 2;
+    /// Number of retirements after which a thread collects its retired nodes.
+    const COLLECT_THRESHOLD: usize = 64;
+
+    /// Creates a new split ordered list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of buckets.
+    pub fn bucket_count(&self) -> usize {
+        self.size.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of entries, under the same caveat as `SplitOrderedList::len`.
+    pub fn len(&self) -> usize {
+        let count = self.count.sum();
+        if count > isize::max_value() as usize {
+            0
+        } else {
+            count
+        }
+    }
+
+    /// Returns `true` if the map has no entries, under the same caveat as `len`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a clone of the value of `key`.
+    pub fn lookup(&self, key: usize) -> Option<V>
+    where
+        V: Clone,
+    {
+        let position = self.find_key(key);
+        if !position.found {
+            return None;
+        }
+        unsafe { position.curr.deref() }.value.clone()
+    }
+
+    /// Inserts a key-value pair. Returns the value back if the key is already in the map.
+    pub fn insert(&self, key: usize, value: V) -> Result<(), V> {
+        let so_key = SoKey::regular(key);
+        let mut node = Owned::new(Node::new(so_key, Some(value)));
+        let size = loop {
+            let size = self.size.load(Ordering::Acquire);
+            let position = self.find(self.lookup_bucket(key % size), &so_key);
+            if position.found {
+                let raw = node.into_shared().into_usize() as *mut Node<V>;
+                return Err(unsafe { Box::from_raw(raw) }.value.unwrap());
+            }
+
+            node.next.store(position.curr, Ordering::Relaxed);
+            let shared = node.into_shared();
+            match unsafe { &*position.prev }.compare_and_set(
+                position.curr,
+                shared,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(()) => break size,
+                Err(_) => node = unsafe { shared.into_owned() },
+            }
+        };
+
+        if self.count.increment(key) > size * Self::LOAD_FACTOR
+            && self.len().saturating_sub(1) > size * Self::LOAD_FACTOR
+        {
+            self.size
+                .compare_and_swap(size, size * 2, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Deletes the key and returns a clone of its value.
+    pub fn delete(&self, key: usize) -> Result<V, ()>
+    where
+        V: Clone,
+    {
+        loop {
+            let position = self.find_key(key);
+            if !position.found {
+                return Err(());
+            }
+
+            let node = unsafe { position.curr.deref() };
+            let next = node.next.fetch_or(1, Ordering::AcqRel);
+            if next.tag() != 0 {
+                // Deleted concurrently. The next search unlinks it.
+                continue;
+            }
+            let value = node.value.clone().unwrap();
+
+            if unsafe { &*position.prev }
+                .compare_and_set(position.curr, next, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                let curr = position.curr;
+                drop(position);
+                Self::retire(curr);
+            } else {
+                drop(position);
+                // Unlinks the node.
+                let _ = self.find_key(key);
+            }
+
+            let _ = self.count.decrement(key);
+            return Ok(value);
+        }
+    }
+
+    /// Returns the guard of the bucket array.
+    fn buckets_guard() -> UnprotectedGuard {
+        // SAFETY: the sentinels are never removed, and the array is never shrunk.
+        unsafe { UnprotectedGuard::new() }
+    }
+
+    /// Returns the sentinel node of bucket `index` if it's initialized.
+    fn bucket(&self, index: usize) -> Option<Shared<Node<V>>> {
+        let guard = Self::buckets_guard();
+        let bucket = self
+            .buckets
+            .try_get(index, &guard)?
+            .load(Ordering::Acquire, &guard);
+        if bucket.is_null() {
+            None
+        } else {
+            Some(Shared::from_usize(bucket.as_raw() as usize))
+        }
+    }
+
+    fn to_bucket<'g>(node: Shared<Node<V>>) -> reclaim::Shared<'g, Node<V>> {
+        reclaim::Shared::from(node.into_usize() as *const Node<V>)
+    }
+
+    /// Returns the sentinel node of bucket `index`, initializing it and its ancestors if
+    /// necessary. The sentinel nodes are never freed while the list is alive.
+    fn lookup_bucket(&self, index: usize) -> Shared<Node<V>> {
+        if let Some(bucket) = self.bucket(index) {
+            return bucket;
+        }
+
+        // The parent of a bucket is the bucket with its most significant set bit cleared.
+        let mut msb = 1;
+        while msb <= index >> 1 {
+            msb <<= 1;
+        }
+        let parent = self.lookup_bucket(index - msb);
+
+        let so_key = SoKey::sentinel(index);
+        let mut node = Owned::new(Node::new(so_key, None));
+        let sentinel = loop {
+            let position = self.find(parent, &so_key);
+            if position.found {
+                drop(node);
+                break position.curr;
+            }
+
+            node.next.store(position.curr, Ordering::Relaxed);
+            let shared = node.into_shared();
+            match unsafe { &*position.prev }.compare_and_set(
+                position.curr,
+                shared,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(()) => break shared,
+                Err(_) => node = unsafe { shared.into_owned() },
+            }
+        };
+
+        // Publish the sentinel. If another thread already did, it published the same node.
+        let _ = self.buckets.compare_exchange(
+            index,
+            reclaim::Shared::null(),
+            Self::to_bucket(sentinel),
+            Ordering::Release,
+            Ordering::Relaxed,
+            &Self::buckets_guard(),
+        );
+        sentinel
+    }
+
+    /// Finds the position of the regular node of `key`.
+    fn find_key(&self, key: usize) -> Position<V> {
+        let size = self.size.load(Ordering::Acquire);
+        self.find(self.lookup_bucket(key % size), &SoKey::regular(key))
+    }
+
+    /// Finds the position of `key` after the sentinel node `start`, unlinking and retiring the
+    /// marked nodes on the way.
+    fn find(&self, start: Shared<Node<V>>, key: &SoKey) -> Position<V> {
+        'retry: loop {
+            let mut prev: *const Atomic<Node<V>> = &unsafe { start.deref() }.next;
+            let mut prev_shield = None;
+            let mut curr = unsafe { &*prev }.load(Ordering::Acquire);
+
+            loop {
+                if curr.is_null() {
+                    return Position {
+                        found: false,
+                        prev,
+                        curr,
+                        _prev_shield: prev_shield,
+                        _curr_shield: None,
+                    };
+                }
+
+                let curr_shield = hazard_pointer::protect(curr)
+                    .expect("more than 6 hazard pointers of the thread are in use");
+                if unsafe { &*prev }.load(Ordering::Acquire).into_usize() != curr.into_usize() {
+                    continue 'retry;
+                }
+
+                let curr_node = unsafe { curr_shield.deref() };
+                let next = curr_node.next.load(Ordering::Acquire);
+                if next.tag() != 0 {
+                    let next = next.with_tag(0);
+                    if unsafe { &*prev }
+                        .compare_and_set(curr, next, Ordering::Release, Ordering::Relaxed)
+                        .is_err()
+                    {
+                        continue 'retry;
+                    }
+                    drop(curr_shield);
+                    Self::retire(curr);
+                    curr = next;
+                    continue;
+                }
+
+                match curr_node.key.cmp(key) {
+                    Less => {
+                        prev = &curr_node.next;
+                        prev_shield = Some(curr_shield);
+                        curr = next;
+                    }
+                    Equal | Greater => {
+                        return Position {
+                            found: curr_node.key == *key,
+                            prev,
+                            curr,
+                            _prev_shield: prev_shield,
+                            _curr_shield: Some(curr_shield),
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    /// Retires an unlinked node, and collects the current thread's retired nodes every
+    /// `COLLECT_THRESHOLD` retirements.
+    fn retire(node: Shared<Node<V>>) {
+        hazard_pointer::retire(node);
+        RETIRED.with(|retired| {
+            let count = retired.get() + 1;
+            if count < Self::COLLECT_THRESHOLD {
+                retired.set(count);
+            } else {
+                retired.set(0);
+                hazard_pointer::collect();
+            }
+        });
+    }
+}
@@ -0,0 +1,12 @@
+//! The ordered list backing the split-ordered structures.
+//!
+//! `SplitOrderedList`, `SplitOrderedHashMap` and `SplitOrderedSet` consume the list only through
+//! the `Cursor` API re-exported here (`head`, `find_harris`, `insert`, `delete`, `lookup`, `curr`,
+//! `prev`, `from_raw`) and the `Node` accessors, so a different Harris list of the same shape can
+//! be plugged in by re-exporting it from this module.
+//!
+//! There is no feature selecting another list: this crate has no Harris list of its own.
+//! `OrderedListSet` is lock-coupled and `LinkedList` is sequential, neither of which can mark and
+//! unlink nodes concurrently like `lockfree::list`.
+
+pub use lockfree::list::{Cursor, List, Node};
@@ -1,7 +1,27 @@
 //! Lock-free hash table Based on https://dl.acm.org/doi/abs/10.1145/1147954.1147958
 
+mod counter;
 mod growable_array;
+#[cfg(feature = "std")]
+mod hp_split_ordered_list;
+mod list;
+mod split_ordered_hash_map;
 mod split_ordered_list;
+mod split_ordered_set;
 
-pub use growable_array::GrowableArray;
-pub use split_ordered_list::SplitOrderedList;
+#[cfg(feature = "instrument")]
+pub use growable_array::GrowableArrayMetrics;
+pub use growable_array::{GrowableArray, GrowableArrayStats, SegmentPoolStats};
+#[cfg(feature = "std")]
+pub use hp_split_ordered_list::HpSplitOrderedList;
+pub use split_ordered_hash_map::SplitOrderedHashMap;
+#[cfg(feature = "instrument")]
+pub use split_ordered_list::SplitOrderedListMetrics;
+#[cfg(feature = "std")]
+pub use split_ordered_list::{BucketTxn, Drain, IterChunks, ValueRef};
+pub use split_ordered_list::{
+    Buckets, Entry, GrowthPolicy, InsertError, IntoIter, Iter, Keys, LogOp, Lookup, OccupiedEntry,
+    SplitOrderedList, SplitOrderedListBuilder, SplitOrderedListStats, TryInsertError, VacantEntry,
+    Values,
+};
+pub use split_ordered_set::{SetIter, SplitOrderedSet};
@@ -0,0 +1,559 @@
+//! Split-ordered hash map.
+
+use alloc::boxed::Box;
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{BuildHasher, Hash, Hasher};
+use core::mem;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_epoch::{Atomic, Owned, Shared};
+use crossbeam_utils::Backoff;
+#[cfg(feature = "std")]
+use std::collections::hash_map::RandomState;
+
+use super::counter::StripedCounter;
+use super::growable_array::GrowableArray;
+use super::list::{Cursor, List, Node};
+use super::split_ordered_list::SoKey;
+use crate::map::NonblockingMap;
+use crate::reclaim::{self, unprotected, BackendGuard, Guard};
+
+/// Aligned so that the pointers to it have room for the `MOVING` and `MOVED` tags.
+#[repr(align(4))]
+struct Entry<K, V> {
+    key: K,
+    value: V,
+}
+
+/// Pointer to the entry of a regular node, null for the sentinel nodes.
+///
+/// The entries are boxed apart from the nodes so that `SplitOrderedHashMap::rehash` moves them to
+/// the next table without copying. The pointer is tagged `MOVING` while its entry is being moved,
+/// and `MOVED` once the entry is owned by a node of the next table.
+struct EntryPtr<K, V> {
+    entry: Atomic<Entry<K, V>>,
+}
+
+/// Tag of an entry that is being moved to the next table.
+const MOVING: usize = 1;
+/// Tag of an entry that is moved to the next table.
+const MOVED: usize = 2;
+
+type HashNode<K, V> = Node<SoKey, EntryPtr<K, V>>;
+type HashCursor<'g, K, V> = Cursor<'g, SoKey, EntryPtr<K, V>>;
+
+impl<K, V> EntryPtr<K, V> {
+    fn new(entry: Entry<K, V>) -> Self {
+        Self {
+            entry: Atomic::new(entry),
+        }
+    }
+
+    fn null() -> Self {
+        Self {
+            entry: Atomic::null(),
+        }
+    }
+
+    /// Gives up the ownership of the entry.
+    fn into_raw(self) -> *mut Entry<K, V> {
+        let entry = self
+            .entry
+            .load(Ordering::Relaxed, unsafe { unprotected() }.backend());
+        mem::forget(self);
+        entry.with_tag(0).as_raw() as *mut _
+    }
+}
+
+impl<K, V> Drop for EntryPtr<K, V> {
+    fn drop(&mut self) {
+        let guard = unsafe { unprotected() };
+        let entry = self.entry.load(Ordering::Relaxed, guard.backend());
+        if !entry.is_null() && entry.tag() != MOVED {
+            drop(unsafe { entry.with_tag(0).into_owned() });
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for EntryPtr<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntryPtr").finish()
+    }
+}
+
+/// Lock-free map from any hashable key to `V`, on a split-ordered list like [`SplitOrderedList`].
+///
+/// The keys are hashed with the `BuildHasher` `S`, and each `(key, value)` entry has its own node
+/// in the list, ordered by the split order of its hash. The nodes of the keys with the same hash
+/// are thus adjacent, and are told apart by comparing the keys. An insertion links its node before
+/// the first node of its hash, so that the insertions with the same hash conflict on the same link
+/// and a key is never inserted twice. A deletion marks and unlinks the node of the key.
+///
+/// [`SplitOrderedList`]: crate::SplitOrderedList
+#[derive(Debug)]
+pub struct SplitOrderedHashMap<K, V, S> {
+    /// the current table. `rehash` replaces it once the entries are migrated to the next one.
+    table: Atomic<Table<K, V, S>>,
+}
+
+/// The list of a `SplitOrderedHashMap` with the hasher its keys are hashed by.
+#[derive(Debug)]
+struct Table<K, V, S> {
+    list: List<SoKey, EntryPtr<K, V>>,
+    /// pointers to the sentinel nodes
+    buckets: GrowableArray<HashNode<K, V>>,
+    /// number of buckets, doubled when `count > size * LOAD_FACTOR`
+    size: AtomicUsize,
+    /// number of entries, striped by hash
+    count: StripedCounter,
+    hash_builder: S,
+    /// the table the entries are migrated to by `rehash`, or null. Once set, the writers go
+    /// through the next table, and the lookups follow the entries that are already moved.
+    next: Atomic<Table<K, V, S>>,
+    /// number of writers operating on this table that may not have seen `next`
+    writers: AtomicUsize,
+}
+
+impl<K, V, S: Default> Default for SplitOrderedHashMap<K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> Drop for SplitOrderedHashMap<K, V, S> {
+    fn drop(&mut self) {
+        let guard = unsafe { unprotected() };
+        drop(unsafe {
+            self.table
+                .load(Ordering::Relaxed, guard.backend())
+                .into_owned()
+        });
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V> SplitOrderedHashMap<K, V, RandomState> {
+    /// Creates a new split ordered hash map with a randomly seeded hasher.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, V, S> SplitOrderedHashMap<K, V, S> {
+    /// Creates a new split ordered hash map that hashes the keys with `hash_builder`.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            table: Atomic::new(Table::new(hash_builder)),
+        }
+    }
+
+    fn table<'g>(&self, guard: &'g Guard) -> &'g Table<K, V, S> {
+        unsafe { self.table.load(Ordering::Acquire, guard.backend()).deref() }
+    }
+}
+
+impl<K, V, S> Table<K, V, S> {
+    const LOAD_FACTOR: usize = 2;
+    const DEFAULT_BUCKETS: usize = 2;
+
+    fn new(hash_builder: S) -> Self {
+        Self {
+            list: List::new(),
+            buckets: GrowableArray::new(),
+            size: AtomicUsize::new(Self::DEFAULT_BUCKETS),
+            count: StripedCounter::new(),
+            hash_builder,
+            next: Atomic::null(),
+            writers: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of entries, or 0 while the concurrent deletions outweigh the insertions
+    /// in the counter.
+    fn len(&self) -> usize {
+        let count = self.count.sum();
+        if count > isize::max_value() as usize {
+            0
+        } else {
+            count
+        }
+    }
+
+    /// Returns a cursor at the sentinel node of bucket `index`, inserting it and its ancestors if
+    /// necessary.
+    fn bucket<'g>(&'g self, index: usize, guard: &'g Guard) -> HashCursor<'g, K, V> {
+        let store = self.buckets.get(index, guard).as_backend();
+        let sentinel = store.load(Ordering::Acquire, guard.backend());
+        if !sentinel.is_null() {
+            return unsafe { Cursor::from_raw(store, sentinel.as_raw()) };
+        }
+
+        // The parent of a bucket is the bucket with its most significant set bit cleared.
+        let parent = if index == 0 {
+            self.list.head(guard.backend())
+        } else {
+            let msb = mem::size_of::<usize>() * 8 - 1 - index.leading_zeros() as usize;
+            self.bucket(index & !(1 << msb), guard)
+        };
+
+        let key = SoKey::sentinel(index);
+        let mut node = Owned::new(Node::new(key, EntryPtr::null()));
+        let sentinel = loop {
+            let mut cursor = parent.clone();
+            match cursor.find_harris(&key, guard.backend()) {
+                Ok(true) => break cursor.curr(),
+                Ok(false) => match cursor.insert(node, guard.backend()) {
+                    Ok(()) => break cursor.curr(),
+                    Err(n) => node = n,
+                },
+                Err(()) => {}
+            }
+        };
+
+        // Publish the sentinel. If another thread already did, it published the same node.
+        let _ = self.buckets.compare_exchange(
+            index,
+            reclaim::Shared::null(),
+            reclaim::Shared::from_backend(sentinel),
+            Ordering::Release,
+            Ordering::Relaxed,
+            guard,
+        );
+        unsafe { Cursor::from_raw(store, sentinel.as_raw()) }
+    }
+
+    /// Returns a cursor at the first node of `hash`, or where it would be inserted, in the table
+    /// of `size` buckets.
+    fn group<'g>(&'g self, hash: usize, size: usize, guard: &'g Guard) -> HashCursor<'g, K, V> {
+        let key = SoKey::regular(hash);
+        let bucket = self.bucket(hash % size, guard);
+        loop {
+            let mut cursor = bucket.clone();
+            if cursor.find_harris(&key, guard.backend()).is_ok() {
+                return cursor;
+            }
+        }
+    }
+
+    /// Finds the node of `key` among the nodes of `hash` from `group`, their first one. Skips the
+    /// deleted nodes and those whose entries are moved to the next table.
+    fn find<'g, Q>(
+        group: &HashCursor<'g, K, V>,
+        hash: usize,
+        key: &Q,
+        guard: &'g Guard,
+    ) -> Option<HashCursor<'g, K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let so_key = SoKey::regular(hash);
+        let mut prev = group.prev();
+        let mut curr = group.curr();
+        while let Some(node) = unsafe { curr.as_ref() } {
+            if *node.key() != so_key {
+                return None;
+            }
+            let next = node.next().load(Ordering::Acquire, guard.backend());
+            let entry = node.value().entry.load(Ordering::Acquire, guard.backend());
+            if next.tag() == 0
+                && entry.tag() != MOVED
+                && Borrow::<Q>::borrow(&unsafe { entry.deref() }.key) == key
+            {
+                return Some(unsafe { Cursor::from_raw(prev, curr.as_raw()) });
+            }
+            prev = node.next();
+            curr = next.with_tag(0);
+        }
+        None
+    }
+
+    /// Waits for the writers that may not have seen `next`, and may still modify the list.
+    fn wait_for_writers(&self) {
+        let backoff = Backoff::new();
+        while self.writers.load(Ordering::SeqCst) != 0 {
+            backoff.snooze();
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> Table<K, V, S> {
+    /// Returns the hash of `key`, the key of its node in split order.
+    fn hash<Q: Hash + ?Sized>(&self, key: &Q) -> usize {
+        let mut hasher = self.hash_builder.build_hasher();
+        key.hash(&mut hasher);
+        hasher.finish() as usize
+    }
+
+    fn get<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<&'g Entry<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hash(key);
+        let group = self.group(hash, self.size.load(Ordering::Acquire), guard);
+        let cursor = Self::find(&group, hash, key, guard)?;
+        let node = unsafe { cursor.curr().deref() };
+        Some(unsafe {
+            node.value()
+                .entry
+                .load(Ordering::Acquire, guard.backend())
+                .deref()
+        })
+    }
+
+    fn insert_owned(&self, key: K, value: V, guard: &Guard) -> Result<(), (K, V)> {
+        let hash = self.hash(&key);
+        let node = Node::new(SoKey::regular(hash), EntryPtr::new(Entry { key, value }));
+        self.insert_node(Owned::new(node), hash, guard)
+            .map_err(|node| {
+                let entry = unsafe { Box::from_raw(node.into_box().into_value().into_raw()) };
+                (entry.key, entry.value)
+            })
+    }
+
+    /// Links `node`, the node of an entry with the hash `hash`, before the first node of `hash`.
+    /// Gives it back if the table already contains its key.
+    fn insert_node(
+        &self,
+        mut node: Owned<HashNode<K, V>>,
+        hash: usize,
+        guard: &Guard,
+    ) -> Result<(), Owned<HashNode<K, V>>> {
+        let entry = node.value().entry.load(Ordering::Relaxed, guard.backend());
+        let key = &unsafe { entry.with_tag(0).deref() }.key;
+        let size = loop {
+            let size = self.size.load(Ordering::Acquire);
+            let mut cursor = self.group(hash, size, guard);
+            if Self::find(&cursor, hash, key, guard).is_some() {
+                return Err(node);
+            }
+            match cursor.insert(node, guard.backend()) {
+                Ok(()) => break size,
+                Err(n) => node = n,
+            }
+        };
+
+        if self.count.increment(hash) > size * Self::LOAD_FACTOR
+            && self.len().saturating_sub(1) > size * Self::LOAD_FACTOR
+        {
+            self.size
+                .compare_and_swap(size, size * 2, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    fn delete<'g>(&'g self, key: &K, guard: &'g Guard) -> Result<&'g V, ()> {
+        let hash = self.hash(key);
+        loop {
+            let group = self.group(hash, self.size.load(Ordering::Acquire), guard);
+            let cursor = Self::find(&group, hash, key, guard).ok_or(())?;
+            // The entry is destroyed with the node.
+            if let Ok(entry) = cursor.delete(guard.backend()) {
+                let entry = unsafe { entry.entry.load(Ordering::Acquire, guard.backend()).deref() };
+                let _ = self.count.decrement(hash);
+                return Ok(&entry.value);
+            }
+        }
+    }
+
+    /// Moves the entries with the hash `hash` to `next` unless they're moved already, waiting
+    /// for the threads that move them if there are any.
+    fn migrate_hash(&self, hash: usize, next: &Self, guard: &Guard) {
+        self.wait_for_writers();
+        let so_key = SoKey::regular(hash);
+        let group = self.group(hash, self.size.load(Ordering::Acquire), guard);
+        let mut curr = group.curr();
+        while let Some(node) = unsafe { curr.as_ref() } {
+            if *node.key() != so_key {
+                return;
+            }
+            self.migrate(node, next, guard);
+            curr = node
+                .next()
+                .load(Ordering::Acquire, guard.backend())
+                .with_tag(0);
+        }
+    }
+
+    /// Moves the entry of `node` to `next`, like `migrate_hash`. The writers of the table must be
+    /// done.
+    fn migrate(&self, node: &HashNode<K, V>, next: &Self, guard: &Guard) {
+        let backoff = Backoff::new();
+        loop {
+            let entry = node.value().entry.load(Ordering::Acquire, guard.backend());
+            if entry.is_null() || node.next().load(Ordering::Acquire, guard.backend()).tag() != 0 {
+                // A sentinel or a deleted node.
+                return;
+            }
+            match entry.tag() {
+                MOVED => return,
+                MOVING => backoff.snooze(),
+                _ => {
+                    if node
+                        .value()
+                        .entry
+                        .compare_and_set(
+                            entry,
+                            entry.with_tag(MOVING),
+                            Ordering::AcqRel,
+                            guard.backend(),
+                        )
+                        .is_err()
+                    {
+                        continue;
+                    }
+
+                    // The entry is shared with the node in the next table, which owns it from now.
+                    let hash = next.hash(&unsafe { entry.deref() }.key);
+                    let moved = Node::new(
+                        SoKey::regular(hash),
+                        EntryPtr {
+                            entry: Atomic::from(entry),
+                        },
+                    );
+                    if let Err(moved) = next.insert_node(Owned::new(moved), hash, guard) {
+                        let _ = moved.into_box().into_value().into_raw();
+                        unreachable!("the writers move a key before operating on the next table");
+                    }
+                    node.value()
+                        .entry
+                        .store(entry.with_tag(MOVED), Ordering::Release);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> SplitOrderedHashMap<K, V, S> {
+    /// Lookups the given key to get the reference to its value.
+    pub fn lookup<'g>(&'g self, key: &K, guard: &'g Guard) -> Option<&'g V> {
+        self.get_key_value(key, guard).map(|(_, value)| value)
+    }
+
+    /// Lookups the given key to get the references to the stored key and its value, e.g. to
+    /// recover an interned key from an equal one.
+    ///
+    /// `key` may be any borrowed form of the key type, e.g. `&str` for `String` keys, whose `Hash`
+    /// and `Eq` must match those of the key type.
+    pub fn get_key_value<'g, Q>(&'g self, key: &Q, guard: &'g Guard) -> Option<(&'g K, &'g V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut table = self.table(guard);
+        loop {
+            // An entry that is being moved is not modified until it's in the next table.
+            if let Some(entry) = table.get(key, guard) {
+                return Some((&entry.key, &entry.value));
+            }
+            let next = table.next.load(Ordering::Acquire, guard.backend());
+            if next.is_null() {
+                return None;
+            }
+            table = unsafe { next.deref() };
+        }
+    }
+
+    /// Inserts a key-value pair. Returns them back if the map already contains the key.
+    pub fn insert_owned(&self, key: K, value: V, guard: &Guard) -> Result<(), (K, V)> {
+        let writer = self.writer(&key, guard);
+        writer.table.insert_owned(key, value, guard)
+    }
+
+    /// Migrates the entries to a table whose keys are hashed by `hash_builder`, e.g. with a fresh
+    /// seed to defend a long-lived map against hash flooding. Gives `hash_builder` back if another
+    /// rehash is in progress.
+    ///
+    /// The map stays usable during the migration: the lookups consult the new table for the
+    /// entries that are already moved and the old one for the others, and a writer first moves the
+    /// entries with the old hash of its key, then operates on the new table. The entries with the
+    /// same old hash are moved one at a time, and each is found in exactly one of the tables at
+    /// any time.
+    ///
+    /// NOTE: The migration waits for the writers that started before it to finish, and a writer
+    /// waits for a concurrent move of the entries with the old hash of its key.
+    pub fn rehash(&self, hash_builder: S, guard: &Guard) -> Result<(), S> {
+        let current = self.table.load(Ordering::Acquire, guard.backend());
+        let table = unsafe { current.deref() };
+        let next = match table.next.compare_and_set(
+            Shared::null(),
+            Owned::new(Table::new(hash_builder)),
+            Ordering::SeqCst,
+            guard.backend(),
+        ) {
+            Ok(next) => next,
+            Err(e) => return Err(e.new.into_box().hash_builder),
+        };
+
+        // Every entry of the old table is in the list after this: only the writers that didn't see
+        // `next` modify it.
+        table.wait_for_writers();
+        let mut curr = table.list.head(guard.backend()).curr();
+        while let Some(node) = unsafe { curr.as_ref() } {
+            table.migrate(node, unsafe { next.deref() }, guard);
+            curr = node
+                .next()
+                .load(Ordering::Acquire, guard.backend())
+                .with_tag(0);
+        }
+
+        self.table.store(next, Ordering::Release);
+        unsafe { guard.defer_destroy(current) };
+        Ok(())
+    }
+
+    /// Registers a writer of `key` on the table that is to hold it, i.e. the newest one, after
+    /// migrating the entries with the hash of `key` from the older ones.
+    fn writer<'g>(&'g self, key: &K, guard: &'g Guard) -> Writer<'g, K, V, S> {
+        let mut table = self.table(guard);
+        loop {
+            let next = table.next.load(Ordering::SeqCst, guard.backend());
+            if next.is_null() {
+                // Pairs with `rehash` setting `next` and waiting for the writers: either the
+                // writer sees `next`, or the migration waits for it.
+                let _ = table.writers.fetch_add(1, Ordering::SeqCst);
+                let writer = Writer { table };
+                if table.next.load(Ordering::SeqCst, guard.backend()).is_null() {
+                    return writer;
+                }
+                continue;
+            }
+
+            let next = unsafe { next.deref() };
+            table.migrate_hash(table.hash(key), next, guard);
+            table = next;
+        }
+    }
+}
+
+impl<K, V, S> NonblockingMap<K, V> for SplitOrderedHashMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    S: BuildHasher,
+{
+    fn lookup<'a>(&'a self, key: &K, guard: &'a Guard) -> Option<&'a V> {
+        Self::lookup(self, key, guard)
+    }
+
+    fn insert(&self, key: &K, value: V, guard: &Guard) -> Result<(), V> {
+        self.insert_owned(key.clone(), value, guard)
+            .map_err(|(_, value)| value)
+    }
+
+    fn delete<'a>(&'a self, key: &K, guard: &'a Guard) -> Result<&'a V, ()> {
+        self.writer(key, guard).table.delete(key, guard)
+    }
+}
+
+/// A writer registered on a table by `SplitOrderedHashMap::writer`, until it's dropped.
+struct Writer<'g, K, V, S> {
+    table: &'g Table<K, V, S>,
+}
+
+impl<K, V, S> Drop for Writer<'_, K, V, S> {
+    fn drop(&mut self) {
+        let _ = self.table.writers.fetch_sub(1, Ordering::Release);
+    }
+}
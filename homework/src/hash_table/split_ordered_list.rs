@@ -1,59 +1,985 @@
 //! Split-ordered linked list.
 
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp;
+use core::iter::{FromIterator, Peekable};
+use core::marker::PhantomData;
 use core::sync::atomic::{AtomicUsize, Ordering};
-use crossbeam_epoch::{Guard, Owned, Shared};
-use lockfree::list::{Cursor, List, Node};
+use crossbeam_epoch::{Atomic, Owned, Shared};
+#[cfg(feature = "rayon")]
+use rayon_crate::prelude::*;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, MutexGuard};
+#[cfg(feature = "access-time")]
+use std::time::{Duration, Instant};
 
+use super::counter::StripedCounter;
 use super::growable_array::GrowableArray;
+use super::list::{Cursor, List, Node};
+#[cfg(feature = "std")]
+use crate::append_log::AppendLog;
+use crate::budget::{MemoryBudget, OutOfBudget};
 use crate::map::NonblockingMap;
+#[cfg(feature = "pmem")]
+use crate::pmem::PersistHooks;
+#[cfg(feature = "std")]
+use crate::reclaim::{assert_unpinned, pin, synchronize};
+use crate::utils::{Watchdog, YieldPoint};
 
-//TODO remove where
-
-/// Lock-free map from `usize` in range [0, 2^63-1] to `V`.
+/// Lock-free map from `usize` to `V`.
 ///
 /// NOTE: We don't care about hashing in this homework for simplicity.
 #[derive(Debug)]
-pub struct SplitOrderedList<V> where V: std::fmt::Debug {
+pub struct SplitOrderedList<V> {
     /// Lock-free list sorted by recursive-split order. Use `None` sentinel node value.
-    list: List<usize, Option<V>>,
+    list: List<SoKey, Option<Slot<V>>>,
     /// array of pointers to the buckets
-    buckets: GrowableArray<Node<usize, Option<V>>>,
+    buckets: GrowableArray<Node<SoKey, Option<Slot<V>>>>,
     /// number of buckets
     size: AtomicUsize,
-    /// number of items
-    count: AtomicUsize,
+    /// `size` is doubled when `count > size * load_factor`, and halved when
+    /// `count < size / (2 * load_factor)`
+    load_factor: usize,
+    /// `size` is never halved below the initial size
+    min_size: usize,
+    /// how far `size` grows when it's doubled
+    growth_policy: GrowthPolicy,
+    /// number of items, striped by key. The increment of an insertion may land after the
+    /// decrement of a concurrent deletion of the same node, so it may transiently wrap below zero;
+    /// see `clamp_count`.
+    count: StripedCounter,
+    /// counters of the contended paths
+    counters: Counters,
+    /// striped locks for `bucket_txn`; never taken by the lock-free operations
+    #[cfg(feature = "std")]
+    txn_locks: TxnLocks,
+    /// hooks invoked after node publication and unlink
+    #[cfg(feature = "pmem")]
+    persist: Option<Box<dyn PersistHooks>>,
+    /// budget charged for the nodes
+    budget: Option<MemoryBudget>,
+    /// write-ahead log of the mutations
+    #[cfg(feature = "std")]
+    op_log: Option<OpLog<V>>,
+    /// origin of the access timestamps stored in the nodes' metadata words
+    #[cfg(feature = "access-time")]
+    clock_origin: Instant,
+}
+
+/// Builder of a `SplitOrderedList` with a custom resize policy, e.g. for benchmarks sweeping the
+/// policies.
+///
+/// This `struct` is created by [`SplitOrderedList::builder`].
+///
+/// ```
+/// use cs492_concur_homework::SplitOrderedList;
+///
+/// let list = SplitOrderedList::<usize>::builder()
+///     .load_factor(4)
+///     .initial_buckets(1024)
+///     .build();
+/// assert_eq!(list.bucket_count(), 1024);
+/// ```
+#[derive(Debug)]
+pub struct SplitOrderedListBuilder<V> {
+    load_factor: usize,
+    initial_buckets: usize,
+    growth_policy: GrowthPolicy,
+    _marker: PhantomData<V>,
+}
+
+/// How far the number of buckets of a `SplitOrderedList` grows once the average bucket has more
+/// entries than the load factor. See [`SplitOrderedListBuilder::growth_policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrowthPolicy {
+    /// Double the number of buckets.
+    Double,
+    /// Grow the number of buckets directly to the smallest power of two that brings the average
+    /// back within the load factor, and at least double it, so that a burst of insertions doesn't
+    /// leave the table undersized until enough later insertions double it step by step.
+    Fit,
+}
+
+impl Default for GrowthPolicy {
+    fn default() -> Self {
+        Self::Double
+    }
+}
+
+impl<V> SplitOrderedListBuilder<V> {
+    /// Sets the average number of entries per bucket above which the number of buckets is
+    /// doubled. The number of buckets is halved when the average falls below a quarter of it.
+    /// Defaults to 2.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `load_factor` is 0.
+    pub fn load_factor(mut self, load_factor: usize) -> Self {
+        assert!(load_factor > 0, "load factor must be positive");
+        self.load_factor = load_factor;
+        self
+    }
+
+    /// Sets the initial number of buckets, below which the table never shrinks. The buckets are
+    /// still initialized lazily. Defaults to 2.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial_buckets` is not a power of two.
+    pub fn initial_buckets(mut self, initial_buckets: usize) -> Self {
+        assert!(
+            initial_buckets.is_power_of_two(),
+            "number of buckets must be a power of two"
+        );
+        self.initial_buckets = initial_buckets;
+        self
+    }
+
+    /// Sets how far the number of buckets grows at a time. Defaults to `GrowthPolicy::Double`.
+    pub fn growth_policy(mut self, growth_policy: GrowthPolicy) -> Self {
+        self.growth_policy = growth_policy;
+        self
+    }
+
+    /// Creates the split ordered list.
+    pub fn build(self) -> SplitOrderedList<V> {
+        SplitOrderedList {
+            size: AtomicUsize::new(self.initial_buckets),
+            load_factor: self.load_factor,
+            min_size: self.initial_buckets,
+            growth_policy: self.growth_policy,
+            ..SplitOrderedList::default()
+        }
+    }
+}
+
+/// Mutation recorded in the op log of a `SplitOrderedList`. See
+/// [`SplitOrderedList::with_op_log`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogOp<V> {
+    /// The key-value pair was inserted.
+    Insert(usize, V),
+    /// The key was deleted.
+    Delete(usize),
+    /// The value of the key was replaced.
+    Update(usize, V),
+}
+
+#[cfg(feature = "std")]
+struct OpLog<V> {
+    log: Arc<AppendLog<LogOp<V>>>,
+    /// Striped locks serializing each mutation with its append, so that the operations on a key
+    /// are logged in the order they took effect.
+    stripes: Box<[Mutex<()>]>,
+    /// `V::clone`, captured by the constructor so that the map itself doesn't require `V: Clone`
+    clone_value: fn(&V) -> V,
+}
+
+#[cfg(feature = "std")]
+impl<V> core::fmt::Debug for OpLog<V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OpLog")
+            .field("len", &self.log.len())
+            .finish()
+    }
+}
+
+/// Error of [`SplitOrderedList::insert_budgeted`]. Both variants give the value back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsertError<V> {
+    /// The key is already in the map.
+    Occupied(V),
+    /// The node doesn't fit in the memory budget.
+    OutOfBudget(V),
+}
+
+impl<V> InsertError<V> {
+    /// Extracts the value that was not inserted.
+    pub fn into_value(self) -> V {
+        match self {
+            Self::Occupied(v) | Self::OutOfBudget(v) => v,
+        }
+    }
+}
+
+/// Error of [`SplitOrderedList::try_insert`]. Both variants give the value back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TryInsertError<'g, V> {
+    /// The key is already in the map, with the given value.
+    Occupied(V, &'g V),
+    /// The node doesn't fit in the memory budget.
+    OutOfBudget(V),
+}
+
+impl<V> TryInsertError<'_, V> {
+    /// Extracts the value that was not inserted.
+    pub fn into_value(self) -> V {
+        match self {
+            Self::Occupied(v, _) | Self::OutOfBudget(v) => v,
+        }
+    }
+}
+
+/// Counters of the contended paths of a `SplitOrderedList`, returned by
+/// [`SplitOrderedList::metrics`].
+#[cfg(feature = "instrument")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitOrderedListMetrics {
+    /// Number of searches in the list restarted because `find_harris` failed to unlink a chain
+    /// of deleted nodes.
+    pub find_retries: usize,
+    /// Number of failed CASes linking the node of an insertion.
+    pub insert_cas_failures: usize,
+    /// Number of bucket initializations that found the sentinel already inserted by another
+    /// thread.
+    pub bucket_init_races: usize,
+    /// Number of resizes triggered by an insertion or deletion, including the ones whose CAS of
+    /// the number of buckets failed.
+    pub resize_triggers: usize,
+}
+
+/// Counters of the contended paths. Without the `instrument` feature, they are empty and
+/// updating them is a no-op.
+#[derive(Debug, Default)]
+struct Counters {
+    #[cfg(feature = "instrument")]
+    find_retries: AtomicUsize,
+    #[cfg(feature = "instrument")]
+    insert_cas_failures: AtomicUsize,
+    #[cfg(feature = "instrument")]
+    bucket_init_races: AtomicUsize,
+    #[cfg(feature = "instrument")]
+    resize_triggers: AtomicUsize,
+}
+
+#[cfg(feature = "instrument")]
+impl Counters {
+    #[inline]
+    fn find_retry(&self) {
+        self.find_retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn insert_cas_failure(&self) {
+        self.insert_cas_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn bucket_init_race(&self) {
+        self.bucket_init_races.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn resize_trigger(&self) {
+        self.resize_triggers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn metrics(&self) -> SplitOrderedListMetrics {
+        SplitOrderedListMetrics {
+            find_retries: self.find_retries.load(Ordering::Relaxed),
+            insert_cas_failures: self.insert_cas_failures.load(Ordering::Relaxed),
+            bucket_init_races: self.bucket_init_races.load(Ordering::Relaxed),
+            resize_triggers: self.resize_triggers.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(not(feature = "instrument"))]
+impl Counters {
+    #[inline]
+    fn find_retry(&self) {}
+
+    #[inline]
+    fn insert_cas_failure(&self) {}
+
+    #[inline]
+    fn bucket_init_race(&self) {}
+
+    #[inline]
+    fn resize_trigger(&self) {}
+}
+
+/// Result of [`SplitOrderedList::lookup_verbose`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup<'g, V> {
+    /// The key is in the map.
+    Found(&'g V),
+    /// No node with the key was observed.
+    Absent,
+    /// A node with the key was observed, but it was already marked deleted.
+    Unlinked,
+}
+
+/// Key of a node of the underlying list.
+///
+/// The nodes are sorted by the bit-reversed key of the regular nodes and the bit-reversed bucket
+/// index of the sentinel nodes, i.e. in split order, and the sentinel of bucket `b` precedes the
+/// regular node of key `b`. The paper instead sets the most significant bit of the regular keys
+/// before reversing them, which packs the split-ordered key in a word but leaves the keys with
+/// that bit set unsupported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct SoKey {
+    reversed: usize,
+    regular: bool,
+}
+
+impl SoKey {
+    /// Returns the split-ordered key of the regular node of `key`.
+    pub(super) fn regular(key: usize) -> Self {
+        Self {
+            reversed: key.reverse_bits(),
+            regular: true,
+        }
+    }
+
+    /// Returns the split-ordered key of the sentinel node of bucket `index`.
+    pub(super) fn sentinel(index: usize) -> Self {
+        Self {
+            reversed: index.reverse_bits(),
+            regular: false,
+        }
+    }
+
+    /// Returns the key of a regular node, or `None` for a sentinel node.
+    pub(super) fn regular_key(&self) -> Option<usize> {
+        if self.regular {
+            Some(self.reversed.reverse_bits())
+        } else {
+            None
+        }
+    }
+}
+
+/// Shape statistics of a `SplitOrderedList`, returned by [`SplitOrderedList::stats`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitOrderedListStats {
+    /// Current number of buckets.
+    pub bucket_count: usize,
+    /// Number of initialized buckets, i.e. of sentinel nodes in the list.
+    pub sentinels: usize,
+    /// `(bucket_index, chain_len)` of each initialized bucket in list (split) order, where
+    /// `chain_len` is the number of entries between its sentinel and the next one.
+    pub chain_lengths: Vec<(usize, usize)>,
+    /// Largest number of nodes a search walks from a sentinel to reach an entry.
+    pub max_probe_distance: usize,
+    /// Mean number of nodes a search walks from a sentinel to reach an entry, or 0 if there is
+    /// none.
+    pub mean_probe_distance: f64,
+    /// Average number of entries per bucket.
+    pub load_factor: f64,
+}
+
+/// Tag of the value pointer of a `Slot` whose entry is deleted.
+const SEALED: usize = 1;
+
+/// Value of a `Slot`, over-aligned so that its pointer has a low bit for `SEALED` even if `V` has
+/// an alignment of 1, e.g. `()` or `u8`: the tag of an `Atomic` is masked to the alignment of the
+/// pointee.
+#[repr(align(2))]
+struct Aligned<V>(V);
+
+/// Value of a regular node, which `update` replaces in place.
+///
+/// `delete` seals the slot (tags its pointer with `SEALED`) before it marks the node, so a sealed
+/// slot is logically deleted, and a replacement either happens before the deletion or fails.
+/// Replaced values are retired through the epoch GC; the last value is dropped with the node.
+struct Slot<V> {
+    value: Atomic<Aligned<V>>,
+}
+
+impl<V> Slot<V> {
+    fn new(value: V) -> Self {
+        Self {
+            value: Atomic::new(Aligned(value)),
+        }
+    }
+
+    /// Returns the value, or `None` if the slot is sealed.
+    fn load<'g>(&self, guard: &'g Guard) -> Option<&'g V> {
+        let value = self.value.load(Ordering::Acquire, guard.backend());
+        if value.tag() == SEALED {
+            None
+        } else {
+            Some(&unsafe { value.deref() }.0)
+        }
+    }
+
+    /// Returns the value even if the slot is sealed. It stays valid under `guard` even if it's
+    /// replaced or deleted afterwards.
+    fn peek<'g>(&self, guard: &'g Guard) -> &'g V {
+        &unsafe { self.value.load(Ordering::Acquire, guard.backend()).deref() }.0
+    }
+
+    /// Seals the slot if `predicate` holds for the current value, and returns that value. Returns
+    /// `None` if the slot is sealed or the predicate fails.
+    fn seal_if<'g, P>(&self, mut predicate: P, guard: &'g Guard) -> Option<&'g V>
+    where
+        P: FnMut(&V) -> bool,
+    {
+        let mut current = self.value.load(Ordering::Acquire, guard.backend());
+        loop {
+            if current.tag() == SEALED || !predicate(&unsafe { current.deref() }.0) {
+                return None;
+            }
+
+            match self.value.compare_and_set(
+                current,
+                current.with_tag(SEALED),
+                Ordering::AcqRel,
+                guard.backend(),
+            ) {
+                Ok(_) => return Some(&unsafe { current.deref() }.0),
+                Err(err) => current = err.current,
+            }
+        }
+    }
+
+    /// Replaces the value with `new` if `predicate` holds for the current value, and returns the
+    /// replaced value. Gives `new` back if the slot is sealed or the predicate fails.
+    fn replace_if<'g, P>(&self, new: V, mut predicate: P, guard: &'g Guard) -> Result<&'g V, V>
+    where
+        P: FnMut(&V) -> bool,
+    {
+        let mut new = Owned::new(Aligned(new));
+        let mut current = self.value.load(Ordering::Acquire, guard.backend());
+        loop {
+            if current.tag() == SEALED || !predicate(&unsafe { current.deref() }.0) {
+                return Err(new.into_box().0);
+            }
+
+            match self
+                .value
+                .compare_and_set(current, new, Ordering::AcqRel, guard.backend())
+            {
+                Ok(_) => {
+                    unsafe { guard.defer_destroy(current) };
+                    return Ok(&unsafe { current.deref() }.0);
+                }
+                Err(err) => {
+                    current = err.current;
+                    new = err.new;
+                }
+            }
+        }
+    }
+
+    /// Unlinks the value of a sealed slot, and returns its pointer. The value can be moved out with
+    /// `take` once no reference to it is alive.
+    #[cfg(feature = "std")]
+    fn unlink_value(&self, guard: &Guard) -> *mut Aligned<V> {
+        let sealed = Shared::null().with_tag(SEALED);
+        let value = self.value.swap(sealed, Ordering::AcqRel, guard.backend());
+        debug_assert_eq!(value.tag(), SEALED);
+        value.with_tag(0).as_raw() as *mut _
+    }
+
+    /// Moves out a value unlinked by `unlink_value`.
+    ///
+    /// # Safety
+    ///
+    /// No reference to the value may be alive.
+    #[cfg(feature = "std")]
+    unsafe fn take(value: *mut Aligned<V>) -> V {
+        Box::from_raw(value).0
+    }
+
+    /// Moves the value out of a slot that is no longer shared, unless it's sealed. The value of a
+    /// sealed slot is left to `drop`.
+    fn take_unsealed(&self, guard: &Guard) -> Option<V> {
+        let value = self.value.load(Ordering::Relaxed, guard.backend());
+        if value.tag() == SEALED {
+            return None;
+        }
+        let _ = self.value.swap(
+            Shared::null().with_tag(SEALED),
+            Ordering::Relaxed,
+            guard.backend(),
+        );
+        Some(unsafe { value.into_owned() }.into_box().0)
+    }
+
+    /// Extracts the value of a slot that was never shared.
+    fn into_inner(self) -> V {
+        let guard = unsafe { unprotected() };
+        let value = self
+            .value
+            .swap(Shared::null(), Ordering::Relaxed, guard.backend());
+        unsafe { value.into_owned() }.into_box().0
+    }
 }
 
-impl<V> Default for SplitOrderedList<V> where V: std::fmt::Debug {
+impl<V> Drop for Slot<V> {
+    fn drop(&mut self) {
+        let guard = unsafe { unprotected() };
+        let value = self.value.load(Ordering::Relaxed, guard.backend());
+        if !value.is_null() {
+            drop(unsafe { value.into_owned() });
+        }
+    }
+}
+
+impl<V> core::fmt::Debug for Slot<V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Slot").finish()
+    }
+}
+
+/// Number of stripes of the `bucket_txn` locks. Must be a power of two.
+#[cfg(feature = "std")]
+const TXN_STRIPES: usize = 64;
+
+/// Locks of `bucket_txn`, striped by `key % TXN_STRIPES` and allocated by the first transaction.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+struct TxnLocks {
+    stripes: Atomic<Box<[Mutex<()>]>>,
+}
+
+#[cfg(feature = "std")]
+impl TxnLocks {
+    /// Returns the stripes, allocating them if necessary.
+    fn stripes<'s>(&'s self, guard: &'s Guard) -> &'s [Mutex<()>] {
+        let stripes = self.stripes.load(Ordering::Acquire, guard.backend());
+        if !stripes.is_null() {
+            return unsafe { stripes.deref() };
+        }
+
+        let new = (0..TXN_STRIPES)
+            .map(|_| Mutex::new(()))
+            .collect::<Box<[_]>>();
+        match self.stripes.compare_and_set(
+            Shared::null(),
+            Owned::new(new),
+            Ordering::AcqRel,
+            guard.backend(),
+        ) {
+            Ok(stripes) => unsafe { stripes.deref() },
+            Err(e) => unsafe { e.current.deref() },
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for TxnLocks {
+    fn drop(&mut self) {
+        let guard = unsafe { unprotected() };
+        let stripes = self.stripes.load(Ordering::Relaxed, guard.backend());
+        if !stripes.is_null() {
+            drop(unsafe { stripes.into_owned() });
+        }
+    }
+}
+
+impl<V> Default for SplitOrderedList<V> {
     fn default() -> Self {
         Self {
             list: List::new(),
             buckets: GrowableArray::new(),
-            size: AtomicUsize::new(2),
-            count: AtomicUsize::new(0),
+            size: AtomicUsize::new(Self::DEFAULT_BUCKETS),
+            load_factor: Self::DEFAULT_LOAD_FACTOR,
+            min_size: Self::DEFAULT_BUCKETS,
+            growth_policy: GrowthPolicy::default(),
+            count: StripedCounter::new(),
+            counters: Counters::default(),
+            #[cfg(feature = "std")]
+            txn_locks: TxnLocks::default(),
+            #[cfg(feature = "pmem")]
+            persist: None,
+            budget: None,
+            #[cfg(feature = "std")]
+            op_log: None,
+            #[cfg(feature = "access-time")]
+            clock_origin: Instant::now(),
         }
     }
 }
 
-impl<V> SplitOrderedList<V> where V: std::fmt::Debug {
-    /// `size` is doubled when `count > size * LOAD_FACTOR`.
-    const LOAD_FACTOR: usize = 2;
-    const HI_MASK: usize = 0x8000000000000000usize;
-    const MASK: usize    = 0x0000FFFFFFFFFFFFusize;
+impl<V> SplitOrderedList<V> {
+    const DEFAULT_LOAD_FACTOR: usize = 2;
+    const DEFAULT_BUCKETS: usize = 2;
 
     /// Creates a new split ordered list.
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Creates a new split ordered list with enough buckets for `capacity` entries at the default
+    /// load factor, below which it never shrinks.
+    ///
+    /// Unlike the lazily initialized buckets of `SplitOrderedListBuilder::initial_buckets`, the
+    /// sentinels of all the buckets are inserted up front, so that the first insertions into
+    /// each bucket don't pay for its initialization.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let buckets = cmp::max(
+            Self::DEFAULT_BUCKETS,
+            (capacity / Self::DEFAULT_LOAD_FACTOR).next_power_of_two(),
+        );
+        let list = Self::builder().initial_buckets(buckets).build();
+        // SAFETY: the list is not shared yet, and the bucket initialization retires nothing.
+        let guard = unsafe { unprotected() };
+        // The parent of a bucket has a smaller index, so each sentinel is inserted right after an
+        // initialized parent.
+        for index in 0..buckets {
+            let _ = list.lookup_bucket(index, guard);
+        }
+        list
+    }
+
+    /// Returns a builder to configure the resize policy of a new split ordered list.
+    pub fn builder() -> SplitOrderedListBuilder<V> {
+        SplitOrderedListBuilder {
+            load_factor: Self::DEFAULT_LOAD_FACTOR,
+            initial_buckets: Self::DEFAULT_BUCKETS,
+            growth_policy: GrowthPolicy::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a new split ordered list that invokes the given hooks on each node publication,
+    /// unlink and value replacement, as described in [`PersistHooks`].
+    #[cfg(feature = "pmem")]
+    pub fn with_persist_hooks(hooks: Box<dyn PersistHooks>) -> Self {
+        Self {
+            persist: Some(hooks),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a new split ordered list whose nodes and bucket segments are charged to the given
+    /// budget. Insertions whose node doesn't fit in the budget fail fast.
+    pub fn with_budget(budget: MemoryBudget) -> Self {
+        Self {
+            buckets: GrowableArray::with_budget(budget.clone()),
+            budget: Some(budget),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a new split ordered list that records its successful mutations in the given log,
+    /// from which [`rebuild_from_log`](SplitOrderedList::rebuild_from_log) reconstructs the
+    /// map, e.g. after a restart if the log is persisted.
+    ///
+    /// Each mutation takes a striped lock around its update of the list and its append, so that
+    /// the log order of the operations on a key is their linearization order. The mutations are
+    /// thus no longer lock-free, while the lookups still are.
+    #[cfg(feature = "std")]
+    pub fn with_op_log(log: Arc<AppendLog<LogOp<V>>>) -> Self
+    where
+        V: Clone,
+    {
+        Self {
+            op_log: Some(OpLog {
+                log,
+                stripes: (0..TXN_STRIPES).map(|_| Mutex::new(())).collect(),
+                clone_value: V::clone,
+            }),
+            ..Self::default()
+        }
+    }
+
+    /// Reconstructs the map by replaying the (complete prefix of the) given log. The result keeps
+    /// recording its mutations in the log.
+    #[cfg(feature = "std")]
+    pub fn rebuild_from_log(log: Arc<AppendLog<LogOp<V>>>, guard: &Guard) -> Self
+    where
+        V: Clone,
+    {
+        let map = Self::new();
+        for op in log.iter(guard) {
+            match op {
+                LogOp::Insert(key, value) => {
+                    let _ = map.insert(key, value.clone(), guard);
+                }
+                LogOp::Delete(key) => {
+                    let _ = map.delete(key, guard);
+                }
+                LogOp::Update(key, value) => {
+                    let _ = map.update(key, value.clone(), guard);
+                }
+            }
+        }
+
+        Self {
+            op_log: Some(OpLog {
+                log,
+                stripes: (0..TXN_STRIPES).map(|_| Mutex::new(())).collect(),
+                clone_value: V::clone,
+            }),
+            ..map
+        }
+    }
+
+    /// Locks the op log stripe of `key`, if there is an op log.
+    #[cfg(feature = "std")]
+    fn lock_log_stripe(&self, key: usize) -> Option<MutexGuard<'_, ()>> {
+        let op_log = self.op_log.as_ref()?;
+        Some(op_log.stripes[key % TXN_STRIPES].lock().unwrap())
+    }
+
+    /// Appends the mutation built by `op` to the op log, if there is one. `op` is given the
+    /// function cloning values.
+    #[cfg(feature = "std")]
+    fn log_op<F: FnOnce(fn(&V) -> V) -> LogOp<V>>(&self, op: F, guard: &Guard) {
+        if let Some(op_log) = &self.op_log {
+            op_log.log.append(op(op_log.clone_value), guard);
+        }
+    }
+
+    /// Tag of the bucket slot of a sentinel that `compact` is removing.
+    const RETIRING: usize = 1;
+
+    const NODE_BYTES: usize =
+        core::mem::size_of::<Node<SoKey, Option<Slot<V>>>>() + core::mem::size_of::<V>();
+
+    /// Persists a node before it is linked into the list: the node and its value allocation.
+    #[inline]
+    fn persist_new_node(&self, _node: &Node<SoKey, Option<Slot<V>>>, _guard: &Guard) {
+        #[cfg(feature = "pmem")]
+        {
+            if let Some(hooks) = &self.persist {
+                hooks.flush(
+                    _node as *const _ as *const u8,
+                    core::mem::size_of::<Node<SoKey, Option<Slot<V>>>>(),
+                );
+                if let Some(slot) = _node.value() {
+                    Self::flush_value(&**hooks, slot, _guard);
+                }
+                hooks.fence();
+            }
+        }
+    }
+
+    /// Persists the predecessor's link after a node is linked into the list.
+    #[inline]
+    fn persist_link(&self, _link: &Atomic<Node<SoKey, Option<Slot<V>>>>) {
+        #[cfg(feature = "pmem")]
+        {
+            if let Some(hooks) = &self.persist {
+                hooks.flush(
+                    _link as *const _ as *const u8,
+                    core::mem::size_of::<Atomic<Node<SoKey, Option<Slot<V>>>>>(),
+                );
+                hooks.fence();
+            }
+        }
+    }
+
+    /// Persists the value of a slot that has just been replaced: the new value allocation, and
+    /// then the slot pointing to it.
+    #[inline]
+    fn persist_value(&self, _slot: &Slot<V>, _guard: &Guard) {
+        #[cfg(feature = "pmem")]
+        {
+            if let Some(hooks) = &self.persist {
+                Self::flush_value(&**hooks, _slot, _guard);
+                hooks.fence();
+                hooks.flush(
+                    &_slot.value as *const _ as *const u8,
+                    core::mem::size_of::<Atomic<Aligned<V>>>(),
+                );
+                hooks.fence();
+            }
+        }
+    }
+
+    /// Flushes the value allocation of a slot, if any.
+    #[cfg(feature = "pmem")]
+    fn flush_value(hooks: &dyn PersistHooks, slot: &Slot<V>, guard: &Guard) {
+        let value = slot.value.load(Ordering::Acquire, guard.backend());
+        if !value.is_null() {
+            hooks.flush(
+                value.as_raw() as *const u8,
+                core::mem::size_of::<Aligned<V>>(),
+            );
+        }
+    }
+
+    /// Deletes the node at `cursor`, and persists the marked node and its predecessor's link.
+    /// Fails if the node is already marked.
+    ///
+    /// The nodes unlinked by the searches, when they meet a chain of marked nodes, are not
+    /// persisted: their marks were persisted by the deletions, so a recovery can skip them.
+    fn delete_node<'g>(
+        &self,
+        cursor: Cursor<'g, SoKey, Option<Slot<V>>>,
+        guard: &'g Guard,
+    ) -> Result<(), ()> {
+        let _node = cursor.curr();
+        let _link = cursor.prev();
+        cursor.delete(guard.backend())?;
+        #[cfg(feature = "pmem")]
+        {
+            if let Some(hooks) = &self.persist {
+                hooks.flush(
+                    _node.as_raw() as *const u8,
+                    core::mem::size_of::<Node<SoKey, Option<Slot<V>>>>(),
+                );
+                hooks.flush(
+                    _link as *const _ as *const u8,
+                    core::mem::size_of::<Atomic<Node<SoKey, Option<Slot<V>>>>>(),
+                );
+                hooks.fence();
+            }
+        }
+        Ok(())
+    }
+
+    /// Records an access to the node: stores the current coarse timestamp in its metadata word.
+    #[inline]
+    fn touch(&self, _node: &Node<SoKey, Option<Slot<V>>>) {
+        #[cfg(feature = "access-time")]
+        _node.meta().store(self.now_millis(), Ordering::Relaxed);
+    }
+
+    /// Milliseconds since the creation of the map.
+    #[cfg(feature = "access-time")]
+    fn now_millis(&self) -> usize {
+        self.clock_origin.elapsed().as_millis() as usize
+    }
+
+    /// Iterates the entries that were neither inserted nor looked up in the last `idle`, e.g. to
+    /// pick eviction candidates. Timestamps have a millisecond resolution, and the updates of
+    /// concurrent lookups may or may not be observed.
+    #[cfg(feature = "access-time")]
+    pub fn iter_idle_longer_than<'g>(
+        &'g self,
+        idle: Duration,
+        guard: &'g Guard,
+    ) -> impl Iterator<Item = (usize, &'g V)> + 'g {
+        let now = self.now_millis();
+        let idle = idle.as_millis() as usize;
+        self.nodes(guard)
+            .filter(|node| !Self::is_sentinel_key(*node.key()))
+            .filter(move |node| now.saturating_sub(node.meta().load(Ordering::Relaxed)) > idle)
+            .filter_map(move |node| {
+                let value = node.value().as_ref().unwrap().load(guard)?;
+                Some((Self::original_key(*node.key()), value))
+            })
+    }
+
     /// Creates a cursor and moves it to the bucket for the given index.  If the bucket doesn't
-    /// exist, recursively initializes the buckets.
-    fn lookup_bucket<'s>(&'s self, index: usize, guard: &'s Guard) -> Cursor<'s, usize, Option<V>> {
+    /// exist, initializes it and its uninitialized ancestors.
+    ///
+    /// The ancestors are initialized by a loop rather than recursion: it walks down the ancestor
+    /// chain to the closest initialized one, then inserts the sentinels back up from there, each
+    /// one starting from its parent's.
+    fn lookup_bucket<'s>(
+        &'s self,
+        index: usize,
+        guard: &'s Guard,
+    ) -> Cursor<'s, SoKey, Option<Slot<V>>> {
+        if let Some(cursor) = self.initialized_bucket(index, guard) {
+            return cursor;
+        }
+
+        let mut cursor = self.list.head(guard.backend());
+        let mut bucket = 0;
+        let mut ancestor = index;
+        while ancestor != 0 {
+            ancestor = Self::parent_bucket(ancestor);
+            if let Some(ancestor_cursor) = self.initialized_bucket(ancestor, guard) {
+                cursor = ancestor_cursor;
+                bucket = Self::child_bucket(ancestor, index);
+                break;
+            }
+        }
+
+        let mut yield_point = YieldPoint::new();
+        loop {
+            cursor = self.initialize_bucket(bucket, cursor, &mut yield_point, guard);
+            if bucket == index {
+                return cursor;
+            }
+            bucket = Self::child_bucket(bucket, index);
+        }
+    }
+
+    /// Returns a cursor at the sentinel of bucket `index` if it's initialized. Doesn't allocate
+    /// segments. Helps remove the sentinel if `compact` is removing it.
+    fn initialized_bucket<'s>(
+        &'s self,
+        index: usize,
+        guard: &'s Guard,
+    ) -> Option<Cursor<'s, SoKey, Option<Slot<V>>>> {
+        let bucket_store = self
+            .buckets
+            .try_get(index.reverse_bits(), guard)?
+            .as_backend();
+        let bucket = bucket_store.load(Ordering::Acquire, guard.backend());
+        if bucket.is_null() {
+            None
+        } else if bucket.tag() == Self::RETIRING {
+            let _ = self.retire_sentinel(index, bucket, guard);
+            None
+        } else {
+            Some(unsafe { Cursor::from_raw(bucket_store, bucket.as_raw()) })
+        }
+    }
+
+    /// Marks the sentinel `bucket` of bucket `index`, whose slot `compact` tagged `RETIRING`, and
+    /// nulls the slot. Returns `true` if this call marked it. The traversals unlink it.
+    ///
+    /// The slot is tagged before the sentinel is marked, so that a search that started from the
+    /// slot fails to unlink the sentinel through it, and a thread that finds the slot null
+    /// reinserts a sentinel rather than republishing the marked one.
+    fn retire_sentinel(
+        &self,
+        index: usize,
+        bucket: Shared<'_, Node<SoKey, Option<Slot<V>>>>,
+        guard: &Guard,
+    ) -> bool {
+        let next = unsafe { bucket.deref() }
+            .next()
+            .fetch_or(1, Ordering::AcqRel, guard.backend());
+        let marked = next.tag() == 0;
+        if marked {
+            if let Some(budget) = &self.budget {
+                budget.release(Self::NODE_BYTES);
+            }
+        }
+        let _ = self.buckets.compare_exchange(
+            index.reverse_bits(),
+            reclaim::Shared::from_backend(bucket),
+            reclaim::Shared::null(),
+            Ordering::Release,
+            Ordering::Relaxed,
+            guard,
+        );
+        marked
+    }
+
+    /// Returns the parent of bucket `index`, i.e. `index` with its most significant set bit
+    /// cleared. `index` must not be 0.
+    fn parent_bucket(index: usize) -> usize {
+        let msb = core::mem::size_of::<usize>() * 8 - 1 - index.leading_zeros() as usize;
+        index & !(1 << msb)
+    }
+
+    /// Returns the child of bucket `ancestor` on the ancestor chain of bucket `index`, i.e.
+    /// `ancestor` with the lowest bit of `index` it's missing set.
+    fn child_bucket(ancestor: usize, index: usize) -> usize {
+        let missing = index ^ ancestor;
+        ancestor | (missing & missing.wrapping_neg())
+    }
+
+    /// Inserts the sentinel of bucket `index` after `parent_cursor`, the sentinel of its parent
+    /// (or the head of the list for bucket 0), and publishes it.
+    fn initialize_bucket<'s>(
+        &'s self,
+        index: usize,
+        mut parent_cursor: Cursor<'s, SoKey, Option<Slot<V>>>,
+        yield_point: &mut YieldPoint,
+        guard: &'s Guard,
+    ) -> Cursor<'s, SoKey, Option<Slot<V>>> {
         let reversed_key = index.reverse_bits();
-        let bucket_store = self.buckets.get(reversed_key, guard);
-        let bucket = bucket_store.load(Ordering::Acquire, guard);
+        let bucket_store = self.buckets.get(reversed_key, guard).as_backend();
+        let bucket = bucket_store.load(Ordering::Acquire, guard.backend());
 
-        if !bucket.is_null() {
+        if bucket.tag() == Self::RETIRING {
+            let _ = self.retire_sentinel(index, bucket, guard);
+        } else if !bucket.is_null() {
             return unsafe {
                 Cursor::from_raw(
                     bucket_store,
@@ -62,65 +988,69 @@ impl<V> SplitOrderedList<V> where V: std::fmt::Debug {
             };
         }
 
-        // Initialize Bucket
-        let parent = {
-            let mut parent = self.size.load(Ordering::Acquire);
-            while {
-                parent = parent >> 1;
-                parent > index
-            } {};
-            index - parent
-        };
-        let mut parent_cursor =
-            if parent == 0
-            { self.list.head(guard) } else
-            { self.lookup_bucket(parent, guard) };
-
-        let mut sentinel_node = Owned::new(
-            Node::new(reversed_key, None)
-        );
+        yield_point.step();
+        let sentinel_key = Self::sentinel_key(index);
+        let mut sentinel_node = Owned::new(Node::new(sentinel_key, None));
 
+        let mut watchdog = Watchdog::new("SplitOrderedList::lookup_bucket");
         let inserted_cursor = loop {
             let (found, mut my_cursor) = loop {
                 let mut my_cursor = parent_cursor.clone();
 
-                match my_cursor.find_harris(&reversed_key, guard) {
+                match my_cursor.find_harris(&sentinel_key, guard.backend()) {
                     Ok(found) => break (found, my_cursor),
-                    Err(_) => ()
+                    Err(_) => self.counters.find_retry(),
                 }
+                // The parent's sentinel may be being removed by `compact`.
+                if index != 0 {
+                    parent_cursor = self.lookup_bucket(Self::parent_bucket(index), guard);
+                }
+                watchdog.tick(|| format!("bucket {}", index));
             };
 
             if found {
+                // Another thread inserted the sentinel after this one saw the bucket uninitialized.
+                self.counters.bucket_init_race();
                 drop(sentinel_node);
                 break my_cursor;
             }
 
-            match my_cursor.insert(sentinel_node, guard) {
-                Ok(_) => break my_cursor,
+            self.persist_new_node(&sentinel_node, guard);
+            match my_cursor.insert(sentinel_node, guard.backend()) {
+                Ok(_) => {
+                    self.persist_link(my_cursor.prev());
+                    if let Some(budget) = &self.budget {
+                        budget.charge(Self::NODE_BYTES);
+                    }
+                    break my_cursor;
+                }
                 Err(e) => { sentinel_node = e; }
             };
+            watchdog.tick(|| format!("bucket {}", index));
         };
 
-        match bucket_store.compare_and_set(
-            Shared::null(),
-            inserted_cursor.curr(),
+        // Publish the sentinel. If another thread already did, it published the same node.
+        let _ = self.buckets.compare_exchange(
+            reversed_key,
+            reclaim::Shared::null(),
+            reclaim::Shared::from_backend(inserted_cursor.curr()),
             Ordering::Release,
+            Ordering::Relaxed,
             guard
-        ) {
-            Err(e) => drop(e.new),
-            _ => ()
-        };
+        );
 
         inserted_cursor
     }
 
-    fn make_content_key(key: &usize) -> usize { (
-        key | SplitOrderedList::<V>::HI_MASK
+    /// Returns the split-ordered key of the regular node of `key`.
+    fn make_content_key(key: &usize) -> SoKey {
+        SoKey::regular(*key)
+    }
 
-        /*SplitOrderedList::<V>::MASK
-            & key
-            | SplitOrderedList::<V>::HI_MASK*/
-    ).reverse_bits() }
+    /// Returns the split-ordered key of the sentinel node of bucket `index`.
+    fn sentinel_key(index: usize) -> SoKey {
+        SoKey::sentinel(index)
+    }
 
     /// Moves the bucket cursor returned from `lookup_bucket` to the position of the given key.
     /// Returns `(size, found, cursor)`
@@ -128,80 +1058,1881 @@ impl<V> SplitOrderedList<V> where V: std::fmt::Debug {
         &'s self,
         key: &usize,
         guard: &'s Guard,
-    ) -> (usize, bool, Cursor<'s, usize, Option<V>>) {
+    ) -> (usize, bool, Cursor<'s, SoKey, Option<Slot<V>>>) {
         let size = self.size.load(Ordering::Acquire);
         let bucket_key = (key % size);
-        let cursor = self.lookup_bucket(bucket_key, guard);
 
         let content_key = SplitOrderedList::<V>::make_content_key(key);
+        let mut watchdog = Watchdog::new("SplitOrderedList::find");
         loop {
-            let mut my_cursor = cursor.clone();
-            match my_cursor.find_harris(&content_key, guard) {
+            // Looked up again after a failure, as the sentinel may be being removed by `compact`.
+            let mut my_cursor = self.lookup_bucket(bucket_key, guard);
+            match my_cursor.find_harris(&content_key, guard.backend()) {
                 Ok(found) => break (size, found, my_cursor),
-                Err(_) => ()
+                Err(_) => self.counters.find_retry(),
             }
+            watchdog.tick(|| format!("key {}, bucket {} of {}", key, bucket_key, size));
         }
     }
 
-    fn assert_valid_key(key: usize) {
-        assert_ne!(key.leading_zeros(), 0);
+    /// Returns `true` if the given split-ordered key belongs to a sentinel node.
+    fn is_sentinel_key(so_key: SoKey) -> bool {
+        !so_key.regular
     }
-}
 
-impl<V> NonblockingMap<usize, V> for SplitOrderedList<V> where V: std::fmt::Debug {
-    fn lookup<'a>(&'a self, key: &usize, guard: &'a Guard) -> Option<&'a V> {
-        Self::assert_valid_key(*key);
-        let (_, found, cursor) = self.find(key, guard);
+    /// Recovers the original key from the split-ordered key of a regular node, or the bucket
+    /// index from that of a sentinel node.
+    fn original_key(so_key: SoKey) -> usize {
+        so_key.reversed.reverse_bits()
+    }
 
-        if found {
-            cursor.lookup().unwrap().as_ref()
+    /// Returns the first live node whose split-ordered key is at least `so_key`.
+    fn seek<'g>(
+        &'g self,
+        so_key: SoKey,
+        guard: &'g Guard,
+    ) -> Shared<'g, Node<SoKey, Option<Slot<V>>>> {
+        if Self::is_sentinel_key(so_key) {
+            self.lookup_bucket(Self::original_key(so_key), guard).curr()
         } else {
-            None
+            self.find(&Self::original_key(so_key), guard).2.curr()
         }
     }
 
-    fn insert(&self, key: &usize, value: V, guard: &Guard) -> Result<(), V> {
-        Self::assert_valid_key(*key);
+    /// Iterates the live nodes of the list (including sentinels) in split order.
+    fn nodes<'g>(&'g self, guard: &'g Guard) -> Nodes<'g, V> {
+        Nodes {
+            list: self,
+            curr: self.list.head(guard.backend()).curr(),
+            last: None,
+            checkpoint: None,
+            yield_point: YieldPoint::new(),
+            guard,
+        }
+    }
+
+    /// Iterates the live nodes of the list (including sentinels) whose split-ordered key is
+    /// greater than `so_key`.
+    fn nodes_after<'g>(&'g self, so_key: SoKey, guard: &'g Guard) -> Nodes<'g, V> {
+        Nodes {
+            list: self,
+            curr: self.seek(so_key, guard),
+            last: None,
+            checkpoint: Some(so_key),
+            yield_point: YieldPoint::new(),
+            guard,
+        }
+    }
+
+    /// Returns the indices of the initialized buckets in split order, each with the next one, i.e.
+    /// the bounds of their chains for `chain_entries`. They are found by the range scan of the
+    /// bucket array; unlike `sentinels`, it doesn't walk the list.
+    fn chains(&self, guard: &Guard) -> Vec<(usize, Option<usize>)> {
+        let mut buckets = Vec::new();
+        let mut from = 0;
+        while let Some(index) = self.buckets.first_occupied(from, guard) {
+            buckets.push(index.reverse_bits());
+            from = some_or!(index.checked_add(1), break);
+        }
+        buckets
+            .iter()
+            .enumerate()
+            .map(|(i, &bucket)| (bucket, buckets.get(i + 1).copied()))
+            .collect()
+    }
+
+    /// Returns an iterator over the entries between the sentinel of the initialized bucket
+    /// `bucket` and the sentinel of `end`, or the end of the list, with the guarantees of `iter`.
+    /// With `end` the next initialized bucket in split order, these are the entries of the bucket
+    /// and of its uninitialized split descendants; the sentinels of the buckets initialized in
+    /// between are skipped. It never initializes a bucket, and yields nothing if `bucket` isn't
+    /// initialized.
+    fn chain_entries<'g>(
+        &'g self,
+        bucket: usize,
+        end: Option<usize>,
+        guard: &'g Guard,
+    ) -> impl Iterator<Item = (usize, &'g V)> + 'g {
+        let end = end.map(Self::sentinel_key);
+        // A sentinel that `compact` is removing still leads to its chain.
+        let sentinel = self
+            .buckets
+            .try_get(bucket.reverse_bits(), guard)
+            .map_or(Shared::null(), |store| {
+                store.as_backend().load(Ordering::Acquire, guard.backend())
+            });
+        let mut curr = unsafe { sentinel.as_ref() }.map_or(Shared::null(), |sentinel| {
+            sentinel
+                .next()
+                .load(Ordering::Acquire, guard.backend())
+                .with_tag(0)
+        });
+        core::iter::from_fn(move || loop {
+            let node = unsafe { curr.as_ref() }?;
+            if end.map_or(false, |end| *node.key() >= end) {
+                return None;
+            }
+            let next = node.next().load(Ordering::Acquire, guard.backend());
+            curr = next.with_tag(0);
+
+            // Skip logically deleted nodes, and the sentinels
+            if next.tag() != 0 || Self::is_sentinel_key(*node.key()) {
+                continue;
+            }
+            if let Some(value) = node.value().as_ref().unwrap().load(guard) {
+                return Some((Self::original_key(*node.key()), value));
+            }
+        })
+    }
+
+    /// Lookups the given key and returns a reference to the part of its value selected by `f`,
+    /// valid for the same guard lifetime as the value itself.
+    pub fn lookup_project<'a, U, F>(&'a self, key: &usize, f: F, guard: &'a Guard) -> Option<&'a U>
+    where
+        U: ?Sized,
+        F: FnOnce(&V) -> &U,
+    {
+        self.lookup(key, guard).map(f)
+    }
+
+    /// Lookups the given keys like `NonblockingMap::lookup`, e.g. for read-heavy batch workloads,
+    /// and returns the results in the order of `keys`.
+    ///
+    /// The keys are sorted in split order first, so that the keys of a bucket are looked up in
+    /// one pass over its chain, each search continuing from the previous one rather than from the
+    /// bucket sentinel.
+    pub fn lookup_many<'g>(&'g self, keys: &[usize], guard: &'g Guard) -> Vec<Option<&'g V>> {
+        let mut order: Vec<_> = (0..keys.len()).collect();
+        order.sort_by_key(|&index| Self::make_content_key(&keys[index]));
+
+        let mut results = vec![None; keys.len()];
+        // Bucket of the previous search, and the number of buckets and the cursor it ended at.
+        let mut last: Option<(usize, usize, Cursor<'_, SoKey, Option<Slot<V>>>)> = None;
+        for index in order {
+            let key = keys[index];
+            let size = self.size.load(Ordering::Acquire);
+            let content_key = Self::make_content_key(&key);
+            let continued = last.take().and_then(|(bucket, last_size, mut cursor)| {
+                if last_size == size && key % size == bucket {
+                    let found = cursor.find_harris(&content_key, guard.backend()).ok()?;
+                    Some((size, found, cursor))
+                } else {
+                    None
+                }
+            });
+            let (size, found, cursor) = continued.unwrap_or_else(|| self.find(&key, guard));
+
+            if found {
+                results[index] = cursor.lookup().unwrap().as_ref().unwrap().load(guard);
+                if results[index].is_some() {
+                    self.touch(unsafe { cursor.curr().deref() });
+                }
+            }
+            last = Some((key % size, size, cursor));
+        }
+        results
+    }
+
+    /// Lookups the given key like `NonblockingMap::lookup`, but pins the current thread itself,
+    /// e.g. for casual use where threading a guard through is a nuisance.
+    ///
+    /// ```
+    /// use cs492_concur_homework::SplitOrderedList;
+    ///
+    /// let list = SplitOrderedList::new();
+    /// assert_eq!(list.insert_pinned(&1, "one"), Ok(()));
+    /// assert_eq!(list.lookup_pinned(&1).as_deref(), Some(&"one"));
+    /// assert_eq!(list.delete_pinned(&1).as_deref(), Ok(&"one"));
+    /// assert!(list.lookup_pinned(&1).is_none());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn lookup_pinned(&self, key: &usize) -> Option<ValueRef<'_, V>> {
+        let guard = pin();
+        let value: *const V = self.lookup(key, &guard)?;
+        Some(ValueRef::new(value, guard))
+    }
+
+    /// Inserts a key-value pair like `NonblockingMap::insert`, but pins the current thread itself.
+    #[cfg(feature = "std")]
+    pub fn insert_pinned(&self, key: &usize, value: V) -> Result<(), V> {
+        self.insert(key, value, &pin())
+    }
+
+    /// Deletes the given key like `NonblockingMap::delete`, but pins the current thread itself.
+    #[cfg(feature = "std")]
+    pub fn delete_pinned(&self, key: &usize) -> Result<ValueRef<'_, V>, ()> {
+        let guard = pin();
+        let value: *const V = self.delete(key, &guard)?;
+        Ok(ValueRef::new(value, guard))
+    }
 
+    /// Lookups the given key like `NonblockingMap::lookup`, but tells a key that was not observed
+    /// at all (`Absent`) from a key whose node was observed already marked deleted (`Unlinked`),
+    /// e.g. to detect that a concurrent delete raced with a read-then-act protocol.
+    ///
+    /// Unlike `lookup`, this doesn't help unlink deleted nodes: it walks the bucket through the
+    /// marked nodes so that it can observe them.
+    pub fn lookup_verbose<'g>(&'g self, key: &usize, guard: &'g Guard) -> Lookup<'g, V> {
+        let size = self.size.load(Ordering::Acquire);
         let content_key = SplitOrderedList::<V>::make_content_key(key);
-        let mut node = Owned::new(
-            Node::new(content_key, Some(value))
+
+        let mut curr = self.lookup_bucket(key % size, guard).curr();
+        loop {
+            let node = some_or!(unsafe { curr.as_ref() }, return Lookup::Absent);
+            let next = node.next().load(Ordering::Acquire, guard.backend());
+
+            if *node.key() > content_key {
+                return Lookup::Absent;
+            }
+
+            if *node.key() == content_key {
+                if next.tag() != 0 {
+                    return Lookup::Unlinked;
+                }
+                // A sealed node is deleted, but not marked yet.
+                return match node.value().as_ref().unwrap().load(guard) {
+                    Some(value) => {
+                        self.touch(node);
+                        Lookup::Found(value)
+                    }
+                    None => Lookup::Unlinked,
+                };
+            }
+
+            curr = next.with_tag(0);
+        }
+    }
+
+    /// Moves the entry of `key` from `self` to `dst`, e.g. for rebalancing shards.
+    ///
+    /// A clone of the value is first inserted into `dst`, and then the value is deleted from
+    /// `self` only if it's still the value that was read. The transfer is NOT atomic: there is a
+    /// window in which the key is visible in both maps, and if the value is updated, or the key is
+    /// deleted and reinserted, in `self` during the window, the newer entry stays in `self` while
+    /// `dst` holds the value that was read. Returns `Err(())` if `key` is not in `self` or is
+    /// already in `dst`.
+    pub fn transfer(&self, key: &usize, dst: &SplitOrderedList<V>, guard: &Guard) -> Result<(), ()>
+    where
+        V: Clone,
+    {
+        let value = self.lookup(key, guard).ok_or(())?;
+        dst.insert(key, value.clone(), guard).map_err(|_| ())?;
+
+        // The value read can't be freed, and its address reused by a newer value, while `guard`
+        // is held.
+        let _ = self.remove_if(key, |other| core::ptr::eq(other, value), guard);
+        Ok(())
+    }
+
+    /// Inserts a key-value pair like `NonblockingMap::insert`, but distinguishes an occupied key
+    /// from an exhausted memory budget.
+    pub fn insert_budgeted(
+        &self,
+        key: &usize,
+        value: V,
+        guard: &Guard,
+    ) -> Result<(), InsertError<V>> {
+        self.insert_node(key, value, &mut None, guard).map(|_| ())
+    }
+
+    /// Inserts a key-value pair like `insert_budgeted` and returns the inserted value. If the key
+    /// is already in the map, gives the value back together with the existing value, so that the
+    /// caller can read the winner without a second lookup.
+    pub fn try_insert<'g>(
+        &'g self,
+        key: &usize,
+        value: V,
+        guard: &'g Guard,
+    ) -> Result<&'g V, TryInsertError<'g, V>> {
+        let mut value = value;
+        loop {
+            value = match self.insert_node(key, value, &mut None, guard) {
+                Ok(value) => return Ok(value),
+                Err(InsertError::Occupied(value)) => value,
+                Err(InsertError::OutOfBudget(value)) => {
+                    return Err(TryInsertError::OutOfBudget(value))
+                }
+            };
+
+            // The existing entry may be deleted in the meantime, in which case retry.
+            if let Some(existing) = self.lookup(key, guard) {
+                return Err(TryInsertError::Occupied(value, existing));
+            }
+        }
+    }
+
+    /// Returns the value of `key`, or inserts the value produced by `f` and returns it if the key
+    /// is absent, e.g. for a memoization cache.
+    ///
+    /// `f` is called at most once, and only if the key was observed absent. If a concurrent
+    /// insertion of the key wins the race, its value is returned and the produced value is
+    /// dropped. An error is returned under the same conditions as `insert_budgeted`, except that
+    /// the key is never reported occupied.
+    pub fn get_or_insert_with<'g, F>(
+        &'g self,
+        key: &usize,
+        f: F,
+        guard: &'g Guard,
+    ) -> Result<&'g V, InsertError<V>>
+    where
+        F: FnOnce() -> V,
+    {
+        let mut f = Some(f);
+        let mut pending = None;
+        loop {
+            if let Some(value) = self.lookup(key, guard) {
+                return Ok(value);
+            }
+
+            let value = pending.take().unwrap_or_else(|| (f.take().unwrap())());
+            match self.insert_node(key, value, &mut None, guard) {
+                Ok(value) => return Ok(value),
+                // Lost the race: return the winner's value, unless it's already deleted.
+                Err(InsertError::Occupied(value)) => pending = Some(value),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Replaces the value of `key` with `new` and returns the old value, which stays valid for the
+    /// guard lifetime. Gives `new` back if the key is absent.
+    ///
+    /// The value is swung in place with a CAS, without relinking the node. An update that races
+    /// with a `delete` of the key either lands before the deletion, which then returns the new
+    /// value, or fails.
+    pub fn update<'g>(&'g self, key: &usize, new: V, guard: &'g Guard) -> Result<&'g V, V> {
+        self.update_if(key, new, |_| true, guard)
+    }
+
+    /// Replaces the value of `key` with `new` only if the current value equals `expected`, like
+    /// `update` otherwise. Gives `new` back if the key is absent or its value differs, e.g. to
+    /// retry a read-modify-write loop:
+    ///
+    /// ```
+    /// use cs492_concur_homework::reclaim::pin;
+    /// use cs492_concur_homework::{NonblockingMap, SplitOrderedList};
+    ///
+    /// let list = SplitOrderedList::new();
+    /// let guard = pin();
+    /// list.insert(&1, 0, &guard).unwrap();
+    ///
+    /// let mut current = *list.lookup(&1, &guard).unwrap();
+    /// while list.compare_and_update(&1, &current, current + 1, &guard).is_err() {
+    ///     current = *list.lookup(&1, &guard).unwrap();
+    /// }
+    /// assert_eq!(list.lookup(&1, &guard), Some(&1));
+    /// ```
+    pub fn compare_and_update<'g>(
+        &'g self,
+        key: &usize,
+        expected: &V,
+        new: V,
+        guard: &'g Guard,
+    ) -> Result<&'g V, V>
+    where
+        V: PartialEq,
+    {
+        self.update_if(key, new, |current| current == expected, guard)
+    }
+
+    /// Replaces the value of `key` with `new` if `predicate` holds for the current value. The
+    /// predicate may be called several times if the value is replaced concurrently.
+    pub fn update_if<'g, P>(
+        &'g self,
+        key: &usize,
+        new: V,
+        predicate: P,
+        guard: &'g Guard,
+    ) -> Result<&'g V, V>
+    where
+        P: FnMut(&V) -> bool,
+    {
+        #[cfg(feature = "std")]
+        let _stripe = self.lock_log_stripe(*key);
+        let (_, found, cursor) = self.find(key, guard);
+        if !found {
+            return Err(new);
+        }
+
+        let node = unsafe { cursor.curr().deref() };
+        let slot = node.value().as_ref().unwrap();
+        let old = slot.replace_if(new, predicate, guard)?;
+        self.touch(node);
+        self.persist_value(slot, guard);
+        #[cfg(feature = "std")]
+        self.log_op(
+            |clone| LogOp::Update(*key, clone(slot.load(guard).unwrap())),
+            guard,
         );
+        Ok(old)
+    }
+
+    /// Installs `value` for `key` unconditionally: replaces the current value like `update`, or
+    /// inserts the key if it is absent. Returns the previous value, if any.
+    ///
+    /// An error is returned under the same conditions as `insert_budgeted`, except that the key is
+    /// never reported occupied. It is only raised when the key is absent, e.g. the budget is not
+    /// charged for a replacement.
+    pub fn upsert<'g>(
+        &'g self,
+        key: &usize,
+        value: V,
+        guard: &'g Guard,
+    ) -> Result<Option<&'g V>, InsertError<V>> {
+        let mut value = value;
+        loop {
+            value = match self.update(key, value, guard) {
+                Ok(old) => return Ok(Some(old)),
+                Err(value) => value,
+            };
+
+            value = match self.insert_node(key, value, &mut None, guard) {
+                Ok(_) => return Ok(None),
+                // Lost the race to an insertion: replace its value.
+                Err(InsertError::Occupied(value)) => value,
+                Err(err) => return Err(err),
+            };
+        }
+    }
+
+    /// Returns the entry of `key`, for multi-step logic on the key that reuses the position of a
+    /// single search in the list:
+    ///
+    /// ```
+    /// use cs492_concur_homework::reclaim::pin;
+    /// use cs492_concur_homework::SplitOrderedList;
+    ///
+    /// let list = SplitOrderedList::new();
+    /// let guard = pin();
+    /// for _ in 0..3 {
+    ///     list.entry(7, &guard)
+    ///         .and_modify(|count| count + 1)
+    ///         .or_insert(1)
+    ///         .unwrap();
+    /// }
+    /// assert_eq!(list.entry(7, &guard).or_insert(0), Ok(&3));
+    /// ```
+    ///
+    /// The entry is a snapshot: a concurrent operation may insert or delete the key before the
+    /// entry is used. The entry operations detect this and fall back to a fresh search, except
+    /// where documented otherwise.
+    pub fn entry<'g>(&'g self, key: usize, guard: &'g Guard) -> Entry<'g, V> {
+        let mut watchdog = Watchdog::new("SplitOrderedList::entry");
+        loop {
+            let (size, found, mut cursor) = self.find(&key, guard);
+            if !found {
+                return Entry::Vacant(VacantEntry {
+                    list: self,
+                    key,
+                    size,
+                    cursor,
+                    guard,
+                });
+            }
+
+            let node = unsafe { cursor.curr().deref() };
+            if node.value().as_ref().unwrap().load(guard).is_some() {
+                return Entry::Occupied(OccupiedEntry {
+                    list: self,
+                    key,
+                    cursor,
+                    guard,
+                });
+            }
 
+            // Help the delete that sealed the node to mark it, and retry.
+            let _ = self.delete_node(cursor, guard);
+            watchdog.tick(|| format!("key {}, {} buckets", key, size));
+        }
+    }
+
+    /// `insert_budgeted` that returns the inserted value. If given, the first attempt inserts at
+    /// `start`, a cursor (and the number of buckets it was found with) that was positioned for
+    /// the key by a search that didn't find it. On success, `start` is set to the cursor at the
+    /// inserted node, from which the search for a greater key in the same bucket can continue.
+    fn insert_node<'g>(
+        &'g self,
+        key: &usize,
+        value: V,
+        start: &mut Option<(usize, Cursor<'g, SoKey, Option<Slot<V>>>)>,
+        guard: &'g Guard,
+    ) -> Result<&'g V, InsertError<V>> {
+        if let Some(budget) = &self.budget {
+            if let Err(OutOfBudget) = budget.try_reserve(Self::NODE_BYTES) {
+                return Err(InsertError::OutOfBudget(value));
+            }
+        }
+
+        let content_key = SplitOrderedList::<V>::make_content_key(key);
+        let mut node = Owned::new(Node::new(content_key, Some(Slot::new(value))));
+        self.touch(&node);
+        // Stays valid under `guard` even if the value is replaced or deleted right away.
+        let value = node.value().as_ref().unwrap().peek(guard);
+
+        #[cfg(feature = "std")]
+        let _stripe = self.lock_log_stripe(*key);
+        let mut watchdog = Watchdog::new("SplitOrderedList::insert");
         let size = loop {
-            let (size, found, mut cursor) = self.find(key, guard);
+            let (size, found, mut cursor) = match start.take() {
+                Some((size, cursor)) => (size, false, cursor),
+                None => self.find(key, guard),
+            };
             if found {
+                let found = unsafe { cursor.curr().deref() };
+                if found.value().as_ref().unwrap().load(guard).is_none() {
+                    // Help the delete that sealed the node to mark it, and retry.
+                    let _ = self.delete_node(cursor, guard);
+                    watchdog.tick(|| format!("key {}, {} buckets", key, size));
+                    continue;
+                }
+
+                if let Some(budget) = &self.budget {
+                    budget.release(Self::NODE_BYTES);
+                }
                 let inner = *node.into_box();
-                return Err(inner.into_value().unwrap());
+                return Err(InsertError::Occupied(
+                    inner.into_value().unwrap().into_inner(),
+                ));
             }
 
-            match cursor.insert(node, guard) {
-                Ok(_) => break size,
-                Err(val) => node = val
+            self.persist_new_node(&node, guard);
+            match cursor.insert(node, guard.backend()) {
+                Ok(_) => {
+                    self.persist_link(cursor.prev());
+                    #[cfg(feature = "std")]
+                    self.log_op(|clone| LogOp::Insert(*key, clone(value)), guard);
+                    *start = Some((size, cursor));
+                    break size;
+                }
+                Err(val) => {
+                    self.counters.insert_cas_failure();
+                    node = val;
+                }
             }
+            watchdog.tick(|| format!("key {}, {} buckets", key, size));
         };
 
-        let count = self.count.fetch_add(1, Ordering::Relaxed);
-        if count > size * SplitOrderedList::<V>::LOAD_FACTOR {
-            self.size.compare_and_swap(size, size * 2, Ordering::Relaxed);
+        // The estimate from the stripe of the key filters the checks, and the exact count before
+        // the insertion confirms them.
+        let threshold = size * self.load_factor;
+        if Self::clamp_count(self.count.increment(*key)) > threshold {
+            let count = Self::clamp_count(self.count.sum()).saturating_sub(1);
+            if count > threshold {
+                self.counters.resize_trigger();
+                self.size
+                    .compare_and_swap(size, self.grown_size(size, count), Ordering::Relaxed);
+            }
         }
 
-        Ok(())
+        Ok(value)
     }
 
-    fn delete<'a>(&'a self, key: &usize, guard: &'a Guard) -> Result<&'a V, ()> {
-        Self::assert_valid_key(*key);
-        let (_, found, mut cursor) = self.find(key, guard);
+    /// Returns the number of buckets to grow `size` buckets to for `count` entries.
+    fn grown_size(&self, size: usize, count: usize) -> usize {
+        match self.growth_policy {
+            GrowthPolicy::Double => size * 2,
+            GrowthPolicy::Fit => {
+                let fit = ((count + self.load_factor - 1) / self.load_factor)
+                    .checked_next_power_of_two()
+                    .unwrap_or(size * 2);
+                cmp::max(size * 2, fit)
+            }
+        }
+    }
+
+    /// Inserts the given entries, replacing the values of the keys that are already in the map,
+    /// e.g. to build a large table quickly. Later entries replace earlier ones with the same key.
+    ///
+    /// The entries are inserted as by `insert_many`. Entries that don't fit in the memory budget
+    /// are dropped.
+    pub fn upsert_many<I>(&self, iter: I, guard: &Guard)
+    where
+        I: IntoIterator<Item = (usize, V)>,
+    {
+        let entries: Vec<_> = iter.into_iter().collect();
+        let keys: Vec<_> = entries.iter().map(|(key, _)| *key).collect();
+        for (key, result) in keys.iter().zip(self.insert_many(entries, guard)) {
+            if let Err(InsertError::Occupied(value)) = result {
+                let _ = self.upsert(key, value, guard);
+            }
+        }
+    }
+
+    /// Inserts the given entries like `insert_budgeted`, e.g. to bulk-load a table, and returns
+    /// the result of each entry in the order of `iter`. Of the entries with the same key, only the
+    /// first one can be inserted.
+    ///
+    /// The entries are sorted in split order first, so that the keys of a bucket are inserted
+    /// together, each search continuing from the previous insertion rather than from the bucket
+    /// sentinel.
+    pub fn insert_many<I>(&self, iter: I, guard: &Guard) -> Vec<Result<(), InsertError<V>>>
+    where
+        I: IntoIterator<Item = (usize, V)>,
+    {
+        let mut entries: Vec<_> = iter.into_iter().enumerate().collect();
+        // Stable, so that equal keys stay in the order of `iter`.
+        entries.sort_by_key(|(_, (key, _))| Self::make_content_key(key));
+
+        let mut results: Vec<_> = (0..entries.len()).map(|_| None).collect();
+        // Bucket of the previous insertion, and the number of buckets and the cursor at its node.
+        let mut last: Option<(usize, usize, Cursor<'_, SoKey, Option<Slot<V>>>)> = None;
+        for (index, (key, value)) in entries {
+            let size = self.size.load(Ordering::Acquire);
+            let content_key = Self::make_content_key(&key);
+            let mut start = last.take().and_then(|(bucket, last_size, mut cursor)| {
+                if last_size == size
+                    && key % size == bucket
+                    && cursor.find_harris(&content_key, guard.backend()) == Ok(false)
+                {
+                    Some((size, cursor))
+                } else {
+                    None
+                }
+            });
+
+            let result = self.insert_node(&key, value, &mut start, guard);
+            if result.is_ok() {
+                last = start.map(|(size, cursor)| (key % size, size, cursor));
+            }
+            results[index] = Some(result.map(|_| ()));
+        }
+
+        results.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// Deletes the given key and moves its value out, e.g. so that the caller can release the
+    /// resources it owns right away rather than when the epoch GC reclaims the node.
+    ///
+    /// The value is unlinked from its node, and moved out once no reference to it obtained from
+    /// `lookup` or `delete` can be alive: this blocks until every thread pinned at the time of the
+    /// deletion has unpinned. Only the node is left to the epoch GC.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread is pinned, since the wait would never end.
+    #[cfg(feature = "std")]
+    pub fn remove_owned(&self, key: &usize) -> Result<V, ()> {
+        assert_unpinned();
+        let value = {
+            let guard = &pin();
+            let (slot, _) = self.delete_slot(key, |_| true, guard)?;
+            slot.unlink_value(guard)
+        };
+        synchronize();
+        Ok(unsafe { Slot::take(value) })
+    }
+
+    /// Deletes the given key only if `predicate` holds for its current value, and returns the
+    /// deleted value, e.g. to invalidate a cache entry only if it still holds a stale value.
+    /// Returns `Err(())` if the key is absent or the predicate fails.
+    ///
+    /// The check and the deletion are atomic: the value is sealed only if it's still the one the
+    /// predicate held for, so a concurrent `update` either lands before the check, which then sees
+    /// the new value, or fails. The predicate may be called several times under contention.
+    pub fn remove_if<'g, P>(
+        &'g self,
+        key: &usize,
+        predicate: P,
+        guard: &'g Guard,
+    ) -> Result<&'g V, ()>
+    where
+        P: Fn(&V) -> bool,
+    {
+        self.delete_slot(key, predicate, guard)
+            .map(|(_, value)| value)
+    }
+
+    /// `NonblockingMap::delete` that also returns the sealed slot of the value, if `predicate`
+    /// holds for it.
+    fn delete_slot<'g, P>(
+        &'g self,
+        key: &usize,
+        predicate: P,
+        guard: &'g Guard,
+    ) -> Result<(&'g Slot<V>, &'g V), ()>
+    where
+        P: FnMut(&V) -> bool,
+    {
+        #[cfg(feature = "std")]
+        let _stripe = self.lock_log_stripe(*key);
+        let (_, found, cursor) = self.find(key, guard);
         if !found {
-            return Err(())
+            return Err(());
+        }
+        self.delete_at(key, cursor, predicate, guard)
+    }
+
+    /// Deletes the node of `key` at `cursor` if `predicate` holds for its value, with the op log
+    /// stripe of the key locked.
+    fn delete_at<'g, P>(
+        &'g self,
+        key: &usize,
+        cursor: Cursor<'g, SoKey, Option<Slot<V>>>,
+        predicate: P,
+        guard: &'g Guard,
+    ) -> Result<(&'g Slot<V>, &'g V), ()>
+    where
+        P: FnMut(&V) -> bool,
+    {
+        // Sealing the value is the linearization point, so that a concurrent `update` either
+        // replaces the value before the deletion or fails.
+        let node = cursor.curr();
+        let slot = unsafe { node.deref() }.value().as_ref().unwrap();
+        let value = slot.seal_if(predicate, guard).ok_or(())?;
+        // Fails if a concurrent insertion already helped mark the node.
+        let _ = self.delete_node(cursor, guard);
+
+        #[cfg(feature = "std")]
+        self.log_op(|_| LogOp::Delete(*key), guard);
+        if let Some(budget) = &self.budget {
+            budget.release(Self::NODE_BYTES);
+        }
+        let estimate = self.count.decrement(*key);
+        let size = self.size.load(Ordering::Relaxed);
+        let threshold = size / (2 * self.load_factor);
+        if size > self.min_size
+            && Self::clamp_count(estimate) < threshold
+            && Self::clamp_count(self.count.sum()) < threshold
+        {
+            self.counters.resize_trigger();
+            self.size
+                .compare_and_swap(size, size / 2, Ordering::Relaxed);
+        }
+        Ok((slot, value))
+    }
+
+    /// Deletes the entries for which `f` returns `false`, e.g. for an eviction sweep of a cache.
+    ///
+    /// The entries are visited like `iter`, and each failing entry is deleted like `delete`, so
+    /// the sweep runs concurrently with the other operations. An entry inserted or updated
+    /// concurrently may or may not be visited, and one whose value is replaced after `f` looked at
+    /// it is still deleted.
+    pub fn retain<F>(&self, mut f: F, guard: &Guard)
+    where
+        F: FnMut(usize, &V) -> bool,
+    {
+        for (key, value) in self.iter(guard) {
+            if !f(key, value) {
+                // A concurrent delete is fine: the entry is gone either way.
+                let _ = self.delete(&key, guard);
+            }
+        }
+    }
+
+    /// Deletes all entries. Entries inserted concurrently may or may not be deleted.
+    ///
+    /// `count` is kept exact by the deletions, and the number of buckets shrinks with it as usual.
+    /// The sentinels of the unused buckets stay in the list until `compact` is called.
+    pub fn clear(&self, guard: &Guard) {
+        self.retain(|_, _| false, guard);
+    }
+
+    /// Returns the current number of buckets.
+    pub fn bucket_count(&self) -> usize {
+        self.size.load(Ordering::Acquire)
+    }
+
+    /// Returns the counters of the contended paths since the list was created, e.g. for
+    /// performance analysis. The counters of its bucket array are in `GrowableArray::metrics`.
+    #[cfg(feature = "instrument")]
+    pub fn metrics(&self) -> SplitOrderedListMetrics {
+        self.counters.metrics()
+    }
+
+    /// Removes the sentinel nodes of the buckets that are no longer in use after the table shrank,
+    /// and nulls their bucket slots. Returns the number of sentinels this call removed.
+    ///
+    /// Halving the number of buckets is lock-free, but the unused sentinels are left in the list
+    /// (they only lengthen the searches of their parent buckets) until this is called. The
+    /// removal is concurrent with the other operations: the slot of a sentinel is tagged
+    /// `RETIRING` first, the threads that find the tag help mark the sentinel and null the slot,
+    /// and the traversals unlink the marked sentinel like any deleted node. A thread that read the
+    /// number of buckets before it was halved may reinsert a removed sentinel.
+    ///
+    /// The bucket segments that became empty are deallocated by `shrink_buckets`.
+    pub fn compact(&self, guard: &Guard) -> usize {
+        let size = self.size.load(Ordering::Acquire);
+        let mut removed = 0;
+        for bucket in self.sentinels(guard) {
+            if bucket < size {
+                continue;
+            }
+            let reversed_key = bucket.reverse_bits();
+            let sentinel = some_or!(self.buckets.try_get(reversed_key, guard), continue)
+                .as_backend()
+                .load(Ordering::Acquire, guard.backend());
+            // Not published yet, or already being removed.
+            if sentinel.is_null() || sentinel.tag() != 0 {
+                continue;
+            }
+            let retiring = sentinel.with_tag(Self::RETIRING);
+            if self
+                .buckets
+                .compare_exchange(
+                    reversed_key,
+                    reclaim::Shared::from_backend(sentinel),
+                    reclaim::Shared::from_backend(retiring),
+                    Ordering::AcqRel,
+                    Ordering::Relaxed,
+                    guard,
+                )
+                .is_err()
+            {
+                continue;
+            }
+            if self.retire_sentinel(bucket, retiring, guard) {
+                removed += 1;
+            }
+            debug_assert!(
+                unsafe { sentinel.deref() }
+                    .next()
+                    .load(Ordering::Relaxed, guard.backend())
+                    .tag()
+                    != 0
+            );
+
+            // Unlinks it now rather than on the next traversal.
+            let mut cursor = self.lookup_bucket(Self::parent_bucket(bucket), guard);
+            let _ = cursor.find_harris(&Self::sentinel_key(bucket), guard.backend());
+        }
+        removed
+    }
+
+    /// Deallocates the bucket segments whose slots are all null, e.g. after `compact`. Returns the
+    /// number of deallocated segments.
+    ///
+    /// It takes `&mut self` like `GrowableArray::shrink`.
+    pub fn shrink_buckets(&mut self) -> usize {
+        // SAFETY: `&mut self` excludes concurrent accesses.
+        self.buckets.shrink(unsafe { unprotected() })
+    }
+
+    /// Returns the number of entries. The count is updated after the entries are linked or
+    /// unlinked, so it may be briefly off under concurrent updates, but it is never negative.
+    ///
+    /// This sums all the stripes of the count. See `len_approx` for a cheaper estimate.
+    pub fn len(&self) -> usize {
+        Self::clamp_count(self.count.sum())
+    }
+
+    /// Returns an estimate of the number of entries that only reads one stripe of the count, e.g.
+    /// for frequent polling. It's accurate as long as the keys are spread evenly over the stripes,
+    /// which holds for sequences of keys and for hashes.
+    pub fn len_approx(&self) -> usize {
+        Self::clamp_count(self.count.estimate(0))
+    }
+
+    /// Returns `true` if the map has no entries, under the same caveat as `len`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads a value of `count` that wrapped below zero as zero.
+    fn clamp_count(count: usize) -> usize {
+        if count > isize::max_value() as usize {
+            0
+        } else {
+            count
+        }
+    }
+
+    /// Returns the indices of the initialized buckets, i.e. the buckets whose sentinel node is in
+    /// the list, in list (split) order. Intended for debugging and for checking the invariants of
+    /// the algorithm.
+    pub fn sentinels(&self, guard: &Guard) -> Vec<usize> {
+        self.nodes(guard)
+            .map(|node| *node.key())
+            .filter(|so_key| Self::is_sentinel_key(*so_key))
+            .map(Self::original_key)
+            .collect()
+    }
+
+    /// Returns an iterator over the initialized buckets in list (split) order, yielding
+    /// `(bucket_index, chain_len, first_key)` per bucket, where `chain_len` is the number of
+    /// entries in the bucket and `first_key` is the key of the first of them, if any.
+    ///
+    /// The per-bucket metadata is not a consistent snapshot if the list is modified
+    /// concurrently.
+    pub fn buckets<'g>(&'g self, guard: &'g Guard) -> Buckets<'g, V> {
+        Buckets {
+            nodes: self.nodes(guard).peekable(),
+        }
+    }
+
+    /// Returns statistics of the shape of the list, e.g. to check that the split ordering keeps
+    /// the buckets short. They are computed in one pass over the list, which is not a consistent
+    /// snapshot if the list is modified concurrently.
+    pub fn stats(&self, guard: &Guard) -> SplitOrderedListStats {
+        let bucket_count = self.bucket_count();
+        let chain_lengths: Vec<_> = self
+            .buckets(guard)
+            .map(|(bucket, chain_len, _)| (bucket, chain_len))
+            .collect();
+
+        let entries: usize = chain_lengths.iter().map(|&(_, len)| len).sum();
+        // The i-th entry of a chain is reached after walking i nodes from the sentinel.
+        let total_probes: usize = chain_lengths
+            .iter()
+            .map(|&(_, len)| len * (len + 1) / 2)
+            .sum();
+        let max_probe_distance = chain_lengths.iter().map(|&(_, len)| len).max().unwrap_or(0);
+        let mean_probe_distance = if entries == 0 {
+            0.0
+        } else {
+            total_probes as f64 / entries as f64
+        };
+
+        SplitOrderedListStats {
+            bucket_count,
+            sentinels: chain_lengths.len(),
+            chain_lengths,
+            max_probe_distance,
+            mean_probe_distance,
+            load_factor: entries as f64 / bucket_count as f64,
+        }
+    }
+
+    /// Returns an iterator over the entries, yielding `(key, &value)` in split order, i.e. not in
+    /// the order of the keys. Every key that is present during the whole iteration is yielded
+    /// exactly once; keys inserted or deleted concurrently may or may not be yielded.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> Iter<'g, V> {
+        Iter {
+            nodes: self.nodes(guard),
+        }
+    }
+
+    /// Returns an iterator over the keys, in the same order and with the same guarantees as
+    /// `iter`.
+    pub fn keys<'g>(&'g self, guard: &'g Guard) -> Keys<'g, V> {
+        Keys {
+            inner: self.iter(guard),
+        }
+    }
+
+    /// Returns an iterator over the values, in the same order and with the same guarantees as
+    /// `iter`.
+    pub fn values<'g>(&'g self, guard: &'g Guard) -> Values<'g, V> {
+        Values {
+            inner: self.iter(guard),
+        }
+    }
+
+    /// Returns the entry with the smallest key, e.g. for a simple priority queue. An entry inserted
+    /// or deleted concurrently may or may not be considered, like in `iter`.
+    ///
+    /// The order of the keys is unrelated to the split order, so this walks the chains of the
+    /// initialized buckets in the order of their indices, and stops at the first one whose index
+    /// is not less than the smallest key so far: the keys in the chain of a bucket belong to it
+    /// or to its split descendants, so they are at least its index. It walks every entry in the
+    /// worst case, but only the first few chains when the small keys are present. It never
+    /// initializes a bucket.
+    pub fn first<'g>(&'g self, guard: &'g Guard) -> Option<(usize, &'g V)> {
+        let mut chains = self.chains(guard);
+        chains.sort_unstable();
+        let mut first: Option<(usize, &'g V)> = None;
+        for (bucket, end) in chains {
+            if matches!(first, Some((key, _)) if key <= bucket) {
+                break;
+            }
+            for (key, value) in self.chain_entries(bucket, end, guard) {
+                if first.map_or(true, |(min, _)| key < min) {
+                    first = Some((key, value));
+                }
+            }
+        }
+        first
+    }
+
+    /// Returns an iterator over the entries in the order of the keys, with the guarantees of
+    /// `iter`, e.g. for range-style reports.
+    ///
+    /// The split order of the list is unrelated to the order of the keys, so this collects the
+    /// entries and sorts them first: it takes `O(n log n)` time and `O(n)` space before yielding
+    /// the first entry.
+    pub fn iter_key_order<'g>(&'g self, guard: &'g Guard) -> alloc::vec::IntoIter<(usize, &'g V)> {
+        let mut entries: Vec<_> = self.iter(guard).collect();
+        entries.sort_unstable_by_key(|&(key, _)| key);
+        entries.into_iter()
+    }
+
+    /// Returns clones of the entries, in split order, e.g. for debugging or periodic dumps while
+    /// the writers continue.
+    ///
+    /// The snapshot is weakly consistent, with the guarantees of `iter`: it contains every key
+    /// that is present during the whole call, with one of the values the key had during the call,
+    /// no key that is absent during the whole call, and each key at most once. The keys inserted
+    /// or deleted during the call may or may not be in it, so it may not match the contents of the
+    /// map at any single point in time.
+    pub fn snapshot(&self, guard: &Guard) -> Vec<(usize, V)>
+    where
+        V: Clone,
+    {
+        self.iter(guard)
+            .map(|(key, value)| (key, value.clone()))
+            .collect()
+    }
+
+    /// Returns a deep copy of the list rebuilt from a `snapshot`, with its guarantees, e.g. to
+    /// checkpoint a table before a stress run and diff it afterwards.
+    ///
+    /// The copy has the same number of buckets and resize policy, but no memory budget, op log or
+    /// persistence hooks.
+    pub fn clone_snapshot(&self, guard: &Guard) -> Self
+    where
+        V: Clone,
+    {
+        let list = Self {
+            size: AtomicUsize::new(self.bucket_count()),
+            load_factor: self.load_factor,
+            min_size: self.min_size,
+            growth_policy: self.growth_policy,
+            ..Self::default()
+        };
+        // The keys of a snapshot are unique, so every insertion succeeds.
+        let _ = list.insert_many(self.snapshot(guard), guard);
+        list
+    }
+
+    /// Removes all entries and returns them as an iterator of owned `(key, value)` pairs, in split
+    /// order, e.g. to migrate the table into another data structure.
+    ///
+    /// The entries are deleted up front, and their values are moved out as in `remove_owned`,
+    /// after a single wait for the concurrent readers. The entries inserted concurrently may be
+    /// left in the map, and those deleted concurrently are not yielded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the current thread is pinned, like `remove_owned`.
+    #[cfg(feature = "std")]
+    pub fn drain(&self) -> Drain<V> {
+        assert_unpinned();
+        let values = {
+            let guard = &pin();
+            let keys: Vec<_> = self.iter(guard).map(|(key, _)| key).collect();
+            keys.into_iter()
+                .filter_map(|key| {
+                    let (slot, _) = self.delete_slot(&key, |_| true, guard).ok()?;
+                    Some((key, slot.unlink_value(guard)))
+                })
+                .collect::<Vec<_>>()
+        };
+        synchronize();
+        Drain {
+            entries: values
+                .into_iter()
+                .map(|(key, value)| (key, unsafe { Slot::take(value) }))
+                .collect::<Vec<_>>()
+                .into_iter(),
+        }
+    }
+
+    /// Returns an iterator over the entries in chunks of at most `n` cloned `(key, value)` pairs.
+    ///
+    /// Each chunk is collected under its own guard, so the consumer doesn't keep the epoch pinned
+    /// while processing a chunk. The next chunk resumes right after the last entry of the previous
+    /// chunk in the split order; entries inserted or deleted in between may or may not be
+    /// observed.
+    #[cfg(feature = "std")]
+    pub fn iter_chunks(&self, n: usize) -> IterChunks<'_, V>
+    where
+        V: Clone,
+    {
+        assert!(n > 0, "chunk size must be positive");
+        IterChunks {
+            list: self,
+            chunk_size: n,
+            last: None,
+            done: false,
+        }
+    }
+
+    /// Runs `f` as a transaction over the keys in the bucket of `key`, i.e. the keys `k` with
+    /// `k % n == key % n` where `n` is the number of buckets when the transaction starts.
+    ///
+    /// The operations of the transaction remain lock-free, but the locks of the bucket are held
+    /// while `f` runs, so the transaction is atomic with respect to the other transactions over
+    /// any of its keys (e.g. "remove A and insert B" is never observed half-done by another
+    /// transaction). It is NOT atomic with respect to the plain `NonblockingMap` operations, which
+    /// never take the locks.
+    #[cfg(feature = "std")]
+    pub fn bucket_txn<'a, F, R>(&'a self, key: &usize, guard: &'a Guard, f: F) -> R
+    where
+        F: FnOnce(&BucketTxn<'a, V>) -> R,
+    {
+        let size = self.size.load(Ordering::Acquire);
+        let bucket = key % size;
+
+        // The keys of the bucket are those of the stripes congruent to it modulo the largest power
+        // of two dividing both `size` and `TXN_STRIPES`. Two transactions sharing a key thus share
+        // a stripe, and they lock their stripes in increasing order so as not to deadlock.
+        let step = 1 << cmp::min(size.trailing_zeros(), TXN_STRIPES.trailing_zeros());
+        let _locks = self.txn_locks.stripes(guard)[bucket % step..]
+            .iter()
+            .step_by(step)
+            .map(|stripe| stripe.lock().unwrap())
+            .collect::<Vec<_>>();
+        f(&BucketTxn {
+            list: self,
+            size,
+            bucket,
+            guard,
+        })
+    }
+}
+
+/// Transaction over the keys of a bucket of a `SplitOrderedList`.
+///
+/// This `struct` is created by [`SplitOrderedList::bucket_txn`]. Every operation panics if the
+/// given key is not in the bucket of the transaction.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct BucketTxn<'a, V> {
+    list: &'a SplitOrderedList<V>,
+    /// number of buckets when the transaction started
+    size: usize,
+    bucket: usize,
+    guard: &'a Guard,
+}
+
+#[cfg(feature = "std")]
+impl<'a, V> BucketTxn<'a, V> {
+    fn assert_in_bucket(&self, key: usize) {
+        assert_eq!(
+            key % self.size,
+            self.bucket,
+            "key {} is not in the transaction's bucket",
+            key
+        );
+    }
+
+    /// Lookups the given key.
+    pub fn lookup(&self, key: &usize) -> Option<&'a V> {
+        self.assert_in_bucket(*key);
+        self.list.lookup(key, self.guard)
+    }
+
+    /// Inserts a key-value pair.
+    pub fn insert(&self, key: &usize, value: V) -> Result<(), V> {
+        self.assert_in_bucket(*key);
+        self.list.insert(key, value, self.guard)
+    }
+
+    /// Deletes the given key and its value.
+    pub fn delete(&self, key: &usize) -> Result<&'a V, ()> {
+        self.assert_in_bucket(*key);
+        self.list.delete(key, self.guard)
+    }
+}
+
+/// Iterator over the live nodes of the underlying list, in split order.
+///
+/// Only nodes whose key is greater than the key of the previously yielded node (the checkpoint)
+/// are yielded, so keys are yielded in strictly increasing split order and never twice. Nodes
+/// that are marked deleted when the iterator reaches them are skipped. If the previously yielded
+/// node is deleted after the iterator passed it, its `next` pointer may lead into an unlinked
+/// part of the list, so the iterator restarts with a fresh search from the checkpoint key.
+///
+/// Consequently, every key that is present during the whole iteration is yielded exactly once,
+/// and keys inserted or deleted during the iteration may or may not be yielded.
+#[derive(Debug)]
+struct Nodes<'g, V> {
+    list: &'g SplitOrderedList<V>,
+    /// next node to visit
+    curr: Shared<'g, Node<SoKey, Option<Slot<V>>>>,
+    /// the previously yielded node
+    last: Option<&'g Node<SoKey, Option<Slot<V>>>>,
+    /// key of the previously yielded node
+    checkpoint: Option<SoKey>,
+    yield_point: YieldPoint,
+    guard: &'g Guard,
+}
+
+impl<'g, V> Iterator for Nodes<'g, V> {
+    type Item = &'g Node<SoKey, Option<Slot<V>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(last) = self.last.take() {
+            let next = last.next().load(Ordering::Acquire, self.guard.backend());
+            self.curr = if next.tag() == 0 {
+                next
+            } else {
+                // The previously yielded node has been deleted: recover from the checkpoint.
+                self.list.seek(self.checkpoint.unwrap(), self.guard)
+            };
+        }
+
+        loop {
+            let node = unsafe { self.curr.as_ref() }?;
+            self.yield_point.step();
+            let next = node.next().load(Ordering::Acquire, self.guard.backend());
+            self.curr = next.with_tag(0);
+
+            // Skip logically deleted nodes
+            if next.tag() != 0 {
+                continue;
+            }
+
+            // Skip nodes at or before the checkpoint, e.g. the checkpoint node itself after
+            // recovery.
+            if self
+                .checkpoint
+                .map_or(false, |checkpoint| *node.key() <= checkpoint)
+            {
+                continue;
+            }
+
+            self.checkpoint = Some(*node.key());
+            self.last = Some(node);
+            return Some(node);
+        }
+    }
+}
+
+/// Iterator over the initialized buckets of a `SplitOrderedList`.
+///
+/// This `struct` is created by [`SplitOrderedList::buckets`].
+#[derive(Debug)]
+pub struct Buckets<'g, V> {
+    nodes: Peekable<Nodes<'g, V>>,
+}
+
+impl<'g, V> Iterator for Buckets<'g, V> {
+    type Item = (usize, usize, Option<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sentinel = self.nodes.next()?;
+        let mut chain_len = 0;
+        let mut first_key = None;
+
+        while let Some(node) = self.nodes.peek() {
+            let so_key = *node.key();
+            if SplitOrderedList::<V>::is_sentinel_key(so_key) {
+                break;
+            }
+
+            if first_key.is_none() {
+                first_key = Some(SplitOrderedList::<V>::original_key(so_key));
+            }
+            chain_len += 1;
+            let _ = self.nodes.next();
+        }
+
+        Some((
+            SplitOrderedList::<V>::original_key(*sentinel.key()),
+            chain_len,
+            first_key,
+        ))
+    }
+}
+
+/// Iterator over the entries of a `SplitOrderedList`.
+///
+/// This `struct` is created by [`SplitOrderedList::iter`].
+#[derive(Debug)]
+pub struct Iter<'g, V> {
+    nodes: Nodes<'g, V>,
+}
+
+impl<'g, V> Iterator for Iter<'g, V> {
+    type Item = (usize, &'g V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let guard = self.nodes.guard;
+        self.nodes.find_map(|node| {
+            let value = node.value().as_ref()?.load(guard)?;
+            Some((SplitOrderedList::<V>::original_key(*node.key()), value))
+        })
+    }
+}
+
+/// Iterator over the keys of a `SplitOrderedList`.
+///
+/// This `struct` is created by [`SplitOrderedList::keys`].
+#[derive(Debug)]
+pub struct Keys<'g, V> {
+    inner: Iter<'g, V>,
+}
+
+impl<'g, V> Iterator for Keys<'g, V> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, _)| key)
+    }
+}
+
+/// Iterator over the values of a `SplitOrderedList`.
+///
+/// This `struct` is created by [`SplitOrderedList::values`].
+#[derive(Debug)]
+pub struct Values<'g, V> {
+    inner: Iter<'g, V>,
+}
+
+impl<'g, V> Iterator for Values<'g, V> {
+    type Item = &'g V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+}
+
+/// Owning iterator over the entries of a `SplitOrderedList`, in split order.
+///
+/// This `struct` is created by the `into_iter` method on `SplitOrderedList` (provided by the
+/// `IntoIterator` trait).
+///
+/// The values are moved out of the nodes as they're yielded. The nodes, including the sentinels
+/// and the deleted nodes that are still linked, are freed with the list once the iterator is
+/// dropped, and the values that are not yielded with them.
+#[derive(Debug)]
+pub struct IntoIter<V> {
+    list: SplitOrderedList<V>,
+    /// next node to visit
+    curr: Shared<'static, Node<SoKey, Option<Slot<V>>>>,
+}
+
+impl<V> Iterator for IntoIter<V> {
+    type Item = (usize, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // SAFETY: the list is owned by the iterator, so no other thread accesses it.
+        let guard = unsafe { unprotected() };
+        loop {
+            let node = unsafe { self.curr.as_ref() }?;
+            self.curr = node.next().load(Ordering::Relaxed, guard.backend());
+            // The sentinels have no slot, and the deleted entries a sealed one.
+            if let Some(value) = node
+                .value()
+                .as_ref()
+                .and_then(|slot| slot.take_unsealed(guard))
+            {
+                let key = SplitOrderedList::<V>::original_key(*node.key());
+                return Some((key, value));
+            }
+        }
+    }
+}
+
+/// Entry of a key of a `SplitOrderedList`.
+///
+/// This `enum` is created by [`SplitOrderedList::entry`].
+#[derive(Debug)]
+pub enum Entry<'g, V> {
+    /// The key was in the map.
+    Occupied(OccupiedEntry<'g, V>),
+    /// The key was not in the map.
+    Vacant(VacantEntry<'g, V>),
+}
+
+impl<'g, V> Entry<'g, V> {
+    /// Returns the key of the entry.
+    pub fn key(&self) -> usize {
+        match self {
+            Self::Occupied(entry) => entry.key,
+            Self::Vacant(entry) => entry.key,
         }
+    }
 
-        match cursor.delete(guard) {
-            Ok(v) => {
-                self.count.fetch_sub(1, Ordering::Relaxed);
-                Ok(v.as_ref().unwrap())
+    /// Returns the value of the key, or inserts `value` if the key is vacant. An error is returned
+    /// under the same conditions as `SplitOrderedList::get_or_insert_with`.
+    pub fn or_insert(self, value: V) -> Result<&'g V, InsertError<V>> {
+        match self {
+            Self::Occupied(entry) => match entry.get() {
+                Some(current) => Ok(current),
+                None => entry
+                    .list
+                    .get_or_insert_with(&entry.key, || value, entry.guard),
             },
-            Err(_) => Err(())
+            Self::Vacant(entry) => {
+                let (list, key, guard) = (entry.list, entry.key, entry.guard);
+                match entry.insert(value) {
+                    Err(InsertError::Occupied(value)) => {
+                        list.get_or_insert_with(&key, || value, guard)
+                    }
+                    result => result,
+                }
+            }
+        }
+    }
+
+    /// Replaces the value with the result of `f` on it if the key is occupied, and returns the
+    /// entry for further operations. `f` may be called several times if the value is replaced
+    /// concurrently; if the key is deleted concurrently, the entry is left unmodified.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnMut(&V) -> V,
+    {
+        match self {
+            Self::Occupied(entry) => {
+                entry.modify(f);
+                Self::Occupied(entry)
+            }
+            vacant => vacant,
         }
     }
 }
+
+/// Entry of a key that was in a `SplitOrderedList`. See [`Entry`].
+pub struct OccupiedEntry<'g, V> {
+    list: &'g SplitOrderedList<V>,
+    key: usize,
+    /// cursor at the node of the key
+    cursor: Cursor<'g, SoKey, Option<Slot<V>>>,
+    guard: &'g Guard,
+}
+
+impl<'g, V> core::fmt::Debug for OccupiedEntry<'g, V>
+where
+    V: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OccupiedEntry")
+            .field("key", &self.key)
+            .field("value", &self.get())
+            .finish()
+    }
+}
+
+impl<'g, V> OccupiedEntry<'g, V> {
+    fn slot(&self) -> &'g Slot<V> {
+        unsafe { self.cursor.curr().deref() }
+            .value()
+            .as_ref()
+            .unwrap()
+    }
+
+    /// Returns the key of the entry.
+    pub fn key(&self) -> usize {
+        self.key
+    }
+
+    /// Returns the current value, or `None` if the key was deleted concurrently.
+    pub fn get(&self) -> Option<&'g V> {
+        self.slot().load(self.guard)
+    }
+
+    /// Replaces the value with `new` like `SplitOrderedList::update`, without searching the key.
+    pub fn insert(&self, new: V) -> Result<&'g V, V> {
+        self.update_if(new, |_| true)
+    }
+
+    /// Replaces the value with the result of `f` on it. See [`Entry::and_modify`].
+    fn modify<F>(&self, mut f: F)
+    where
+        F: FnMut(&V) -> V,
+    {
+        while let Some(current) = self.get() {
+            if self
+                .update_if(f(current), |value| core::ptr::eq(value, current))
+                .is_ok()
+            {
+                return;
+            }
+        }
+    }
+
+    fn update_if<P>(&self, new: V, predicate: P) -> Result<&'g V, V>
+    where
+        P: FnMut(&V) -> bool,
+    {
+        #[cfg(feature = "std")]
+        let _stripe = self.list.lock_log_stripe(self.key);
+        let slot = self.slot();
+        let old = slot.replace_if(new, predicate, self.guard)?;
+        self.list.touch(unsafe { self.cursor.curr().deref() });
+        self.list.persist_value(slot, self.guard);
+        #[cfg(feature = "std")]
+        self.list.log_op(
+            |clone| LogOp::Update(self.key, clone(slot.load(self.guard).unwrap())),
+            self.guard,
+        );
+        Ok(old)
+    }
+
+    /// Deletes the key like `delete`, without searching it. Returns `Err(())` if the key was
+    /// deleted concurrently.
+    pub fn remove(self) -> Result<&'g V, ()> {
+        #[cfg(feature = "std")]
+        let _stripe = self.list.lock_log_stripe(self.key);
+        self.list
+            .delete_at(&self.key, self.cursor, |_| true, self.guard)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Entry of a key that was not in a `SplitOrderedList`. See [`Entry`].
+pub struct VacantEntry<'g, V> {
+    list: &'g SplitOrderedList<V>,
+    key: usize,
+    /// number of buckets when the key was searched
+    size: usize,
+    /// cursor at the position of the key
+    cursor: Cursor<'g, SoKey, Option<Slot<V>>>,
+    guard: &'g Guard,
+}
+
+impl<'g, V> core::fmt::Debug for VacantEntry<'g, V>
+where
+    V: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("VacantEntry")
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+impl<'g, V> VacantEntry<'g, V> {
+    /// Returns the key of the entry.
+    pub fn key(&self) -> usize {
+        self.key
+    }
+
+    /// Inserts `value` like `insert_budgeted` and returns the inserted value. The insertion
+    /// starts at the position of the key found by the search, and only searches again if the
+    /// list changed there in the meantime.
+    pub fn insert(self, value: V) -> Result<&'g V, InsertError<V>> {
+        self.list.insert_node(
+            &self.key,
+            value,
+            &mut Some((self.size, self.cursor)),
+            self.guard,
+        )
+    }
+}
+
+/// Draining iterator over the entries of a `SplitOrderedList`.
+///
+/// This `struct` is created by [`SplitOrderedList::drain`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Drain<V> {
+    /// entries that are not yielded yet
+    entries: alloc::vec::IntoIter<(usize, V)>,
+}
+
+#[cfg(feature = "std")]
+impl<V> Iterator for Drain<V> {
+    type Item = (usize, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next()
+    }
+}
+
+/// Reference to a value of a `SplitOrderedList` that keeps the current thread pinned while it's
+/// alive.
+///
+/// This `struct` is created by [`SplitOrderedList::lookup_pinned`] and
+/// [`SplitOrderedList::delete_pinned`]. Like a guard, it delays the reclamation of the memory
+/// retired meanwhile, so it should be dropped promptly.
+#[cfg(feature = "std")]
+pub struct ValueRef<'a, V> {
+    value: *const V,
+    /// keeps `value` from being reclaimed
+    _guard: Guard,
+    _marker: PhantomData<&'a V>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, V> ValueRef<'a, V> {
+    fn new(value: *const V, guard: Guard) -> Self {
+        Self {
+            value,
+            _guard: guard,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, V> core::ops::Deref for ValueRef<'a, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        // SAFETY: the value was reachable when `_guard` was pinned.
+        unsafe { &*self.value }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, V> core::fmt::Debug for ValueRef<'a, V>
+where
+    V: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Iterator over chunks of cloned entries of a `SplitOrderedList`.
+///
+/// This `struct` is created by [`SplitOrderedList::iter_chunks`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IterChunks<'a, V> {
+    list: &'a SplitOrderedList<V>,
+    chunk_size: usize,
+    /// original key of the last yielded entry
+    last: Option<usize>,
+    done: bool,
+}
+
+#[cfg(feature = "std")]
+impl<'a, V> Iterator for IterChunks<'a, V>
+where
+    V: Clone,
+{
+    type Item = Vec<(usize, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let guard = pin();
+        let nodes = match self.last {
+            None => self.list.nodes(&guard),
+            Some(key) => self
+                .list
+                .nodes_after(SplitOrderedList::<V>::make_content_key(&key), &guard),
+        };
+
+        let chunk: Vec<_> = nodes
+            .filter_map(|node| {
+                let value = node.value().as_ref()?.load(&guard)?;
+                Some((
+                    SplitOrderedList::<V>::original_key(*node.key()),
+                    value.clone(),
+                ))
+            })
+            .take(self.chunk_size)
+            .collect();
+
+        if chunk.len() < self.chunk_size {
+            self.done = true;
+        }
+
+        self.last = Some(chunk.last()?.0);
+        Some(chunk)
+    }
+}
+
+impl<V> FromIterator<(usize, V)> for SplitOrderedList<V> {
+    fn from_iter<I: IntoIterator<Item = (usize, V)>>(iter: I) -> Self {
+        // Of the entries with the same key, keep the last one. The stable sort of the reversed
+        // entries puts it first among them.
+        let mut entries: Vec<_> = iter.into_iter().collect();
+        entries.reverse();
+        entries.sort_by_key(|(key, _)| *key);
+        entries.dedup_by_key(|(key, _)| *key);
+
+        let list = Self::new();
+        // SAFETY: the list is not shared yet, and the keys are distinct, so the insertions don't
+        // replace or retire anything that `unprotected` would free right away.
+        for result in list.insert_many(entries, unsafe { unprotected() }) {
+            debug_assert!(result.is_ok());
+        }
+        list
+    }
+}
+
+impl<V> IntoIterator for SplitOrderedList<V> {
+    type Item = (usize, V);
+    type IntoIter = IntoIter<V>;
+
+    /// Returns an iterator yielding the owned entries in split order, e.g. to tear the map down
+    /// into another data structure.
+    fn into_iter(self) -> IntoIter<V> {
+        // SAFETY: the list is owned, so no other thread accesses it.
+        let head = self
+            .list
+            .head(unsafe { unprotected() }.backend())
+            .curr()
+            .as_raw();
+        IntoIter {
+            list: self,
+            curr: Shared::from(head),
+        }
+    }
+}
+
+impl<V> NonblockingMap<usize, V> for SplitOrderedList<V> {
+    fn lookup<'a>(&'a self, key: &usize, guard: &'a Guard) -> Option<&'a V> {
+        let (_, found, cursor) = self.find(key, guard);
+
+        if found {
+            let value = cursor.lookup().unwrap().as_ref().unwrap().load(guard)?;
+            self.touch(unsafe { cursor.curr().deref() });
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn insert(&self, key: &usize, value: V, guard: &Guard) -> Result<(), V> {
+        self.insert_budgeted(key, value, guard)
+            .map_err(InsertError::into_value)
+    }
+
+    fn delete<'a>(&'a self, key: &usize, guard: &'a Guard) -> Result<&'a V, ()> {
+        self.delete_slot(key, |_| true, guard)
+            .map(|(_, value)| value)
+    }
+}
+
+#[cfg(feature = "teaching")]
+impl<V> SplitOrderedList<V>
+where
+    V: core::fmt::Debug,
+{
+    /// Renders the current shape of the list as ASCII art: the bucket and item counts, followed by
+    /// the nodes in split order. Sentinel nodes are drawn as `[bucket b]`.
+    pub fn visualize(&self, guard: &Guard) -> String {
+        use core::fmt::Write;
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            "size: {}, count: {}",
+            self.size.load(Ordering::Acquire),
+            self.len()
+        )
+        .unwrap();
+        write!(out, "head").unwrap();
+
+        for node in self.nodes(guard) {
+            let so_key = *node.key();
+            if Self::is_sentinel_key(so_key) {
+                write!(out, "\n  -> [bucket {}]", Self::original_key(so_key)).unwrap();
+            } else if let Some(value) = node.value().as_ref().unwrap().load(guard) {
+                write!(out, "\n  ->  {}: {:?}", Self::original_key(so_key), value).unwrap();
+            }
+        }
+
+        writeln!(out).unwrap();
+        out
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<V> SplitOrderedList<V>
+where
+    V: Send + Sync,
+{
+    /// Applies `f` to the entries in parallel, and returns a parallel iterator over the results
+    /// in no particular order, e.g. to aggregate a large table with all cores. The entries are
+    /// visited with the guarantees of `iter`.
+    ///
+    /// The work is split by the initialized buckets, found by the range scan of the bucket array,
+    /// and the chain of each is walked by a worker pinned on its own. `f` runs under the guard of
+    /// the worker, so the references to the values can't escape it.
+    ///
+    /// ```
+    /// use cs492_concur_homework::SplitOrderedList;
+    /// use rayon::prelude::*;
+    ///
+    /// let list: SplitOrderedList<usize> = (0..1000).map(|key| (key, key)).collect();
+    /// assert_eq!(list.par_map(|_, value| *value).sum::<usize>(), 499500);
+    /// ```
+    pub fn par_map<'a, F, R>(&'a self, f: F) -> impl ParallelIterator<Item = R> + 'a
+    where
+        F: Fn(usize, &V) -> R + Sync + Send + 'a,
+        R: Send,
+    {
+        self.chains(&pin())
+            .into_par_iter()
+            .flat_map(move |(bucket, end)| {
+                let worker = pin();
+                let results: Vec<R> = self
+                    .chain_entries(bucket, end, &worker)
+                    .map(|(key, value)| f(key, value))
+                    .collect();
+                results
+            })
+    }
+
+    /// Returns a parallel iterator over the entries, yielding `(key, &value)` in no particular
+    /// order with the guarantees of `iter`.
+    ///
+    /// The work is split like in `par_map`, and the chains are walked by workers pinned on their
+    /// own. The values outlive the workers' guards: they are protected by `guard`, which was
+    /// pinned before the workers load them and stays pinned as long as they are borrowed.
+    ///
+    /// ```
+    /// use cs492_concur_homework::reclaim::pin;
+    /// use cs492_concur_homework::SplitOrderedList;
+    /// use rayon::prelude::*;
+    ///
+    /// let list: SplitOrderedList<usize> = (0..1000).map(|key| (key, key)).collect();
+    /// let guard = pin();
+    /// let max = list.par_iter(&guard).max_by_key(|(_, value)| **value);
+    /// assert_eq!(max, Some((999, &999)));
+    /// ```
+    pub fn par_iter<'g>(
+        &'g self,
+        guard: &'g Guard,
+    ) -> impl ParallelIterator<Item = (usize, &'g V)> + 'g {
+        self.chains(guard)
+            .into_par_iter()
+            .flat_map(move |(bucket, end)| {
+                let worker = pin();
+                let entries: Vec<(usize, &'g V)> = self
+                    .chain_entries(bucket, end, &worker)
+                    // A value retired after `guard` was pinned isn't freed while it's pinned.
+                    .map(|(key, value)| (key, unsafe { &*(value as *const V) }))
+                    .collect();
+                entries
+            })
+    }
+}
+
+/// Serializes a snapshot of the entries as a map from the keys to the values. The snapshot has
+/// the guarantees of `iter`: it is not atomic, and the entries inserted or deleted during the
+/// serialization may or may not be included.
+#[cfg(feature = "serde")]
+impl<V> serde_crate::Serialize for SplitOrderedList<V>
+where
+    V: serde_crate::Serialize,
+{
+    fn serialize<S: serde_crate::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let guard = pin();
+        serializer.collect_map(self.iter(&guard))
+    }
+}
+
+/// Deserializes a map from the keys to the values into a fresh list. Later entries replace
+/// earlier ones with the same key.
+#[cfg(feature = "serde")]
+impl<'de, V> serde_crate::Deserialize<'de> for SplitOrderedList<V>
+where
+    V: serde_crate::Deserialize<'de>,
+{
+    fn deserialize<D: serde_crate::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde_crate::de::{MapAccess, Visitor};
+
+        struct EntriesVisitor<V>(PhantomData<V>);
+
+        impl<'de, V> Visitor<'de> for EntriesVisitor<V>
+        where
+            V: serde_crate::Deserialize<'de>,
+        {
+            type Value = SplitOrderedList<V>;
+
+            fn expecting(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.write_str("a map from usize keys to values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some((key, value)) = map.next_entry()? {
+                    entries.push((key, value));
+                }
+                Ok(entries.into_iter().collect())
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor(PhantomData))
+    }
+}
@@ -0,0 +1,201 @@
+//! Split-ordered set.
+
+use core::mem;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_epoch::{Owned, Shared};
+
+use super::counter::StripedCounter;
+use super::growable_array::GrowableArray;
+use super::list::{Cursor, List, Node};
+use super::split_ordered_list::SoKey;
+use crate::reclaim::{self, BackendGuard, Guard};
+
+/// Lock-free set of `usize` on a split-ordered list like [`SplitOrderedList`], whose nodes only
+/// hold their split-ordered keys.
+///
+/// [`SplitOrderedList`]: crate::SplitOrderedList
+#[derive(Debug)]
+pub struct SplitOrderedSet {
+    list: List<SoKey, ()>,
+    /// pointers to the sentinel nodes
+    buckets: GrowableArray<Node<SoKey, ()>>,
+    /// number of buckets, doubled when `count > size * LOAD_FACTOR`
+    size: AtomicUsize,
+    /// number of keys, striped by key
+    count: StripedCounter,
+}
+
+impl Default for SplitOrderedSet {
+    fn default() -> Self {
+        Self {
+            list: List::new(),
+            buckets: GrowableArray::new(),
+            size: AtomicUsize::new(Self::DEFAULT_BUCKETS),
+            count: StripedCounter::new(),
+        }
+    }
+}
+
+impl SplitOrderedSet {
+    const LOAD_FACTOR: usize = 2;
+    const DEFAULT_BUCKETS: usize = 2;
+
+    /// Creates a new split ordered set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if the set contains the key.
+    pub fn contains(&self, key: usize, guard: &Guard) -> bool {
+        let size = self.size.load(Ordering::Acquire);
+        self.find(key, size, guard).0
+    }
+
+    /// Inserts the key. Returns `false` if the set already contains it.
+    pub fn insert(&self, key: usize, guard: &Guard) -> bool {
+        let mut node = Owned::new(Node::new(SoKey::regular(key), ()));
+        let size = loop {
+            let size = self.size.load(Ordering::Acquire);
+            let (found, mut cursor) = self.find(key, size, guard);
+            if found {
+                return false;
+            }
+            match cursor.insert(node, guard.backend()) {
+                Ok(()) => break size,
+                Err(n) => node = n,
+            }
+        };
+
+        if self.count.increment(key) > size * Self::LOAD_FACTOR
+            && self.len().saturating_sub(1) > size * Self::LOAD_FACTOR
+        {
+            self.size
+                .compare_and_swap(size, size * 2, Ordering::Relaxed);
+        }
+        true
+    }
+
+    /// Removes the key. Returns `false` if the set doesn't contain it.
+    pub fn remove(&self, key: usize, guard: &Guard) -> bool {
+        let size = self.size.load(Ordering::Acquire);
+        let (found, cursor) = self.find(key, size, guard);
+        // A concurrent removal that marked the node first wins.
+        if !found || cursor.delete(guard.backend()).is_err() {
+            return false;
+        }
+        let _ = self.count.decrement(key);
+        true
+    }
+
+    /// Returns an iterator over the keys, in split order and with the same guarantees as
+    /// `SplitOrderedList::iter`.
+    pub fn iter<'g>(&'g self, guard: &'g Guard) -> SetIter<'g> {
+        SetIter {
+            curr: self.list.head(guard.backend()).curr(),
+            guard,
+        }
+    }
+
+    /// Returns the number of keys, under the same caveat as `SplitOrderedList::len`.
+    pub fn len(&self) -> usize {
+        let count = self.count.sum();
+        if count > isize::max_value() as usize {
+            0
+        } else {
+            count
+        }
+    }
+
+    /// Returns `true` if the set has no keys, under the same caveat as `SplitOrderedList::len`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a cursor at the sentinel node of bucket `index`, inserting it and its ancestors if
+    /// necessary.
+    fn bucket<'g>(&'g self, index: usize, guard: &'g Guard) -> Cursor<'g, SoKey, ()> {
+        let store = self.buckets.get(index, guard).as_backend();
+        let sentinel = store.load(Ordering::Acquire, guard.backend());
+        if !sentinel.is_null() {
+            return unsafe { Cursor::from_raw(store, sentinel.as_raw()) };
+        }
+
+        // The parent of a bucket is the bucket with its most significant set bit cleared.
+        let parent = if index == 0 {
+            self.list.head(guard.backend())
+        } else {
+            let msb = mem::size_of::<usize>() * 8 - 1 - index.leading_zeros() as usize;
+            self.bucket(index & !(1 << msb), guard)
+        };
+
+        let key = SoKey::sentinel(index);
+        let mut node = Owned::new(Node::new(key, ()));
+        let sentinel = loop {
+            let mut cursor = parent.clone();
+            match cursor.find_harris(&key, guard.backend()) {
+                Ok(true) => break cursor.curr(),
+                Ok(false) => match cursor.insert(node, guard.backend()) {
+                    Ok(()) => break cursor.curr(),
+                    Err(n) => node = n,
+                },
+                Err(()) => {}
+            }
+        };
+
+        // Publish the sentinel. If another thread already did, it published the same node.
+        let _ = self.buckets.compare_exchange(
+            index,
+            reclaim::Shared::null(),
+            reclaim::Shared::from_backend(sentinel),
+            Ordering::Release,
+            Ordering::Relaxed,
+            guard,
+        );
+        unsafe { Cursor::from_raw(store, sentinel.as_raw()) }
+    }
+
+    /// Finds the position of `key` in the set of `size` buckets.
+    fn find<'g>(
+        &'g self,
+        key: usize,
+        size: usize,
+        guard: &'g Guard,
+    ) -> (bool, Cursor<'g, SoKey, ()>) {
+        let so_key = SoKey::regular(key);
+        let bucket = self.bucket(key % size, guard);
+        loop {
+            let mut cursor = bucket.clone();
+            if let Ok(found) = cursor.find_harris(&so_key, guard.backend()) {
+                return (found, cursor);
+            }
+        }
+    }
+}
+
+/// Iterator over the keys of a `SplitOrderedSet`.
+///
+/// This `struct` is created by [`SplitOrderedSet::iter`].
+#[derive(Debug)]
+pub struct SetIter<'g> {
+    /// next node to visit
+    curr: Shared<'g, Node<SoKey, ()>>,
+    guard: &'g Guard,
+}
+
+impl<'g> Iterator for SetIter<'g> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = unsafe { self.curr.as_ref() } {
+            let next = node.next().load(Ordering::Acquire, self.guard.backend());
+            self.curr = next.with_tag(0);
+            // Skips the sentinels and the deleted nodes.
+            if next.tag() == 0 {
+                if let Some(key) = node.key().regular_key() {
+                    return Some(key);
+                }
+            }
+        }
+        None
+    }
+}
@@ -1,29 +1,92 @@
 //! Homeworks
+//!
+//! Without the default `std` feature, the crate is `no_std` and only provides the hash table
+//! (`GrowableArray`, `SplitOrderedList`, `SplitOrderedSet`) and its supporting modules, on top of
+//! `alloc`. The guards must then be obtained from a `crossbeam_epoch::Collector` and wrapped with
+//! `reclaim::Guard::from_backend`.
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
 #![warn(missing_debug_implementations)]
 
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate alloc;
+
 #[macro_use]
 mod utils;
 
+pub mod append_log;
+#[cfg(feature = "std")]
 mod arc;
+#[cfg(feature = "std")]
 mod art;
+pub mod bounded;
+#[cfg(feature = "std")]
 mod bst;
+pub mod budget;
+#[cfg(feature = "cooperative")]
+pub mod cooperative;
+#[cfg(feature = "std")]
 mod elim_stack;
 mod hash_table;
+#[cfg(feature = "std")]
 pub mod hazard_pointer;
+#[cfg(feature = "std")]
 pub mod hello_server;
+#[cfg(feature = "std")]
 mod linked_list;
+#[cfg(feature = "std")]
 mod list_set;
+pub mod mailbox;
 mod map;
+pub mod pagecache;
+#[cfg(feature = "pmem")]
+pub mod pmem;
+#[cfg(feature = "std")]
+pub mod prelude;
+#[cfg(feature = "std")]
+pub mod publish;
+pub mod reclaim;
+#[cfg(feature = "std")]
+pub mod replica;
+#[cfg(feature = "std")]
+pub mod scope;
+#[cfg(feature = "sim")]
+pub mod sim;
+#[cfg(feature = "std")]
+pub mod sync;
+#[cfg(feature = "std")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod wrapper;
 
+#[cfg(feature = "std")]
 pub use arc::Arc;
+#[cfg(feature = "std")]
 pub use art::{Art, Entry};
+#[cfg(feature = "std")]
 pub use bst::Bst;
+#[cfg(feature = "std")]
 pub use elim_stack::ElimStack;
-pub use hash_table::{GrowableArray, SplitOrderedList};
+#[cfg(feature = "std")]
+pub use hash_table::{BucketTxn, Drain, HpSplitOrderedList, IterChunks, ValueRef};
+pub use hash_table::{
+    Buckets, Entry, GrowableArray, GrowableArrayStats, GrowthPolicy, InsertError, IntoIter, Iter,
+    Keys, LogOp, Lookup, OccupiedEntry, SegmentPoolStats, SetIter, SplitOrderedHashMap,
+    SplitOrderedList, SplitOrderedListBuilder, SplitOrderedListStats, SplitOrderedSet,
+    TryInsertError, VacantEntry, Values,
+};
+#[cfg(feature = "instrument")]
+pub use hash_table::{GrowableArrayMetrics, SplitOrderedListMetrics};
+#[cfg(feature = "std")]
 pub use linked_list::LinkedList;
+#[cfg(feature = "std")]
 pub use list_set::OrderedListSet;
+#[cfg(feature = "std")]
+pub use map::RandGen;
 pub use map::{
-    ConcurrentMap, NonblockingConcurrentMap, NonblockingMap, RandGen, SequentialMap, StrStringMap,
+    ConcurrentMap, NonblockingConcurrentMap, NonblockingMap, SequentialMap, StrStringMap,
 };
@@ -1,7 +1,9 @@
 #![allow(clippy::mutex_atomic)]
 use std::cmp;
+use std::iter::Peekable;
 use std::ptr;
 use std::sync::{Mutex, MutexGuard};
+use std::vec;
 
 #[derive(Debug)]
 struct Node<T> {
@@ -130,6 +132,55 @@ impl<T> OrderedListSet<T> {
     }
 }
 
+/// Iterator over the elements of a set that are not in another set.
+///
+/// This `struct` is created by [`OrderedListSet::diff_iter`].
+#[derive(Debug)]
+pub struct DiffIter<'l, T> {
+    left: Iter<'l, T>,
+    right: Peekable<vec::IntoIter<&'l T>>,
+}
+
+impl<T: Ord> OrderedListSet<T> {
+    /// An iterator visiting the elements in `self` but not in `other`, in ascending order.
+    ///
+    /// The elements of `other` are collected first, as references, and `self` is then walked
+    /// with a lock-coupling cursor merging against them. Walking both lists with a cursor each
+    /// would hold a lock of each list at once, which deadlocks with a concurrent
+    /// `other.diff_iter(self)`. The cursor gives the same guarantees as `iter`, and `other` is
+    /// seen as of the time it was collected.
+    pub fn diff_iter<'l>(&'l self, other: &'l Self) -> DiffIter<'l, T> {
+        let right = other.iter().collect::<Vec<_>>();
+        DiffIter {
+            left: self.iter(),
+            right: right.into_iter().peekable(),
+        }
+    }
+}
+
+impl<'l, T: Ord> Iterator for DiffIter<'l, T> {
+    type Item = &'l T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        'outer: loop {
+            let left = self.left.next()?;
+
+            loop {
+                match self.right.peek() {
+                    Some(r) if *r < left => {
+                        self.right.next();
+                    }
+                    Some(r) if *r == left => {
+                        self.right.next();
+                        continue 'outer;
+                    }
+                    _ => return Some(left),
+                }
+            }
+        }
+    }
+}
+
 impl<'l, T> Iterator for Iter<'l, T> {
     type Item = &'l T;
 
@@ -0,0 +1,97 @@
+//! Multi-producer mailbox.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+struct Node<T> {
+    value: T,
+    next: *mut Node<T>,
+}
+
+/// Mailbox where many producers `post` values and a consumer takes all of them at once.
+///
+/// The posted values form a Treiber stack. `take_all` swaps the whole stack out with a single
+/// `swap` and reverses it, so that the batch is owned by the consumer and no guard is needed:
+/// producers never dereference the nodes of the stack, and the consumer only reads the nodes it
+/// has taken.
+pub struct Mailbox<T> {
+    head: AtomicPtr<Node<T>>,
+}
+
+unsafe impl<T: Send> Send for Mailbox<T> {}
+unsafe impl<T: Send> Sync for Mailbox<T> {}
+
+impl<T> Default for Mailbox<T> {
+    fn default() -> Self {
+        Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+}
+
+impl<T> Mailbox<T> {
+    /// Creates a new empty mailbox.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Posts a value. Lock-free.
+    pub fn post(&self, value: T) {
+        let node = Box::into_raw(Box::new(Node {
+            value,
+            next: ptr::null_mut(),
+        }));
+
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*node).next = head };
+            match self
+                .head
+                .compare_exchange_weak(head, node, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Takes all values posted so far, in the order they were posted. Wait-free apart from the
+    /// deallocation of the nodes.
+    ///
+    /// The values posted by each producer are in its posting order; values posted concurrently
+    /// by different producers are in the order their posts took effect.
+    pub fn take_all(&self) -> Vec<T> {
+        let mut curr = self.head.swap(ptr::null_mut(), Ordering::Acquire);
+        let mut values = Vec::new();
+        while !curr.is_null() {
+            let node = unsafe { Box::from_raw(curr) };
+            curr = node.next;
+            values.push(node.value);
+        }
+
+        values.reverse();
+        values
+    }
+
+    /// Returns `true` if no value is waiting. The result may be stale as soon as it is returned.
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Relaxed).is_null()
+    }
+}
+
+impl<T> Drop for Mailbox<T> {
+    fn drop(&mut self) {
+        drop(self.take_all());
+    }
+}
+
+impl<T> fmt::Debug for Mailbox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mailbox")
+            .field("is_empty", &self.is_empty())
+            .finish()
+    }
+}
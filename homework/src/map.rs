@@ -1,16 +1,24 @@
+use alloc::boxed::Box;
+use alloc::string::String;
 use core::marker::PhantomData;
-use crossbeam_epoch::Guard;
+#[cfg(feature = "std")]
 use lock::{Lock, RawLock};
+#[cfg(feature = "std")]
 use rand::{distributions::Alphanumeric, rngs::ThreadRng, Rng};
 
+use crate::reclaim::Guard;
+
 /// Types that has random generator
+#[cfg(feature = "std")]
 pub trait RandGen {
     /// Randomly generates a value.
     fn rand_gen(rng: &mut ThreadRng) -> Self;
 }
 
+#[cfg(feature = "std")]
 const KEY_MAX_LENGTH: usize = 4;
 
+#[cfg(feature = "std")]
 impl RandGen for String {
     fn rand_gen(rng: &mut ThreadRng) -> Self {
         let length = rng.gen::<usize>() % KEY_MAX_LENGTH;
@@ -18,6 +26,7 @@ impl RandGen for String {
     }
 }
 
+#[cfg(feature = "std")]
 impl RandGen for usize {
     /// pick only 16 bits, MSB=0
     fn rand_gen(rng: &mut ThreadRng) -> Self {
@@ -26,6 +35,7 @@ impl RandGen for usize {
     }
 }
 
+#[cfg(feature = "std")]
 impl RandGen for u32 {
     /// pick only 16 bits
     fn rand_gen(rng: &mut ThreadRng) -> Self {
@@ -93,6 +103,7 @@ impl<V, M: SequentialMap<str, V>> SequentialMap<String, V> for StrStringMap<V, M
     }
 }
 
+#[cfg(feature = "std")]
 impl<K: ?Sized, V, L: RawLock, M> ConcurrentMap<K, V> for Lock<L, M>
 where
     M: SequentialMap<K, V>,
@@ -0,0 +1,120 @@
+//! Latch-free page cache building blocks.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::hash_table::GrowableArray;
+use crate::reclaim::{unprotected, Guard, Owned, Shared};
+
+/// Moves pages between memory and secondary storage.
+pub trait PageHooks<T>: Send + Sync {
+    /// Loads page `pid` from secondary storage. Returns `None` if the page was never swapped out.
+    fn swap_in(&self, pid: usize) -> Option<T>;
+
+    /// Persists page `pid` before it is evicted from memory.
+    fn swap_out(&self, pid: usize, page: &T);
+}
+
+/// Mapping table from page ids to in-memory pages, as in the Bw-tree.
+///
+/// Each page id is an indirection slot in a `GrowableArray`. A new version of a page is published
+/// by a single CAS on its slot, and pages are swapped in from/out to secondary storage on demand
+/// through [`PageHooks`]. Replaced and evicted pages are destroyed through the epoch GC.
+#[derive(Debug)]
+pub struct MappingTable<T, H: PageHooks<T>> {
+    table: GrowableArray<T>,
+    /// the next page id to allocate
+    next_pid: AtomicUsize,
+    hooks: H,
+}
+
+impl<T, H: PageHooks<T>> MappingTable<T, H> {
+    /// Creates a new mapping table with the given swap-in/swap-out hooks.
+    pub fn new(hooks: H) -> Self {
+        Self {
+            table: GrowableArray::new(),
+            next_pid: AtomicUsize::new(0),
+            hooks,
+        }
+    }
+
+    /// Allocates a fresh page id. The page is initially absent.
+    pub fn allocate(&self) -> usize {
+        self.next_pid.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Returns the current version of page `pid`, swapping it in if it is not in memory. Returns
+    /// the null pointer if the page is neither in memory nor on secondary storage.
+    pub fn load<'g>(&'g self, pid: usize, guard: &'g Guard) -> Shared<'g, T> {
+        let slot = self.table.get(pid, guard);
+        let page = slot.load(Ordering::Acquire, guard);
+        if !page.is_null() {
+            return page;
+        }
+
+        let loaded = some_or!(self.hooks.swap_in(pid), return page);
+        match slot.compare_and_set(Shared::null(), Owned::new(loaded), Ordering::AcqRel, guard) {
+            Ok(page) => page,
+            Err(e) => e.current,
+        }
+    }
+
+    /// Publishes `new` as the next version of page `pid` if its current version is `current`.
+    ///
+    /// On success, returns the published pointer and retires `current`. On failure, returns
+    /// `new` back.
+    pub fn compare_and_set<'g>(
+        &'g self,
+        pid: usize,
+        current: Shared<'g, T>,
+        new: Owned<T>,
+        guard: &'g Guard,
+    ) -> Result<Shared<'g, T>, Owned<T>> {
+        let slot = self.table.get(pid, guard);
+        match slot.compare_and_set(current, new, Ordering::AcqRel, guard) {
+            Ok(new) => {
+                if !current.is_null() {
+                    unsafe { guard.defer_destroy(current.into_backend()) };
+                }
+                Ok(new)
+            }
+            Err(e) => Err(e.new),
+        }
+    }
+
+    /// Swaps out page `pid` and evicts it from memory.
+    ///
+    /// Returns `false` if the page is not in memory, or if it was replaced concurrently while
+    /// being swapped out (in which case the swapped-out image may be stale).
+    pub fn evict(&self, pid: usize, guard: &Guard) -> bool {
+        let slot = self.table.get(pid, guard);
+        let page = slot.load(Ordering::Acquire, guard);
+        let page_ref = some_or!(unsafe { page.as_ref() }, return false);
+
+        self.hooks.swap_out(pid, page_ref);
+        if slot
+            .compare_and_set(page, Shared::null(), Ordering::AcqRel, guard)
+            .is_err()
+        {
+            return false;
+        }
+
+        unsafe { guard.defer_destroy(page.into_backend()) };
+        true
+    }
+}
+
+impl<T, H: PageHooks<T>> Drop for MappingTable<T, H> {
+    /// Deallocates the in-memory pages. The array itself only deallocates its segments.
+    fn drop(&mut self) {
+        let guard = unsafe { unprotected() };
+        for pid in 0..*self.next_pid.get_mut() {
+            let page = self
+                .table
+                .get(pid, guard)
+                .swap(Shared::null(), Ordering::Relaxed, guard);
+            if !page.is_null() {
+                drop(unsafe { page.into_owned() });
+            }
+        }
+    }
+}
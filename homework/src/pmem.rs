@@ -0,0 +1,11 @@
+//! Persistent-memory hooks.
+//!
+//! The data structures in this crate are volatile. To adapt them to a persistent-memory durability
+//! protocol, a downstream user provides [`PersistHooks`] that write back the cache lines of the
+//! nodes and links the structures write, and order the write-backs (e.g. `clwb` followed by
+//! `sfence` on x86).
+//!
+//! The hooks are shared with the `lockfree` crate, whose `Queue::with_persist_hooks` takes the
+//! same trait objects as [`SplitOrderedList::with_persist_hooks`](crate::SplitOrderedList).
+
+pub use lockfree::pmem::PersistHooks;
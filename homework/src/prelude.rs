@@ -0,0 +1,15 @@
+//! The prelude: the map traits, the guard helpers, and the most-used types.
+//!
+//! ```
+//! use cs492_concur_homework::prelude::*;
+//!
+//! let map = SplitOrderedList::<usize>::new();
+//! let guard = pin();
+//! assert_eq!(map.insert(&1, 1, &guard), Ok(()));
+//! assert_eq!(map.lookup(&1, &guard), Some(&1));
+//! ```
+
+pub use crate::hash_table::{GrowableArray, SplitOrderedList};
+pub use crate::list_set::OrderedListSet;
+pub use crate::map::{ConcurrentMap, NonblockingConcurrentMap, NonblockingMap, SequentialMap};
+pub use crate::reclaim::{pin, Guard};
@@ -0,0 +1,90 @@
+//! Read-mostly publish table.
+
+use core::mem::ManuallyDrop;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::hash_table::GrowableArray;
+use crate::reclaim::{pin, unprotected, Shared};
+
+/// Table of immutable entries, e.g. versioned routing or configuration tables.
+///
+/// Writers install `Arc<T>` entries by index, and readers get `Arc` clones without managing a
+/// guard. The slots of the underlying `GrowableArray` hold `Arc::into_raw` pointers, and a
+/// replaced entry's reference is dropped through the epoch GC, so a reader that loaded the old
+/// pointer can still safely increment its reference count.
+#[derive(Debug)]
+pub struct PublishTable<T> {
+    array: GrowableArray<T>,
+    /// one plus the largest index ever published
+    len: AtomicUsize,
+}
+
+impl<T> Default for PublishTable<T> {
+    fn default() -> Self {
+        Self {
+            array: GrowableArray::new(),
+            len: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<T: Send + Sync> PublishTable<T> {
+    /// Creates a new empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the entry at `index`. Never allocates and never blocks.
+    pub fn get(&self, index: usize) -> Option<Arc<T>> {
+        let guard = pin();
+        let slot = self.array.try_get(index, &guard)?;
+        let ptr = slot.load(Ordering::Acquire, &guard);
+        if ptr.is_null() {
+            return None;
+        }
+
+        let arc = ManuallyDrop::new(unsafe { Arc::from_raw(ptr.as_raw()) });
+        Some(Arc::clone(&arc))
+    }
+
+    /// Installs `value` at `index`, replacing the previous entry if any.
+    pub fn publish(&self, index: usize, value: Arc<T>) {
+        self.len.fetch_max(index + 1, Ordering::Relaxed);
+        self.replace(index, Shared::from(Arc::into_raw(value)));
+    }
+
+    /// Removes the entry at `index`.
+    pub fn remove(&self, index: usize) {
+        self.replace(index, Shared::null());
+    }
+
+    fn replace(&self, index: usize, new: Shared<'_, T>) {
+        let guard = pin();
+        let old = self
+            .array
+            .get(index, &guard)
+            .swap(new, Ordering::AcqRel, &guard);
+        if old.is_null() {
+            return;
+        }
+
+        let old = old.as_raw() as usize;
+        unsafe {
+            guard.defer_unchecked(move || drop(Arc::from_raw(old as *const T)));
+        }
+    }
+}
+
+impl<T> Drop for PublishTable<T> {
+    fn drop(&mut self) {
+        let guard = unsafe { unprotected() };
+        for index in 0..*self.len.get_mut() {
+            let slot = some_or!(self.array.try_get(index, guard), continue);
+            let ptr = slot.load(Ordering::Relaxed, guard);
+            if !ptr.is_null() {
+                drop(unsafe { Arc::from_raw(ptr.as_raw()) });
+            }
+        }
+    }
+}
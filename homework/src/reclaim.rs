@@ -0,0 +1,703 @@
+//! Memory reclamation.
+//!
+//! The public API of the crate takes the [`Guard`] of this module rather than the guard of the
+//! reclamation backend (currently `crossbeam_epoch`), so that the backend can be swapped without
+//! changing downstream signatures. For the same reason, the structures handing out their pointers
+//! (e.g. [`GrowableArray::get`](crate::GrowableArray::get)) use the [`Atomic`], [`Shared`] and
+//! [`Owned`] of this module, which mirror the backend's and take the guards of this module.
+//!
+//! The global epoch only advances when some thread pins, and garbage queued in the global queue is
+//! only collected when some thread pins. Applications whose worker threads pin rarely can start a
+//! [`maintenance_thread`] to do this periodically in the background.
+//!
+//! Structures that support several reclamation schemes (e.g.
+//! [`GrowableArray`](crate::GrowableArray)) are generic over a [`Reclaimer`], defaulting to
+//! [`Epoch`].
+//!
+//! Garbage still buffered in a worker thread's local bag is not visible to other threads, so it is
+//! only reclaimed after that worker flushes it (e.g. by pinning again or exiting).
+
+use alloc::boxed::Box;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+#[cfg(feature = "std")]
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering;
+use crossbeam_epoch::Pointer as _;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::thread::{self, JoinHandle};
+#[cfg(feature = "std")]
+use std::time::Duration;
+
+/// A guard that keeps the current thread pinned, i.e. protects the shared objects it loads from
+/// being reclaimed.
+///
+/// The guard of the backend is not reachable from it: the pointers handed out by the crate are
+/// loaded with the guard itself (see [`Atomic`]).
+#[repr(transparent)]
+#[derive(Debug)]
+pub struct Guard {
+    inner: crossbeam_epoch::Guard,
+}
+
+/// Pins the current thread.
+#[cfg(feature = "std")]
+pub fn pin() -> Guard {
+    Guard {
+        inner: crossbeam_epoch::pin(),
+    }
+}
+
+/// Returns a reference to a dummy guard that allows unprotected access to shared objects.
+///
+/// # Safety
+///
+/// Same as `crossbeam_epoch::unprotected`: the objects must not be accessed concurrently, e.g.
+/// the guard may be used in `drop`.
+pub unsafe fn unprotected() -> &'static Guard {
+    Guard::from_backend(crossbeam_epoch::unprotected())
+}
+
+impl Guard {
+    /// Wraps a guard of the reclamation backend, for interoperation with code that pins the
+    /// backend directly.
+    pub fn from_backend(guard: &crossbeam_epoch::Guard) -> &Self {
+        // SAFETY: `Guard` is a `repr(transparent)` wrapper of the backend guard.
+        unsafe { &*(guard as *const crossbeam_epoch::Guard as *const Self) }
+    }
+}
+
+impl Guard {
+    /// Runs `f` once no thread can hold a reference loaded before the call.
+    pub fn defer<F: FnOnce() + Send + 'static>(&self, f: F) {
+        self.inner.defer(f);
+    }
+
+    /// Moves the garbage buffered by the current thread to the global queue, and tries to advance
+    /// the epoch and collect the global garbage.
+    pub fn flush(&self) {
+        self.inner.flush();
+    }
+
+    /// Same as `crossbeam_epoch::Guard::defer_unchecked`.
+    pub(crate) unsafe fn defer_unchecked<F: FnOnce()>(&self, f: F) {
+        self.inner.defer_unchecked(f);
+    }
+
+    /// Same as `crossbeam_epoch::Guard::defer_destroy`.
+    pub(crate) unsafe fn defer_destroy<T>(&self, ptr: crossbeam_epoch::Shared<'_, T>) {
+        self.inner.defer_destroy(ptr);
+    }
+}
+
+mod sealed {
+    /// Proof that the caller is in the crate, which keeps the methods taking it out of reach of
+    /// downstream code even where the traits are in scope.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Token(pub(crate) ());
+
+    pub trait Guard {
+        fn backend_guard(&self, token: Token) -> &crossbeam_epoch::Guard;
+    }
+
+    pub trait Pointer {
+        fn into_usize(self, token: Token) -> usize;
+
+        /// # Safety
+        ///
+        /// Same as `crossbeam_epoch::Pointer::from_usize`.
+        unsafe fn from_usize(data: usize, token: Token) -> Self;
+    }
+}
+
+/// A guard of one of the reclamation schemes of the crate, e.g. [`Guard`] and [`UnprotectedGuard`].
+///
+/// The trait is sealed.
+pub trait ReclaimGuard: sealed::Guard {}
+
+impl sealed::Guard for Guard {
+    fn backend_guard(&self, _: sealed::Token) -> &crossbeam_epoch::Guard {
+        &self.inner
+    }
+}
+
+impl ReclaimGuard for Guard {}
+
+/// Access to the backend guard, for the code of the crate.
+pub(crate) trait BackendGuard {
+    fn backend(&self) -> &crossbeam_epoch::Guard;
+}
+
+impl<G: ReclaimGuard + ?Sized> BackendGuard for G {
+    fn backend(&self) -> &crossbeam_epoch::Guard {
+        self.backend_guard(sealed::Token(()))
+    }
+}
+
+/// An atomic pointer that can be loaded with the guards of the crate. It mirrors the `Atomic` of
+/// the backend, whose tags and orderings it shares.
+#[repr(transparent)]
+pub struct Atomic<T> {
+    inner: crossbeam_epoch::Atomic<T>,
+}
+
+/// A pointer loaded from an [`Atomic`], valid for the lifetime `'g` of the guard it was loaded
+/// with.
+#[repr(transparent)]
+pub struct Shared<'g, T> {
+    inner: crossbeam_epoch::Shared<'g, T>,
+}
+
+/// An owned heap-allocated object, which can be stored in an [`Atomic`].
+pub struct Owned<T> {
+    inner: crossbeam_epoch::Owned<T>,
+}
+
+/// A pointer that can be stored in an [`Atomic`], i.e. [`Owned`] or [`Shared`].
+///
+/// The trait is sealed.
+pub trait Pointer<T>: sealed::Pointer {}
+
+/// The error returned by a failed [`Atomic::compare_and_set`]: the current pointer, and the new
+/// pointer given back.
+pub struct CompareAndSetError<'g, T, P: Pointer<T>> {
+    /// The value in the atomic pointer at the time of the failed operation.
+    pub current: Shared<'g, T>,
+    /// The new value, which the operation failed to store.
+    pub new: P,
+}
+
+/// The orderings of a compare-and-set: either a single success ordering, from which the failure
+/// ordering is derived, or a pair of success and failure orderings.
+pub trait CompareAndSetOrdering {
+    /// The ordering of the operation when it succeeds.
+    fn success(&self) -> Ordering;
+
+    /// The ordering of the load when the operation fails.
+    fn failure(&self) -> Ordering;
+}
+
+impl CompareAndSetOrdering for Ordering {
+    fn success(&self) -> Ordering {
+        *self
+    }
+
+    fn failure(&self) -> Ordering {
+        match self {
+            Ordering::Relaxed | Ordering::Release => Ordering::Relaxed,
+            Ordering::Acquire | Ordering::AcqRel => Ordering::Acquire,
+            _ => Ordering::SeqCst,
+        }
+    }
+}
+
+impl CompareAndSetOrdering for (Ordering, Ordering) {
+    fn success(&self) -> Ordering {
+        self.0
+    }
+
+    fn failure(&self) -> Ordering {
+        self.1
+    }
+}
+
+impl<T> Atomic<T> {
+    /// Returns a new null pointer.
+    pub fn null() -> Self {
+        Self {
+            inner: crossbeam_epoch::Atomic::null(),
+        }
+    }
+
+    /// Allocates `value` on the heap and returns a new pointer to it.
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: crossbeam_epoch::Atomic::new(value),
+        }
+    }
+
+    /// Loads the pointer.
+    pub fn load<'g, G: ReclaimGuard>(&self, ord: Ordering, guard: &'g G) -> Shared<'g, T> {
+        Shared::from_backend(self.inner.load(ord, guard.backend()))
+    }
+
+    /// Stores `new`.
+    pub fn store<P: Pointer<T>>(&self, new: P, ord: Ordering) {
+        let new = new.into_usize(sealed::Token(()));
+        // SAFETY: `new` gave up the pointer.
+        let new = unsafe { crossbeam_epoch::Shared::<T>::from_usize(new) };
+        self.inner.store(new, ord);
+    }
+
+    /// Stores `new` and returns the previous pointer.
+    pub fn swap<'g, P: Pointer<T>, G: ReclaimGuard>(
+        &self,
+        new: P,
+        ord: Ordering,
+        guard: &'g G,
+    ) -> Shared<'g, T> {
+        let new = new.into_usize(sealed::Token(()));
+        // SAFETY: `new` gave up the pointer.
+        let new = unsafe { crossbeam_epoch::Shared::<T>::from_usize(new) };
+        Shared::from_backend(self.inner.swap(new, ord, guard.backend()))
+    }
+
+    /// Stores `new` if the current pointer is `current`. Returns the new pointer on success, and
+    /// the current pointer and `new` on failure.
+    pub fn compare_and_set<'g, O: CompareAndSetOrdering, P: Pointer<T>, G: ReclaimGuard>(
+        &self,
+        current: Shared<'_, T>,
+        new: P,
+        ord: O,
+        guard: &'g G,
+    ) -> Result<Shared<'g, T>, CompareAndSetError<'g, T, P>> {
+        let new = new.into_usize(sealed::Token(()));
+        // SAFETY: `new` gave up the pointer, and it is given back on failure.
+        let result = self.inner.compare_and_set(
+            current.inner,
+            unsafe { crossbeam_epoch::Shared::<T>::from_usize(new) },
+            (ord.success(), ord.failure()),
+            guard.backend(),
+        );
+        match result {
+            Ok(new) => Ok(Shared::from_backend(new)),
+            Err(err) => Err(CompareAndSetError {
+                current: Shared::from_backend(err.current),
+                new: unsafe { P::from_usize(new, sealed::Token(())) },
+            }),
+        }
+    }
+
+    /// Bitwise-ors the tag with `val` and returns the previous pointer.
+    pub fn fetch_or<'g, G: ReclaimGuard>(
+        &self,
+        val: usize,
+        ord: Ordering,
+        guard: &'g G,
+    ) -> Shared<'g, T> {
+        Shared::from_backend(self.inner.fetch_or(val, ord, guard.backend()))
+    }
+
+    /// Bitwise-ands the tag with `val` and returns the previous pointer.
+    pub fn fetch_and<'g, G: ReclaimGuard>(
+        &self,
+        val: usize,
+        ord: Ordering,
+        guard: &'g G,
+    ) -> Shared<'g, T> {
+        Shared::from_backend(self.inner.fetch_and(val, ord, guard.backend()))
+    }
+
+    /// Takes ownership of the pointee.
+    ///
+    /// # Safety
+    ///
+    /// Same as `crossbeam_epoch::Atomic::into_owned`.
+    pub unsafe fn into_owned(self) -> Owned<T> {
+        Owned {
+            inner: self.inner.into_owned(),
+        }
+    }
+
+    /// Returns the backend atomic.
+    pub(crate) fn as_backend(&self) -> &crossbeam_epoch::Atomic<T> {
+        &self.inner
+    }
+}
+
+impl<T> Default for Atomic<T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T> From<Owned<T>> for Atomic<T> {
+    fn from(owned: Owned<T>) -> Self {
+        Self {
+            inner: crossbeam_epoch::Atomic::from(owned.inner),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Atomic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+impl<'g, T> Shared<'g, T> {
+    /// Returns a new null pointer.
+    pub fn null() -> Self {
+        Self::from_backend(crossbeam_epoch::Shared::null())
+    }
+
+    /// Returns `true` if the pointer is null, ignoring the tag.
+    pub fn is_null(&self) -> bool {
+        self.inner.is_null()
+    }
+
+    /// Returns the raw pointer, without the tag.
+    pub fn as_raw(&self) -> *const T {
+        self.inner.as_raw()
+    }
+
+    /// Dereferences the pointer.
+    ///
+    /// # Safety
+    ///
+    /// Same as `crossbeam_epoch::Shared::deref`.
+    pub unsafe fn deref(&self) -> &'g T {
+        self.inner.deref()
+    }
+
+    /// Converts the pointer to a reference, or `None` if it is null.
+    ///
+    /// # Safety
+    ///
+    /// Same as `crossbeam_epoch::Shared::as_ref`.
+    pub unsafe fn as_ref(&self) -> Option<&'g T> {
+        self.inner.as_ref()
+    }
+
+    /// Takes ownership of the pointee.
+    ///
+    /// # Safety
+    ///
+    /// Same as `crossbeam_epoch::Shared::into_owned`.
+    pub unsafe fn into_owned(self) -> Owned<T> {
+        Owned {
+            inner: self.inner.into_owned(),
+        }
+    }
+
+    /// Returns the tag.
+    pub fn tag(&self) -> usize {
+        self.inner.tag()
+    }
+
+    /// Returns the same pointer with the tag `tag`, truncated to the unused bits.
+    pub fn with_tag(&self, tag: usize) -> Self {
+        Self::from_backend(self.inner.with_tag(tag))
+    }
+
+    pub(crate) fn from_backend(inner: crossbeam_epoch::Shared<'g, T>) -> Self {
+        Self { inner }
+    }
+
+    pub(crate) fn into_backend(self) -> crossbeam_epoch::Shared<'g, T> {
+        self.inner
+    }
+}
+
+impl<T> Clone for Shared<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Shared<'_, T> {}
+
+impl<T> PartialEq for Shared<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<T> Eq for Shared<'_, T> {}
+
+impl<T> Default for Shared<'_, T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T> From<*const T> for Shared<'_, T> {
+    fn from(raw: *const T) -> Self {
+        Self::from_backend(crossbeam_epoch::Shared::from(raw))
+    }
+}
+
+impl<T> fmt::Debug for Shared<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+impl<T> Owned<T> {
+    /// Allocates `value` on the heap.
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: crossbeam_epoch::Owned::new(value),
+        }
+    }
+
+    /// Converts the pointer into a [`Shared`] valid for the lifetime of `guard`.
+    pub fn into_shared<'g, G: ReclaimGuard>(self, guard: &'g G) -> Shared<'g, T> {
+        Shared::from_backend(self.inner.into_shared(guard.backend()))
+    }
+
+    /// Converts the pointer into a box.
+    pub fn into_box(self) -> Box<T> {
+        self.inner.into_box()
+    }
+
+    /// Returns the tag.
+    pub fn tag(&self) -> usize {
+        self.inner.tag()
+    }
+
+    /// Returns the same pointer with the tag `tag`, truncated to the unused bits.
+    pub fn with_tag(self, tag: usize) -> Self {
+        Self {
+            inner: self.inner.with_tag(tag),
+        }
+    }
+}
+
+impl<T> From<Box<T>> for Owned<T> {
+    fn from(b: Box<T>) -> Self {
+        Self {
+            inner: crossbeam_epoch::Owned::from(b),
+        }
+    }
+}
+
+impl<T> Deref for Owned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for Owned<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T> fmt::Debug for Owned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+impl<T> sealed::Pointer for Owned<T> {
+    fn into_usize(self, _: sealed::Token) -> usize {
+        self.inner.into_usize()
+    }
+
+    unsafe fn from_usize(data: usize, _: sealed::Token) -> Self {
+        Self {
+            inner: crossbeam_epoch::Owned::from_usize(data),
+        }
+    }
+}
+
+impl<T> Pointer<T> for Owned<T> {}
+
+impl<T> sealed::Pointer for Shared<'_, T> {
+    fn into_usize(self, _: sealed::Token) -> usize {
+        self.inner.into_usize()
+    }
+
+    unsafe fn from_usize(data: usize, _: sealed::Token) -> Self {
+        Self::from_backend(crossbeam_epoch::Shared::from_usize(data))
+    }
+}
+
+impl<T> Pointer<T> for Shared<'_, T> {}
+
+/// Converts `ptr` to the representation of the backend's pointers, giving up its ownership.
+pub(crate) fn pointer_into_usize<P: sealed::Pointer>(ptr: P) -> usize {
+    ptr.into_usize(sealed::Token(()))
+}
+
+impl<T, P: Pointer<T> + fmt::Debug> fmt::Debug for CompareAndSetError<'_, T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompareAndSetError")
+            .field("current", &self.current)
+            .field("new", &self.new)
+            .finish()
+    }
+}
+
+/// Waits until no thread holds a reference it loaded before the call, i.e. for a grace period of
+/// the default collector, so that the memory unlinked before the call can be reused right away.
+///
+/// It blocks for as long as the other threads stay pinned, and spins on pinning meanwhile.
+///
+/// # Panics
+///
+/// Panics if the current thread is pinned, since the epoch couldn't advance.
+#[cfg(feature = "std")]
+pub(crate) fn synchronize() {
+    assert_unpinned();
+    let done = Arc::new(AtomicBool::new(false));
+    {
+        let guard = pin();
+        let done = done.clone();
+        guard.defer(move || done.store(true, Ordering::Release));
+        guard.flush();
+    }
+    while !done.load(Ordering::Acquire) {
+        pin().flush();
+        thread::yield_now();
+    }
+}
+
+/// Panics if the current thread is pinned, for the operations that call [`synchronize`]. They call
+/// it before any side effect.
+#[cfg(feature = "std")]
+pub(crate) fn assert_unpinned() {
+    assert!(
+        !crossbeam_epoch::is_pinned(),
+        "waiting for a grace period while pinned"
+    );
+}
+
+/// Memory reclamation scheme of a data structure: the guard its operations take, and how it
+/// retires the memory it unlinks.
+///
+/// The guard is one of the [`ReclaimGuard`]s of the crate, with which the structure loads its
+/// pointers. A scheme that protects individual pointers (e.g. hazard pointers)
+/// doesn't fit this interface, since the structures don't announce the pointers they traverse:
+/// such a scheme protects the elements itself and uses [`Unprotected`] for the structure, as
+/// [`HpSplitOrderedList`](crate::HpSplitOrderedList) does.
+pub trait Reclaimer {
+    /// Guard protecting the shared objects loaded by the current thread.
+    type Guard: ReclaimGuard;
+
+    /// Returns a guard for the current thread.
+    ///
+    /// # Safety
+    ///
+    /// The guard must be used as the scheme requires. This is always the case for [`Epoch`]; see
+    /// [`UnprotectedGuard::new`] for [`Unprotected`].
+    #[cfg(feature = "std")]
+    unsafe fn pin() -> Self::Guard;
+
+    /// Runs `f` once no thread can hold a reference to the memory it frees.
+    ///
+    /// # Safety
+    ///
+    /// Same as `crossbeam_epoch::Guard::defer_unchecked`.
+    unsafe fn defer<F: FnOnce()>(guard: &Self::Guard, f: F);
+}
+
+/// Epoch-based reclamation with [`Guard`], the default scheme.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Epoch;
+
+impl Reclaimer for Epoch {
+    type Guard = Guard;
+
+    #[cfg(feature = "std")]
+    unsafe fn pin() -> Guard {
+        pin()
+    }
+
+    unsafe fn defer<F: FnOnce()>(guard: &Guard, f: F) {
+        guard.defer_unchecked(f);
+    }
+}
+
+/// No reclamation protection: retired memory is freed immediately. Only for benchmarking the
+/// cost of the reclamation scheme and for tests without concurrent removals.
+///
+/// A structure using it can be created safely, but not operated on without an
+/// [`UnprotectedGuard`], whose creation is `unsafe`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Unprotected;
+
+/// Guard of [`Unprotected`], which protects nothing.
+#[derive(Debug, Clone, Copy)]
+pub struct UnprotectedGuard {
+    _private: (),
+}
+
+impl UnprotectedGuard {
+    /// Creates a guard.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`unprotected`]: no object loaded through the guard may be reclaimed while it is
+    /// accessed, i.e. the memory the structures retire must not be accessed concurrently. The
+    /// guard must not be used with the pointers of structures using another scheme, e.g. to load
+    /// an [`Atomic`] handed out by a structure using [`Epoch`].
+    pub unsafe fn new() -> Self {
+        Self { _private: () }
+    }
+}
+
+impl sealed::Guard for UnprotectedGuard {
+    fn backend_guard(&self, _: sealed::Token) -> &crossbeam_epoch::Guard {
+        // SAFETY: guaranteed by the creator of the guard.
+        unsafe { crossbeam_epoch::unprotected() }
+    }
+}
+
+impl ReclaimGuard for UnprotectedGuard {}
+
+impl Reclaimer for Unprotected {
+    type Guard = UnprotectedGuard;
+
+    #[cfg(feature = "std")]
+    unsafe fn pin() -> UnprotectedGuard {
+        UnprotectedGuard::new()
+    }
+
+    unsafe fn defer<F: FnOnce()>(_guard: &UnprotectedGuard, f: F) {
+        f();
+    }
+}
+
+/// Handle of a background thread started by [`maintenance_thread`].
+///
+/// The thread is stopped and joined when the handle is dropped.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Maintenance {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Spawns a background thread that pins, flushes and unpins every `interval`, advancing the
+/// global epoch and collecting garbage on behalf of the application's threads.
+///
+/// The thread spends almost all of its time sleeping. The standard library doesn't expose thread
+/// priorities, so it runs at the default priority.
+#[cfg(feature = "std")]
+pub fn maintenance_thread(interval: Duration) -> Maintenance {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let stop = stop.clone();
+        thread::Builder::new()
+            .name("epoch-maintenance".to_string())
+            .spawn(move || {
+                while !stop.load(Ordering::Acquire) {
+                    crossbeam_epoch::pin().flush();
+                    thread::park_timeout(interval);
+                }
+            })
+            .unwrap()
+    };
+
+    Maintenance {
+        stop,
+        handle: Some(handle),
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for Maintenance {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            handle.join().unwrap();
+        }
+    }
+}
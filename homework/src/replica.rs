@@ -0,0 +1,183 @@
+//! Eventually consistent read replicas.
+
+use core::cell::Cell;
+use core::mem;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_epoch::{Atomic, Owned};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::hash_table::SplitOrderedList;
+use crate::map::NonblockingMap;
+use crate::reclaim::{unprotected, BackendGuard, Guard};
+
+#[derive(Debug)]
+enum Mutation<V> {
+    Insert(usize, V),
+    Delete(usize),
+}
+
+#[derive(Debug)]
+struct Replica<V> {
+    /// immutable snapshot answering the lookups
+    snapshot: Atomic<HashMap<usize, V>>,
+    /// mutations of the primary not yet applied to `snapshot`
+    pending: Mutex<Vec<Mutation<V>>>,
+    /// serializes the snapshot updates, without blocking the writers queueing to `pending`
+    apply: Mutex<()>,
+}
+
+/// Map whose primary `SplitOrderedList` streams its mutations into read replicas, for read-heavy
+/// workloads that tolerate slightly stale reads.
+///
+/// Each replica is an immutable hash map snapshot, so `lookup` is wait-free: it loads the
+/// snapshot of the calling thread's replica and probes it. Mutations are applied to the primary
+/// and queued to every replica; [`sync`](ReplicatedMap::sync) applies the queued mutations by
+/// publishing new snapshots. A replica therefore lags the primary by the mutations since the last
+/// `sync`, and `lookup_primary` gives the up-to-date (lock-free) answer.
+///
+/// Mutations are serialized by a writer lock, so that every replica receives them in the order
+/// they took effect on the primary. Each `sync` copies the snapshots, so it should be called
+/// periodically rather than after every mutation.
+#[derive(Debug)]
+pub struct ReplicatedMap<V> {
+    primary: SplitOrderedList<V>,
+    replicas: Box<[Replica<V>]>,
+    writer: Mutex<()>,
+}
+
+static NEXT_THREAD_HINT: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD_HINT: Cell<Option<usize>> = Cell::new(None);
+}
+
+/// Returns a number assigned round-robin to the calling thread on first use, spreading the
+/// threads over the replicas.
+fn thread_hint() -> usize {
+    THREAD_HINT.with(|hint| match hint.get() {
+        Some(hint) => hint,
+        None => {
+            let new = NEXT_THREAD_HINT.fetch_add(1, Ordering::Relaxed);
+            hint.set(Some(new));
+            new
+        }
+    })
+}
+
+impl<V> ReplicatedMap<V>
+where
+    V: Clone,
+{
+    /// Creates a new map with `num_replicas` read replicas, typically one per core.
+    pub fn new(num_replicas: usize) -> Self {
+        assert!(num_replicas > 0, "at least one replica is required");
+        Self {
+            primary: SplitOrderedList::new(),
+            replicas: (0..num_replicas)
+                .map(|_| Replica {
+                    snapshot: Atomic::new(HashMap::new()),
+                    pending: Mutex::new(Vec::new()),
+                    apply: Mutex::new(()),
+                })
+                .collect(),
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Returns the number of read replicas.
+    pub fn replica_count(&self) -> usize {
+        self.replicas.len()
+    }
+
+    /// Lookups the key in the replica of the calling thread. Wait-free; may be stale.
+    pub fn lookup<'g>(&'g self, key: &usize, guard: &'g Guard) -> Option<&'g V> {
+        self.lookup_in(thread_hint() % self.replicas.len(), key, guard)
+    }
+
+    /// Lookups the key in the given replica. Wait-free; may be stale.
+    pub fn lookup_in<'g>(&'g self, replica: usize, key: &usize, guard: &'g Guard) -> Option<&'g V> {
+        let snapshot = self.replicas[replica]
+            .snapshot
+            .load(Ordering::Acquire, guard.backend());
+        unsafe { snapshot.deref() }.get(key)
+    }
+
+    /// Lookups the key in the primary. Lock-free; never stale.
+    pub fn lookup_primary<'g>(&'g self, key: &usize, guard: &'g Guard) -> Option<&'g V> {
+        self.primary.lookup(key, guard)
+    }
+
+    /// Inserts a key-value pair into the primary, and queues it to the replicas on success.
+    pub fn insert(&self, key: &usize, value: V, guard: &Guard) -> Result<(), V> {
+        let _writer = self.writer.lock().unwrap();
+        self.primary.insert(key, value.clone(), guard)?;
+        self.broadcast(|| Mutation::Insert(*key, value.clone()));
+        Ok(())
+    }
+
+    /// Deletes the key from the primary, and queues the deletion to the replicas on success.
+    pub fn delete<'g>(&'g self, key: &usize, guard: &'g Guard) -> Result<&'g V, ()> {
+        let _writer = self.writer.lock().unwrap();
+        let value = self.primary.delete(key, guard)?;
+        self.broadcast(|| Mutation::Delete(*key));
+        Ok(value)
+    }
+
+    fn broadcast<F: Fn() -> Mutation<V>>(&self, mutation: F) {
+        for replica in self.replicas.iter() {
+            replica.pending.lock().unwrap().push(mutation());
+        }
+    }
+
+    /// Applies the queued mutations to every replica. Returns the number of mutations applied.
+    ///
+    /// Concurrent calls are safe: each replica applies its mutations in order, one batch at a
+    /// time. The writers only wait for a replica's queue to be taken, not for the copy.
+    pub fn sync(&self, guard: &Guard) -> usize {
+        self.replicas
+            .iter()
+            .map(|replica| Self::sync_replica(replica, guard))
+            .sum()
+    }
+
+    fn sync_replica(replica: &Replica<V>, guard: &Guard) -> usize {
+        let _apply = replica.apply.lock().unwrap();
+        let pending = mem::take(&mut *replica.pending.lock().unwrap());
+        if pending.is_empty() {
+            return 0;
+        }
+
+        let old = replica.snapshot.load(Ordering::Acquire, guard.backend());
+        let mut map = unsafe { old.deref() }.clone();
+        let applied = pending.len();
+        for mutation in pending {
+            match mutation {
+                Mutation::Insert(key, value) => {
+                    map.insert(key, value);
+                }
+                Mutation::Delete(key) => {
+                    map.remove(&key);
+                }
+            }
+        }
+
+        replica.snapshot.store(Owned::new(map), Ordering::Release);
+        unsafe { guard.defer_destroy(old) };
+        applied
+    }
+}
+
+impl<V> Drop for ReplicatedMap<V> {
+    fn drop(&mut self) {
+        let guard = unsafe { unprotected() };
+        for replica in self.replicas.iter() {
+            drop(unsafe {
+                replica
+                    .snapshot
+                    .load(Ordering::Relaxed, guard.backend())
+                    .into_owned()
+            });
+        }
+    }
+}
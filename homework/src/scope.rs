@@ -0,0 +1,96 @@
+//! Structured concurrency for data structures and their worker threads.
+
+use std::panic;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Owns data structures together with the worker threads using them.
+///
+/// Workers are spawned with [`spawn`](StructureScope::spawn) and get a reference to the
+/// structures. The scope joins all workers before the structures are torn down, whether it is
+/// consumed by [`join`](StructureScope::join) or dropped, so the workers can never observe freed
+/// structures.
+///
+/// # Example
+///
+/// ```
+/// use cs492_concur_homework::reclaim::pin;
+/// use cs492_concur_homework::scope::StructureScope;
+/// use cs492_concur_homework::{NonblockingMap, SplitOrderedList};
+///
+/// let mut scope = StructureScope::new(SplitOrderedList::new());
+/// for t in 0..4 {
+///     scope.spawn(move |list| {
+///         list.insert(&t, t, &pin()).unwrap();
+///     });
+/// }
+/// let list = scope.join();
+/// assert_eq!(list.lookup(&3, &pin()), Some(&3));
+/// ```
+#[derive(Debug)]
+pub struct StructureScope<S> {
+    structures: Arc<S>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl<S: Send + Sync + 'static> StructureScope<S> {
+    /// Creates a scope owning `structures`, e.g. a structure or a tuple of structures.
+    pub fn new(structures: S) -> Self {
+        Self {
+            structures: Arc::new(structures),
+            workers: Vec::new(),
+        }
+    }
+
+    /// Returns the structures.
+    pub fn structures(&self) -> &S {
+        &self.structures
+    }
+
+    /// Spawns a worker running `f` on the structures.
+    pub fn spawn<F>(&mut self, f: F)
+    where
+        F: FnOnce(&S) + Send + 'static,
+    {
+        let structures = self.structures.clone();
+        self.workers.push(thread::spawn(move || f(&structures)));
+    }
+
+    /// Joins all workers and returns the structures.
+    ///
+    /// # Panics
+    ///
+    /// Resumes the panic of the first worker that panicked, after all workers are joined.
+    pub fn join(mut self) -> S {
+        join_all(&mut self.workers);
+        let structures = self.structures.clone();
+        drop(self);
+        match Arc::try_unwrap(structures) {
+            Ok(structures) => structures,
+            Err(_) => unreachable!("all workers are joined"),
+        }
+    }
+}
+
+impl<S> Drop for StructureScope<S> {
+    fn drop(&mut self) {
+        join_all(&mut self.workers);
+    }
+}
+
+/// Joins the workers. Resumes the panic of the first worker that panicked, unless the current
+/// thread is already panicking.
+fn join_all(workers: &mut Vec<JoinHandle<()>>) {
+    let mut panic = None;
+    for worker in workers.drain(..) {
+        if let Err(payload) = worker.join() {
+            let _ = panic.get_or_insert(payload);
+        }
+    }
+
+    if let Some(payload) = panic {
+        if !thread::panicking() {
+            panic::resume_unwind(payload);
+        }
+    }
+}
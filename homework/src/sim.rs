@@ -0,0 +1,53 @@
+//! Simulated time for testing timeout-dependent logic instantly and reproducibly.
+//!
+//! # Example
+//!
+//! ```
+//! use cs492_concur_homework::sim::VirtualClock;
+//! use std::time::Duration;
+//!
+//! let clock = VirtualClock::new();
+//! clock.advance(Duration::from_secs(3));
+//! assert_eq!(clock.now(), Duration::from_secs(3));
+//! ```
+
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+/// Virtual clock that only moves when `advance`d.
+///
+/// Threads `sleep`ing on the clock are woken up exactly when the virtual time passes their
+/// deadline, regardless of the wall-clock time.
+#[derive(Debug, Default)]
+pub struct VirtualClock {
+    now: Mutex<Duration>,
+    advanced: Condvar,
+}
+
+impl VirtualClock {
+    /// Creates a new virtual clock at time zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the virtual time elapsed since the creation of the clock.
+    pub fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+
+    /// Moves the virtual time forward by `duration`, waking up the sleepers whose deadline has
+    /// passed.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+        self.advanced.notify_all();
+    }
+
+    /// Blocks until the virtual time is advanced by at least `duration`.
+    pub fn sleep(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        let deadline = *now + duration;
+        while *now < deadline {
+            now = self.advanced.wait(now).unwrap();
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Exchanger.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_epoch::{pin, Atomic, Owned, Shared};
+use crossbeam_utils::Backoff;
+use std::time::{Duration, Instant};
+
+/// The offer is waiting for a partner.
+const WAITING: usize = 0;
+/// A partner claimed the offer and is writing its response.
+const BUSY: usize = 1;
+/// The partner's response is written.
+const MATCHED: usize = 2;
+/// The offering thread timed out.
+const CANCELLED: usize = 3;
+
+#[derive(Debug)]
+struct Offer<T> {
+    /// the value offered by the waiting thread, taken by the partner
+    item: UnsafeCell<Option<T>>,
+    /// the value given back by the partner
+    response: UnsafeCell<Option<T>>,
+    state: AtomicUsize,
+}
+
+/// Pairwise value exchange between threads.
+///
+/// A thread calling `exchange` either publishes an offer in the slot and waits for a partner, or
+/// finds a waiting offer and swaps values with it. This is the building block of elimination
+/// backoff (e.g. an elimination stack pairs a push with a pop) and of synchronous queues.
+///
+/// The exchange is blocking: once a partner claims an offer, the offering thread waits for the
+/// partner's response regardless of the timeout, which only bounds the wait for a partner to show
+/// up. The offering thread waits unpinned, so that it doesn't hold back the reclamation of the
+/// other threads' garbage in the meantime.
+///
+/// # Example
+///
+/// ```
+/// use cs492_concur_homework::sync::Exchanger;
+/// use std::time::Duration;
+///
+/// let exchanger = Exchanger::new();
+/// crossbeam_utils::thread::scope(|s| {
+///     s.spawn(|_| exchanger.exchange(1, Duration::from_secs(10)));
+///     assert_eq!(exchanger.exchange(2, Duration::from_secs(10)), Ok(1));
+/// })
+/// .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Exchanger<T> {
+    slot: Atomic<Offer<T>>,
+}
+
+unsafe impl<T: Send> Send for Exchanger<T> {}
+unsafe impl<T: Send> Sync for Exchanger<T> {}
+
+impl<T> Default for Exchanger<T> {
+    fn default() -> Self {
+        Self {
+            slot: Atomic::null(),
+        }
+    }
+}
+
+impl<T> Drop for Exchanger<T> {
+    fn drop(&mut self) {
+        // Every offer is removed from the slot before its `exchange` returns.
+        debug_assert!(self
+            .slot
+            .load(Ordering::Relaxed, unsafe { crossbeam_epoch::unprotected() })
+            .is_null());
+    }
+}
+
+impl<T> Exchanger<T> {
+    /// Creates a new exchanger.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Exchanges `value` with a partner thread.
+    ///
+    /// Returns `Ok(v)` with the partner's value `v`, or `Err(value)` if no partner showed up
+    /// within `timeout`. May wait past `timeout` for a partner that showed up in time.
+    pub fn exchange(&self, value: T, timeout: Duration) -> Result<T, T> {
+        let deadline = Instant::now() + timeout;
+        let backoff = Backoff::new();
+        let mut value = value;
+
+        loop {
+            let guard = pin();
+            let curr = self.slot.load(Ordering::Acquire, &guard);
+
+            if let Some(offer) = unsafe { curr.as_ref() } {
+                // Try to claim the waiting offer.
+                if offer
+                    .state
+                    .compare_exchange(WAITING, BUSY, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    let _ =
+                        self.slot
+                            .compare_and_set(curr, Shared::null(), Ordering::Relaxed, &guard);
+                    let item = unsafe { (*offer.item.get()).take().unwrap() };
+                    unsafe { *offer.response.get() = Some(value) };
+                    offer.state.store(MATCHED, Ordering::Release);
+                    return Ok(item);
+                }
+            } else {
+                // Publish an offer and wait for a partner.
+                let offer = Owned::new(Offer {
+                    item: UnsafeCell::new(Some(value)),
+                    response: UnsafeCell::new(None),
+                    state: AtomicUsize::new(WAITING),
+                });
+
+                match self
+                    .slot
+                    .compare_and_set(Shared::null(), offer, Ordering::Release, &guard)
+                {
+                    Ok(curr) => {
+                        let curr = curr.as_raw();
+                        drop(guard);
+                        return self.wait(curr, deadline);
+                    }
+                    Err(e) => {
+                        value = unsafe { (*e.new.item.get()).take().unwrap() };
+                    }
+                }
+            }
+
+            if Instant::now() >= deadline {
+                return Err(value);
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Waits for a partner to respond to `curr`, the offer the current thread published, or
+    /// cancels it once `deadline` passes. Frees the offer.
+    ///
+    /// Only the offering thread frees its offer, so the offer stays valid while it waits unpinned.
+    fn wait(&self, curr: *const Offer<T>, deadline: Instant) -> Result<T, T> {
+        let offer = unsafe { &*curr };
+        let backoff = Backoff::new();
+        while offer.state.load(Ordering::Acquire) == WAITING {
+            if Instant::now() >= deadline
+                && offer
+                    .state
+                    .compare_exchange(WAITING, CANCELLED, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_ok()
+            {
+                let guard = pin();
+                // Nobody else removes a cancelled offer.
+                let _ = self.slot.compare_and_set(
+                    Shared::from(curr),
+                    Shared::null(),
+                    Ordering::Relaxed,
+                    &guard,
+                );
+                let value = unsafe { (*offer.item.get()).take().unwrap() };
+                unsafe { guard.defer_destroy(Shared::from(curr)) };
+                return Err(value);
+            }
+            backoff.snooze();
+        }
+
+        // Claimed by a partner, which removed the offer from the slot: wait for its response.
+        while offer.state.load(Ordering::Acquire) != MATCHED {
+            backoff.snooze();
+        }
+        let response = unsafe { (*offer.response.get()).take().unwrap() };
+        unsafe { pin().defer_destroy(Shared::from(curr)) };
+        Ok(response)
+    }
+}
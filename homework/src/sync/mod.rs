@@ -0,0 +1,5 @@
+//! Synchronization primitives.
+
+mod exchanger;
+
+pub use exchanger::Exchanger;
@@ -0,0 +1,3 @@
+//! Ready-made test scenarios for the concurrent maps in this crate and for user implementations.
+
+pub mod scenarios;
@@ -0,0 +1,115 @@
+//! Concurrent scenarios with exactly known outcomes.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crossbeam_utils::thread::scope;
+
+use crate::map::NonblockingMap;
+use crate::reclaim::{pin, Guard};
+use crate::SplitOrderedList;
+
+/// No-lost-update scenario: `threads` threads increment the counter of every key in `0..keys`,
+/// `steps` times each, and the exact totals are asserted afterwards.
+///
+/// `increment(map, key, guard)` should add one to the counter of `key`, creating it if necessary.
+/// `read(map, key, guard)` should return the current counter of `key`. After all threads are
+/// joined, every counter must be exactly `threads * steps`; otherwise an update was lost (or
+/// duplicated) by the map or by the value-merge logic in `increment`.
+pub fn counter_increment<M, I, R>(
+    map: &M,
+    threads: usize,
+    keys: usize,
+    steps: usize,
+    increment: I,
+    read: R,
+) where
+    M: Sync,
+    I: Fn(&M, usize, &Guard) + Sync,
+    R: Fn(&M, usize, &Guard) -> Option<usize>,
+{
+    scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|_| {
+                for _ in 0..steps {
+                    for key in 0..keys {
+                        increment(map, key, &pin());
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = pin();
+    for key in 0..keys {
+        assert_eq!(
+            read(map, key, &guard),
+            Some(threads * steps),
+            "lost update on key {}",
+            key
+        );
+    }
+}
+
+/// Increments the `AtomicUsize` counter of `key`, inserting a zero counter first if the key is
+/// absent.
+pub fn increment_atomic<M: NonblockingMap<usize, AtomicUsize>>(map: &M, key: usize, guard: &Guard) {
+    let _ = map.insert(&key, AtomicUsize::new(0), guard);
+    map.lookup(&key, guard)
+        .expect("counter was never deleted")
+        .fetch_add(1, Ordering::Relaxed);
+}
+
+/// Reads the `AtomicUsize` counter of `key`.
+pub fn read_atomic<M: NonblockingMap<usize, AtomicUsize>>(
+    map: &M,
+    key: usize,
+    guard: &Guard,
+) -> Option<usize> {
+    map.lookup(&key, guard).map(|c| c.load(Ordering::Relaxed))
+}
+
+/// Runs [`counter_increment`] on a fresh map storing `AtomicUsize` counters.
+pub fn counter_increment_atomic<M>(threads: usize, keys: usize, steps: usize)
+where
+    M: Default + Sync + NonblockingMap<usize, AtomicUsize>,
+{
+    let map = M::default();
+    counter_increment(
+        &map,
+        threads,
+        keys,
+        steps,
+        increment_atomic::<M>,
+        read_atomic::<M>,
+    );
+}
+
+/// Increments the plain counter of `key` by replacing it through
+/// [`SplitOrderedList::compare_and_update`], inserting 1 if the key is absent. Unlike
+/// `increment_atomic`, the values are merged by the map, so a lost update is a bug of its value
+/// replacement.
+pub fn increment_update(map: &SplitOrderedList<usize>, key: usize, guard: &Guard) {
+    loop {
+        let done = match map.lookup(&key, guard) {
+            Some(&current) => map
+                .compare_and_update(&key, &current, current + 1, guard)
+                .is_ok(),
+            None => map.insert(&key, 1, guard).is_ok(),
+        };
+        if done {
+            return;
+        }
+    }
+}
+
+/// Reads the plain counter of `key`.
+pub fn read_value(map: &SplitOrderedList<usize>, key: usize, guard: &Guard) -> Option<usize> {
+    map.lookup(&key, guard).copied()
+}
+
+/// Runs [`counter_increment`] on a fresh `SplitOrderedList` storing plain counters, merged with
+/// [`increment_update`].
+pub fn counter_increment_update(threads: usize, keys: usize, steps: usize) {
+    let map = SplitOrderedList::new();
+    counter_increment(&map, threads, keys, steps, increment_update, read_value);
+}
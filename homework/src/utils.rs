@@ -1,3 +1,5 @@
+use alloc::string::String;
+
 #[macro_export]
 /// Ok or executing the given expression.
 macro_rules! ok_or {
@@ -19,3 +21,67 @@ macro_rules! some_or {
         }
     }};
 }
+
+/// Number of retries after which `Watchdog` considers a loop stuck.
+pub(crate) const WATCHDOG_RETRIES: usize = 1 << 24;
+
+/// Liveness watchdog for CAS retry loops.
+///
+/// In debug builds, panics with the given diagnostics once a loop retries more than
+/// `WATCHDOG_RETRIES` times, turning livelocks into actionable failures during testing. Does
+/// nothing in release builds.
+#[derive(Debug)]
+pub(crate) struct Watchdog {
+    #[cfg(debug_assertions)]
+    retries: usize,
+    #[cfg(debug_assertions)]
+    name: &'static str,
+}
+
+impl Watchdog {
+    #[inline]
+    pub(crate) fn new(_name: &'static str) -> Self {
+        Self {
+            #[cfg(debug_assertions)]
+            retries: 0,
+            #[cfg(debug_assertions)]
+            name: _name,
+        }
+    }
+
+    /// Records a retry. `diagnostics` describes the state of the structure.
+    #[inline]
+    pub(crate) fn tick<F: FnOnce() -> String>(&mut self, _diagnostics: F) {
+        #[cfg(debug_assertions)]
+        {
+            self.retries += 1;
+            if self.retries > WATCHDOG_RETRIES {
+                panic!(
+                    "watchdog: {} retried more than {} times: {}",
+                    self.name,
+                    WATCHDOG_RETRIES,
+                    _diagnostics()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "cooperative")]
+pub(crate) use crate::cooperative::YieldPoint;
+
+/// Yield point of long loops. Does nothing without the `cooperative` feature.
+#[cfg(not(feature = "cooperative"))]
+#[derive(Debug, Default)]
+pub(crate) struct YieldPoint;
+
+#[cfg(not(feature = "cooperative"))]
+impl YieldPoint {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Self
+    }
+
+    #[inline]
+    pub(crate) fn step(&mut self) {}
+}
@@ -0,0 +1,80 @@
+//! Wrappers that make sequential data structures usable concurrently.
+
+use core::hash::{Hash, Hasher};
+use std::collections::hash_map::DefaultHasher;
+use std::sync::RwLock;
+
+use crate::map::{ConcurrentMap, SequentialMap};
+use crate::reclaim::Guard;
+
+/// Default number of shards of [`Striped`].
+const DEFAULT_SHARDS: usize = 16;
+
+/// Shards a sequential structure into several instances, each guarded by its own `RwLock`, and
+/// routes each key to a shard by its hash.
+///
+/// This is a quick way to get a [`ConcurrentMap`] out of any [`SequentialMap`], e.g. to use the
+/// crate's traits and tests before the lock-free implementation is ready. Lookups of different
+/// keys in the same shard run in parallel, but any update blocks the whole shard.
+///
+/// The number of shards is chosen at runtime, since const generics are not available on the
+/// supported toolchain.
+#[derive(Debug)]
+pub struct Striped<M> {
+    shards: Box<[RwLock<M>]>,
+}
+
+impl<M> Striped<M> {
+    /// Creates a new wrapper with `shards` shards, each created with `f`.
+    pub fn with_shards<F>(shards: usize, mut f: F) -> Self
+    where
+        F: FnMut() -> M,
+    {
+        assert!(shards > 0, "number of shards must be positive");
+        Self {
+            shards: (0..shards).map(|_| RwLock::new(f())).collect(),
+        }
+    }
+
+    /// Returns the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    fn shard<K: ?Sized + Hash>(&self, key: &K) -> &RwLock<M> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[(hasher.finish() % self.shards.len() as u64) as usize]
+    }
+}
+
+impl<M: Default> Default for Striped<M> {
+    fn default() -> Self {
+        Self::with_shards(DEFAULT_SHARDS, M::default)
+    }
+}
+
+impl<K: ?Sized + Hash, V, M> ConcurrentMap<K, V> for Striped<M>
+where
+    M: SequentialMap<K, V>,
+{
+    fn lookup<'a, F, R>(&'a self, key: &'a K, _guard: &'a Guard, f: F) -> R
+    where
+        F: FnOnce(Option<&V>) -> R,
+    {
+        f(self.shard(key).read().unwrap().lookup(key))
+    }
+
+    fn insert<'a>(&'a self, key: &'a K, value: V, _guard: &'a Guard) -> Result<(), V> {
+        self.shard(key)
+            .write()
+            .unwrap()
+            .insert(key, value)
+            .map(|_| ())
+            .map_err(|(_, v)| v)
+    }
+
+    fn delete(&self, key: &K, _guard: &Guard) -> Result<V, ()> {
+        self.shard(key).write().unwrap().delete(key)
+    }
+}
@@ -0,0 +1,32 @@
+#![cfg(feature = "cooperative")]
+
+use cs492_concur_homework::cooperative;
+use cs492_concur_homework::reclaim::pin;
+use cs492_concur_homework::{NonblockingMap, SplitOrderedList};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static YIELDS: AtomicUsize = AtomicUsize::new(0);
+
+fn count_yield() {
+    let _ = YIELDS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[test]
+fn scan_yields() {
+    let list = SplitOrderedList::new();
+    let guard = pin();
+    for i in 0..1024 {
+        assert_eq!(list.insert(&i, i, &guard), Ok(()));
+    }
+
+    cooperative::set_yield_hook(count_yield, 16);
+    let sentinels = list.sentinels(&guard);
+    cooperative::clear_yield_hook();
+
+    // At least one step per visited node.
+    assert!(YIELDS.load(Ordering::Relaxed) >= (1024 + sentinels.len()) / 16);
+
+    let before = YIELDS.load(Ordering::Relaxed);
+    let _ = list.sentinels(&guard);
+    assert_eq!(YIELDS.load(Ordering::Relaxed), before);
+}
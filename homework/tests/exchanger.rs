@@ -0,0 +1,65 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::sync::Exchanger;
+use std::time::Duration;
+
+#[test]
+fn timeout() {
+    let exchanger = Exchanger::new();
+    assert_eq!(exchanger.exchange(1, Duration::from_millis(10)), Err(1));
+    assert_eq!(exchanger.exchange(2, Duration::from_millis(0)), Err(2));
+}
+
+#[test]
+fn pair() {
+    let exchanger = Exchanger::new();
+    scope(|s| {
+        let handle = s.spawn(|_| exchanger.exchange(1, Duration::from_secs(10)));
+        assert_eq!(exchanger.exchange(2, Duration::from_secs(10)), Ok(1));
+        assert_eq!(handle.join().unwrap(), Ok(2));
+    })
+    .unwrap();
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 1024;
+
+    let exchanger = Exchanger::new();
+    let results = scope(|s| {
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let exchanger = &exchanger;
+                s.spawn(move |_| {
+                    (0..STEPS)
+                        .map(|i| {
+                            let value = t * STEPS + i;
+                            (value, exchanger.exchange(value, Duration::from_micros(100)))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    })
+    .unwrap();
+
+    // Every successful exchange is matched by exactly one partner.
+    let mut given: Vec<_> = results
+        .iter()
+        .filter(|(_, r)| r.is_ok())
+        .map(|(v, _)| *v)
+        .collect();
+    let mut received: Vec<_> = results.iter().filter_map(|(_, r)| r.ok()).collect();
+    given.sort();
+    received.sort();
+    assert_eq!(given, received);
+    for (value, result) in &results {
+        if let Err(back) = result {
+            assert_eq!(back, value);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Exercises the hash table and its supporting modules under whatever feature combination the
+//! crate is built with. `scripts/feature-matrix.sh` runs it with each feature alone, with and
+//! without `std`, and with all of them.
+//!
+//! Only the `no_std` API is used unconditionally: the guards come from a local `Collector`.
+
+use core::sync::atomic::Ordering;
+use crossbeam_epoch::Collector;
+
+use cs492_concur_homework::append_log::AppendLog;
+use cs492_concur_homework::mailbox::Mailbox;
+use cs492_concur_homework::reclaim::{Guard, Owned, Unprotected, UnprotectedGuard};
+use cs492_concur_homework::{GrowableArray, NonblockingMap, SplitOrderedList, SplitOrderedSet};
+
+/// The features the crate was built with, as exported by the build script.
+fn features() -> Vec<&'static str> {
+    env!("CS492_FEATURES")
+        .split(',')
+        .filter(|feature| !feature.is_empty())
+        .collect()
+}
+
+#[test]
+fn configuration() {
+    let features = features();
+
+    let enabled = |name: &str| features.contains(&name);
+    assert_eq!(enabled("std"), cfg!(feature = "std"));
+    assert_eq!(enabled("cooperative"), cfg!(feature = "cooperative"));
+    assert_eq!(enabled("access-time"), cfg!(feature = "access-time"));
+
+    // Implied features.
+    if enabled("sim") || enabled("access-time") || enabled("serde") || enabled("rayon") {
+        assert!(enabled("std"));
+    }
+}
+
+#[test]
+fn growable_array() {
+    let collector = Collector::new();
+    let handle = collector.register();
+    let backend = handle.pin();
+    let guard = Guard::from_backend(&backend);
+
+    let array = GrowableArray::<usize>::new();
+    for index in (0..1 << 14).step_by(97) {
+        array.store(index, Owned::new(index), Ordering::Release, guard);
+    }
+    for index in (0..1 << 14).step_by(97) {
+        let value = array.get(index, guard).load(Ordering::Acquire, guard);
+        assert_eq!(unsafe { value.deref() }, &index);
+        drop(unsafe { value.into_owned() });
+    }
+
+    // The same operations with the other reclaimer backend.
+    let array = GrowableArray::<usize, Unprotected>::with_reclaimer(None);
+    let guard = unsafe { UnprotectedGuard::new() };
+    array.store(1 << 12, Owned::new(1), Ordering::Release, &guard);
+    let value = array.get(1 << 12, &guard).load(Ordering::Acquire, &guard);
+    assert_eq!(unsafe { value.deref() }, &1);
+    drop(unsafe { value.into_owned() });
+}
+
+#[test]
+fn split_ordered_list() {
+    let collector = Collector::new();
+    let handle = collector.register();
+    let backend = handle.pin();
+    let guard = Guard::from_backend(&backend);
+
+    let list = SplitOrderedList::<usize>::new();
+    for key in 0..1024 {
+        assert_eq!(list.insert(&key, key * 2, guard), Ok(()));
+    }
+    for key in 0..1024 {
+        assert_eq!(list.lookup(&key, guard), Some(&(key * 2)));
+    }
+    for key in (0..1024).step_by(2) {
+        assert_eq!(list.delete(&key, guard), Ok(&(key * 2)));
+    }
+    for key in 0..1024 {
+        let expected = if key % 2 == 0 { None } else { Some(key * 2) };
+        assert_eq!(list.lookup(&key, guard), expected.as_ref());
+    }
+
+    let set = SplitOrderedSet::new();
+    assert!(set.insert(42, guard));
+    assert!(set.contains(42, guard));
+    assert!(set.remove(42, guard));
+    assert!(!set.contains(42, guard));
+}
+
+#[test]
+fn full_key_range() {
+    let collector = Collector::new();
+    let handle = collector.register();
+    let backend = handle.pin();
+    let guard = Guard::from_backend(&backend);
+
+    let list = SplitOrderedList::<usize>::new();
+    for &key in [0, 1 << 63, usize::max_value()].iter() {
+        assert_eq!(list.insert(&key, key, guard), Ok(()));
+        assert_eq!(list.lookup(&key, guard), Some(&key));
+    }
+    assert_eq!(
+        list.delete(&usize::max_value(), guard),
+        Ok(&usize::max_value())
+    );
+    assert_eq!(list.lookup(&usize::max_value(), guard), None);
+}
+
+#[test]
+fn supporting_modules() {
+    let collector = Collector::new();
+    let handle = collector.register();
+    let backend = handle.pin();
+    let guard = Guard::from_backend(&backend);
+
+    let log = AppendLog::new();
+    for i in 0..100 {
+        assert_eq!(log.append(i, guard), i);
+    }
+    assert!(log.iter(guard).copied().eq(0..100));
+
+    let mailbox = Mailbox::new();
+    mailbox.post(1);
+    mailbox.post(2);
+    assert_eq!(mailbox.take_all(), vec![1, 2]);
+}
@@ -1,10 +1,15 @@
 use core::mem::{replace, ManuallyDrop};
 use core::sync::atomic::Ordering;
-use crossbeam_epoch::{pin, unprotected, Atomic, Guard, Owned, Shared};
+use cs492_concur_homework::budget::MemoryBudget;
+use cs492_concur_homework::reclaim::{
+    pin, unprotected, Atomic, Guard, Owned, Shared, Unprotected, UnprotectedGuard,
+};
 use cs492_concur_homework::{GrowableArray, NonblockingConcurrentMap, NonblockingMap};
 
 mod map;
 
+// The tests of the tree shape assume segments of 1024 slots, and are disabled with small segments.
+
 #[derive(Debug, Default)]
 struct ArrayMap<V> {
     array: GrowableArray<Node<V>>,
@@ -71,7 +76,7 @@ impl<T> Default for Stack<T> {
 
 impl<T> Stack<T> {
     fn push_node(&self, mut n: Owned<Node<T>>) {
-        let guard = crossbeam_epoch::pin();
+        let guard = pin();
 
         loop {
             let head = self.head.load(Ordering::Relaxed, &guard);
@@ -124,6 +129,532 @@ fn smoke() {
     assert_eq!(list.lookup(&37, &guard), None);
 }
 
+#[test]
+fn try_get() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    assert!(array.try_get(0, &guard).is_none());
+
+    array.get(5, &guard).store(Owned::new(5), Ordering::Relaxed);
+    assert!(array.try_get(5, &guard).is_some());
+    assert!(array.try_get(6, &guard).is_some());
+    assert!(array.try_get(1 << 20, &guard).is_none());
+
+    let slot = array.try_get(5, &guard).unwrap();
+    let ptr = slot.load(Ordering::Relaxed, &guard);
+    assert_eq!(unsafe { *ptr.deref() }, 5);
+    drop(unsafe { ptr.into_owned() });
+}
+
+#[cfg(not(any(miri, feature = "small-segments")))]
+#[test]
+fn shrink() {
+    let mut array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    let slot = array.get(1 << 20, &guard);
+    slot.store(Owned::new(1), Ordering::Relaxed);
+    array.get(1 << 10, &guard);
+
+    // Only the root and the path to the element survive
+    assert_eq!(array.shrink(&guard), 3);
+    assert!(array.try_get(1 << 10, &guard).is_none());
+    assert!(array.try_get(1 << 20, &guard).is_some());
+
+    let slot = array.try_get(1 << 20, &guard).unwrap();
+    let ptr = slot.swap(Shared::null(), Ordering::Relaxed, &guard);
+    drop(unsafe { ptr.into_owned() });
+    assert_eq!(array.shrink(&guard), 2);
+    assert!(array.try_get(1 << 20, &guard).is_none());
+}
+
+#[cfg(not(any(miri, feature = "small-segments")))]
+#[test]
+fn stats() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    let slot = array.get(1 << 10, &guard);
+    slot.store(Owned::new(1), Ordering::Relaxed);
+
+    let stats = array.stats(&guard);
+    assert_eq!(stats.height, 2);
+    assert_eq!(stats.segments_per_level, vec![1, 2]);
+    assert_eq!(stats.occupied_slots, 1);
+    assert!(stats.segment_bytes >= 3 * (8 << 10));
+
+    drop(unsafe { slot.load(Ordering::Relaxed, &guard).into_owned() });
+}
+
+#[test]
+fn stats_sparse() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    // Slots handed out by each accessor are found by the scans.
+    let slots = array.get_many(&[3, 700, 1023], &guard);
+    for (i, slot) in slots.iter().enumerate() {
+        slot.store(Owned::new(i), Ordering::Relaxed);
+    }
+    array
+        .try_get(5, &guard)
+        .unwrap()
+        .store(Owned::new(3), Ordering::Relaxed);
+    assert_eq!(array.stats(&guard).occupied_slots, 4);
+
+    for &index in &[3, 5, 700, 1023] {
+        let slot = array.get(index, &guard);
+        drop(unsafe {
+            slot.swap(Shared::null(), Ordering::Relaxed, &guard)
+                .into_owned()
+        });
+    }
+    assert_eq!(array.stats(&guard).occupied_slots, 0);
+}
+
+#[test]
+fn clear() {
+    let mut array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    let slot = array.get(1 << 20, &guard);
+    slot.store(Owned::new(1), Ordering::Relaxed);
+    let ptr = slot.load(Ordering::Relaxed, &guard);
+
+    array.clear(&guard);
+    assert_eq!(array.stats(&guard).height, 0);
+    assert!(array.try_get(1 << 20, &guard).is_none());
+    assert!(array
+        .get(1 << 20, &guard)
+        .load(Ordering::Relaxed, &guard)
+        .is_null());
+
+    // The elements are not deallocated.
+    assert_eq!(unsafe { *ptr.deref() }, 1);
+    drop(unsafe { ptr.into_owned() });
+}
+
+#[cfg(not(any(miri, feature = "small-segments")))]
+#[test]
+fn height_capacity() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    assert_eq!(array.height(&guard), 0);
+    assert_eq!(array.capacity(&guard), None);
+
+    array.get(0, &guard);
+    assert_eq!(array.height(&guard), 1);
+    assert_eq!(array.capacity(&guard), Some((1 << 10) - 1));
+
+    array.get(1 << 20, &guard);
+    assert_eq!(array.height(&guard), 3);
+    assert_eq!(array.capacity(&guard), Some((1 << 30) - 1));
+}
+
+#[cfg(not(any(miri, feature = "small-segments")))]
+#[test]
+fn extreme_indices() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    // The largest index of a height doesn't need another level.
+    array.get((1 << 10) - 1, &guard);
+    assert_eq!(array.height(&guard), 1);
+
+    let indices = [0, usize::max_value() - 1, usize::max_value()];
+    for &index in &indices {
+        array
+            .get(index, &guard)
+            .store(Owned::new(index), Ordering::Relaxed);
+    }
+    assert_eq!(array.capacity(&guard), Some(usize::max_value()));
+    let height = array.height(&guard);
+
+    for &index in &indices {
+        let slot = array.get(index, &guard);
+        assert_eq!(
+            unsafe { *slot.load(Ordering::Relaxed, &guard).deref() },
+            index
+        );
+        drop(unsafe {
+            slot.swap(Shared::null(), Ordering::Relaxed, &guard)
+                .into_owned()
+        });
+    }
+    assert_eq!(array.height(&guard), height);
+}
+
+#[test]
+fn get_many() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    let indices = [3000, 5, 1 << 20, 7, 5];
+    let slots = array.get_many(&indices, &guard);
+    assert_eq!(slots.len(), indices.len());
+    for (slot, &index) in slots.iter().zip(indices.iter()) {
+        assert!(core::ptr::eq(*slot, array.get(index, &guard)));
+    }
+}
+
+#[test]
+fn into_par_drop() {
+    let budget = MemoryBudget::new(usize::max_value());
+    let array = GrowableArray::<usize>::with_budget(budget.clone());
+
+    {
+        let guard = pin();
+        for i in 0..64 {
+            array.get(i << 12, &guard);
+        }
+    }
+    assert!(budget.used() > 0);
+
+    array.into_par_drop(4);
+    assert_eq!(budget.used(), 0);
+}
+
+#[cfg(not(any(miri, feature = "small-segments")))]
+#[test]
+fn dump_tree() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    assert_eq!(array.dump_tree(&guard), "height: 0\n");
+
+    for &index in &[1, 3 << 10, (3 << 10) + 1] {
+        array
+            .get(index, &guard)
+            .store(Owned::new(index), Ordering::Relaxed);
+    }
+    assert_eq!(
+        array.dump_tree(&guard),
+        "height: 2\n\
+         root: height 2, 2/1024 occupied\n  \
+         [0000000000]: height 1, 1/1024 occupied\n  \
+         [0000000011]: height 1, 2/1024 occupied\n"
+    );
+
+    for &index in &[1, 3 << 10, (3 << 10) + 1] {
+        let slot = array.get(index, &guard);
+        drop(unsafe {
+            slot.swap(Shared::null(), Ordering::Relaxed, &guard)
+                .into_owned()
+        });
+    }
+}
+
+#[cfg(not(any(miri, feature = "small-segments")))]
+#[test]
+fn reserve() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    array.reserve(1 << 20, &guard);
+    assert_eq!(array.height(&guard), 3);
+    assert_eq!(array.stats(&guard).segments_per_level, vec![1, 1, 1]);
+
+    array.reserve_range(1000..3000, &guard);
+    assert_eq!(array.stats(&guard).segments_per_level, vec![1, 1, 3]);
+
+    // Already reserved: doesn't allocate.
+    array.get(2500, &guard);
+    assert_eq!(array.stats(&guard).segments_per_level, vec![1, 1, 3]);
+}
+
+#[cfg(not(any(miri, feature = "small-segments")))]
+#[test]
+fn with_height() {
+    let array = GrowableArray::<usize>::with_height(3);
+    let guard = pin();
+    assert_eq!(array.height(&guard), 3);
+    assert_eq!(array.capacity(&guard), Some((1 << 30) - 1));
+    assert_eq!(array.stats(&guard).segments_per_level, vec![1, 1, 1]);
+
+    array.get((1 << 30) - 1, &guard);
+    assert_eq!(array.height(&guard), 3);
+
+    assert_eq!(GrowableArray::<usize>::with_height(0).height(&guard), 0);
+}
+
+#[test]
+fn first_occupied_null() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    assert_eq!(array.first_occupied(0, &guard), None);
+    assert_eq!(array.first_null(5, &guard), Some(5));
+
+    let elems: Vec<_> = (0..3).map(|i| Owned::new(i).into_shared(&guard)).collect();
+    array.store(0, elems[0], Ordering::Relaxed, &guard);
+    array.store(1, elems[1], Ordering::Relaxed, &guard);
+    array.store(3 << 20, elems[2], Ordering::Relaxed, &guard);
+    // Handed out but null.
+    array.get(2000, &guard);
+
+    assert_eq!(array.first_occupied(0, &guard), Some(0));
+    assert_eq!(array.first_occupied(1, &guard), Some(1));
+    assert_eq!(array.first_occupied(2, &guard), Some(3 << 20));
+    assert_eq!(array.first_occupied((3 << 20) + 1, &guard), None);
+
+    assert_eq!(array.first_null(0, &guard), Some(2));
+    assert_eq!(array.first_null(3 << 20, &guard), Some((3 << 20) + 1));
+    assert_eq!(array.first_null(1 << 40, &guard), Some(1 << 40));
+
+    for elem in elems {
+        drop(unsafe { elem.into_owned() });
+    }
+}
+
+#[test]
+fn store_compare_exchange() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    array.store(3000, Owned::new(1), Ordering::Release, &guard);
+    let one = array.get(3000, &guard).load(Ordering::Acquire, &guard);
+    assert_eq!(unsafe { *one.deref() }, 1);
+
+    let two = Owned::new(2).into_shared(&guard);
+    assert!(array
+        .compare_exchange(
+            3000,
+            Shared::null(),
+            two,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+            &guard
+        )
+        .is_err());
+    assert_eq!(
+        array
+            .compare_exchange(3000, one, two, Ordering::AcqRel, Ordering::Acquire, &guard)
+            .ok(),
+        Some(two)
+    );
+
+    drop(unsafe { one.into_owned() });
+    drop(unsafe { two.into_owned() });
+}
+
+#[cfg(not(any(miri, feature = "small-segments")))]
+#[test]
+fn collapse_root() {
+    let mut array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    array.get(5, &guard).store(Owned::new(5), Ordering::Relaxed);
+    array.reserve(1 << 20, &guard);
+    assert_eq!(array.height(&guard), 3);
+
+    assert!(array.collapse_root(&guard));
+    assert!(array.collapse_root(&guard));
+    assert!(!array.collapse_root(&guard));
+    assert_eq!(array.height(&guard), 1);
+    let five = array.try_get(5, &guard).unwrap().load(Ordering::Relaxed, &guard);
+    assert_eq!(unsafe { *five.deref() }, 5);
+
+    // A populated upper region prevents collapsing.
+    array.get(1 << 10, &guard);
+    assert!(!array.collapse_root(&guard));
+    assert_eq!(array.height(&guard), 2);
+    array.get(3000, &guard);
+    assert_eq!(array.stats(&guard).segments_per_level, vec![1, 3]);
+
+    drop(unsafe { five.into_owned() });
+}
+
+#[test]
+fn snapshot() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    let indices = [(3 << 20) + 7, 1, 3 << 10, 5];
+    for &index in &indices {
+        array.store(index, Owned::new(index), Ordering::Relaxed, &guard);
+    }
+    // Handed out but null.
+    array.get(2, &guard);
+    drop(guard);
+
+    let snapshot = array.snapshot(&pin());
+    let keys: Vec<_> = snapshot.iter().map(|&(index, _)| index).collect();
+    assert_eq!(keys, vec![1, 5, 3 << 10, (3 << 20) + 7]);
+    for &(index, ptr) in &snapshot {
+        assert_eq!(unsafe { *ptr }, index);
+    }
+
+    for (_, ptr) in snapshot {
+        drop(unsafe { Box::from_raw(ptr as *mut usize) });
+    }
+}
+
+#[test]
+fn clone_with() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    let indices = [1, 3000, 3 << 20];
+    for &index in &indices {
+        array.store(index, Owned::new(index), Ordering::Relaxed, &guard);
+    }
+
+    // Deep copy, leaving out index 3000.
+    let clone = array.clone_with(&guard, |elem| {
+        let value = unsafe { *elem.deref() };
+        if value == 3000 {
+            Shared::null()
+        } else {
+            Owned::new(value * 10).into_shared(&guard)
+        }
+    });
+    assert_eq!(
+        clone.stats(&guard).segments_per_level,
+        array.stats(&guard).segments_per_level
+    );
+
+    let values = |array: &GrowableArray<usize>| {
+        array
+            .snapshot(&guard)
+            .into_iter()
+            .map(|(index, ptr)| (index, unsafe { *ptr }))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        values(&array),
+        vec![(1, 1), (3000, 3000), (3 << 20, 3 << 20)]
+    );
+    assert_eq!(values(&clone), vec![(1, 10), (3 << 20, 30 << 20)]);
+
+    // The arrays are independent.
+    array.store(5, Owned::new(5), Ordering::Relaxed, &guard);
+    assert!(clone
+        .try_get(5, &guard)
+        .unwrap()
+        .load(Ordering::Relaxed, &guard)
+        .is_null());
+
+    for array in &[array, clone] {
+        for (_, ptr) in array.snapshot(&guard) {
+            drop(unsafe { Box::from_raw(ptr as *mut usize) });
+        }
+    }
+}
+
+#[test]
+fn range() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    assert_eq!(array.range(0..usize::max_value(), &guard).count(), 0);
+
+    let indices = [1, 5, 3000, 3 << 20, (3 << 20) + 7];
+    for &index in &indices {
+        array.store(index, Owned::new(index), Ordering::Relaxed, &guard);
+    }
+    // Handed out but null.
+    array.get(2, &guard);
+
+    let in_range = |range| {
+        array
+            .range(range, &guard)
+            .map(|(index, elem)| {
+                assert_eq!(unsafe { *elem.deref() }, index);
+                index
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(in_range(0..usize::max_value()), indices.to_vec());
+    assert_eq!(in_range(2..3000), vec![5]);
+    assert_eq!(in_range(5..(3 << 20) + 1), vec![5, 3000, 3 << 20]);
+    assert_eq!(in_range((3 << 20) + 8..1 << 40), vec![]);
+    assert_eq!(in_range(3000..3000), vec![]);
+
+    for (_, elem) in array.range(0..usize::max_value(), &guard) {
+        drop(unsafe { elem.into_owned() });
+    }
+}
+
+#[cfg(not(any(miri, feature = "small-segments")))]
+#[test]
+fn tags() {
+    let mut array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    let one = Owned::new(1).into_shared(&guard);
+    array.store(3, one, Ordering::Relaxed, &guard);
+    assert_eq!(array.fetch_or_tag(3, 1, Ordering::AcqRel, &guard), one);
+    assert_eq!(
+        array.fetch_or_tag(3 << 10, 2, Ordering::AcqRel, &guard),
+        Shared::null()
+    );
+    assert_eq!(
+        array.snapshot_tagged(&guard),
+        vec![(3, one.as_raw(), 1), (3 << 10, core::ptr::null(), 2)]
+    );
+    assert_eq!(array.snapshot(&guard), vec![(3, one.as_raw())]);
+
+    // A tagged null keeps its segment alive.
+    assert_eq!(array.shrink(&guard), 0);
+    assert_eq!(array.stats(&guard).occupied_slots, 2);
+
+    assert_eq!(
+        array
+            .fetch_clear_tag(3 << 10, 2, Ordering::AcqRel, &guard)
+            .tag(),
+        2
+    );
+    assert_eq!(array.shrink(&guard), 1);
+    assert_eq!(
+        array.fetch_clear_tag(3, 1, Ordering::AcqRel, &guard),
+        one.with_tag(1)
+    );
+
+    drop(unsafe { one.into_owned() });
+}
+
+#[cfg(not(any(miri, feature = "small-segments")))]
+#[test]
+fn shrink_counted() {
+    let mut array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    let one = Owned::new(1).into_shared(&guard);
+    array.store(1 << 10, one, Ordering::Relaxed, &guard);
+    assert_eq!(array.shrink(&guard), 1);
+    assert_eq!(array.stats(&guard).occupied_slots, 1);
+
+    array.store(1 << 10, Shared::null(), Ordering::Relaxed, &guard);
+    assert_eq!(array.shrink(&guard), 1);
+    assert!(array.try_get(1 << 10, &guard).is_none());
+
+    // Filled through the `Atomic`, cleared through the helper.
+    array.get(1 << 10, &guard).store(one, Ordering::Relaxed);
+    array.store(1 << 10, Shared::null(), Ordering::Relaxed, &guard);
+    assert_eq!(array.shrink(&guard), 1);
+
+    drop(unsafe { one.into_owned() });
+}
+
+#[cfg(not(any(miri, feature = "small-segments")))]
+#[test]
+fn unprotected_reclaimer() {
+    let mut array = GrowableArray::<usize, Unprotected>::with_reclaimer(None);
+    let guard = unsafe { UnprotectedGuard::new() };
+
+    array.store(1 << 20, Owned::new(1), Ordering::Relaxed, &guard);
+    array.get(1 << 10, &guard);
+    assert_eq!(array.height(&guard), 3);
+
+    // Retired segments are recycled immediately.
+    assert_eq!(array.shrink(&guard), 3);
+    let slot = array.try_get(1 << 20, &guard).unwrap();
+    let one = slot.swap(Shared::null(), Ordering::Relaxed, &guard);
+    assert_eq!(unsafe { *one.deref() }, 1);
+    drop(unsafe { one.into_owned() });
+    array.clear(&guard);
+    assert_eq!(array.height(&guard), 0);
+}
+
 #[test]
 fn stress_sequential() {
     const STEPS: usize = 4096;
@@ -145,3 +676,96 @@ fn log_concurrent() {
     const STEPS: usize = 4096 * 12;
     map::log_concurrent::<u32, NonblockingConcurrentMap<_, _, ArrayMap<usize>>>(THREADS, STEPS);
 }
+
+#[cfg(all(feature = "instrument", not(any(miri, feature = "small-segments"))))]
+#[test]
+fn metrics() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    array.get(0, &guard);
+    array.get(1 << 10, &guard);
+    let metrics = array.metrics();
+    // The first root, the new root and the new leaf.
+    assert_eq!(metrics.segment_allocations, 3);
+    assert_eq!(metrics.root_cas_failures, 0);
+    assert_eq!(metrics.child_cas_failures, 0);
+    assert_eq!(metrics.traversals, 2);
+    assert!((metrics.average_depth - 1.5).abs() < 1e-9);
+}
+
+#[cfg(not(any(miri, feature = "small-segments")))]
+#[test]
+fn segment_pool() {
+    let mut array = GrowableArray::<usize, Unprotected>::with_reclaimer(None);
+    let guard = unsafe { UnprotectedGuard::new() };
+
+    array.get(1 << 20, &guard);
+    let stats = array.pool_stats();
+    assert_eq!(stats.hits, 0);
+    assert_eq!(stats.pooled, 0);
+
+    // The retired segments are put back into the pool, and the next allocations take them.
+    let retired = array.shrink(&guard);
+    assert!(retired > 0);
+    assert_eq!(array.pool_stats().pooled, retired);
+
+    array.get(1 << 20, &guard);
+    let stats = array.pool_stats();
+    // Only the segments on the path to the index are allocated again.
+    assert_eq!(stats.hits, 2);
+    assert_eq!(stats.pooled, retired - 2);
+    assert!(stats.hit_rate() > 0.0);
+
+    // Recycled segments are reset.
+    let slot = array.get(1 << 20, &guard);
+    assert!(slot.load(Ordering::Relaxed, &guard).is_null());
+    assert_eq!(array.first_occupied(0, &guard), None);
+}
+
+#[cfg(any(miri, feature = "small-segments"))]
+#[test]
+fn small_segments() {
+    let mut array = GrowableArray::<usize>::new();
+    let guard = pin();
+
+    // Every slot of a tree of height 3.
+    let elems: Vec<_> = (0..512)
+        .map(|i| Owned::new(i).into_shared(&guard))
+        .collect();
+    for (index, &elem) in elems.iter().enumerate() {
+        array.store(index, elem, Ordering::Relaxed, &guard);
+    }
+    let stats = array.stats(&guard);
+    assert_eq!(stats.height, 3);
+    assert_eq!(stats.segments_per_level, vec![1, 8, 64]);
+    assert_eq!(stats.occupied_slots, 512);
+
+    for index in 0..512 {
+        array.store(index, Shared::null(), Ordering::Relaxed, &guard);
+    }
+    assert_eq!(array.shrink(&guard), 72);
+    assert_eq!(array.first_occupied(0, &guard), None);
+
+    for elem in elems {
+        drop(unsafe { elem.into_owned() });
+    }
+}
+
+#[cfg(feature = "teaching")]
+#[test]
+fn visualize() {
+    let array = GrowableArray::<usize>::new();
+    let guard = pin();
+    assert!(array.visualize(&guard).contains("|root|"));
+
+    let one = Owned::new(1).into_shared(&guard);
+    array.store(3, one, Ordering::Relaxed, &guard);
+    let out = array.visualize(&guard);
+    assert!(out.contains("height: 1"));
+    assert!(out.contains("(1/"));
+    assert!(out.contains(&format!("{:#x}", one.as_raw() as usize)));
+
+    array.store(3, Shared::null(), Ordering::Relaxed, &guard);
+    drop(unsafe { one.into_owned() });
+}
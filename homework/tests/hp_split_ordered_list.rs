@@ -0,0 +1,105 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::HpSplitOrderedList;
+use std::sync::Arc;
+
+#[test]
+fn smoke() {
+    let list = HpSplitOrderedList::new();
+    assert!(list.is_empty());
+
+    for key in 0..1024 {
+        assert_eq!(list.insert(key, key * 2), Ok(()));
+    }
+    assert_eq!(list.insert(7, 0), Err(0));
+    assert_eq!(list.len(), 1024);
+    assert!(list.bucket_count() > 2);
+
+    for key in (0..1024).step_by(2) {
+        assert_eq!(list.delete(key), Ok(key * 2));
+    }
+    assert_eq!(list.delete(0), Err(()));
+    for key in 0..1024 {
+        let expected = if key % 2 == 0 { None } else { Some(key * 2) };
+        assert_eq!(list.lookup(key), expected);
+    }
+    assert_eq!(list.len(), 512);
+
+    assert_eq!(list.insert(usize::max_value(), 1), Ok(()));
+    assert_eq!(list.lookup(usize::max_value()), Some(1));
+}
+
+#[test]
+fn concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 4096;
+
+    let list = HpSplitOrderedList::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                for i in 0..STEPS {
+                    let key = t * STEPS + i;
+                    assert_eq!(list.insert(key, key.to_string()), Ok(()));
+                    assert_eq!(list.lookup(key), Some(key.to_string()));
+                    if i % 2 == 0 {
+                        assert_eq!(list.delete(key), Ok(key.to_string()));
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    assert_eq!(list.len(), THREADS * STEPS / 2);
+    for key in 0..THREADS * STEPS {
+        let expected = if key % 2 == 0 {
+            None
+        } else {
+            Some(key.to_string())
+        };
+        assert_eq!(list.lookup(key), expected);
+    }
+}
+
+#[test]
+fn contended_delete() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 1024;
+
+    let list = HpSplitOrderedList::new();
+    for key in 0..KEYS {
+        assert_eq!(list.insert(key, key), Ok(()));
+    }
+
+    // Every key is deleted exactly once.
+    let deleted: usize = scope(|s| {
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let list = &list;
+                s.spawn(move |_| (0..KEYS).filter(|&key| list.delete(key).is_ok()).count())
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+    .unwrap();
+
+    assert_eq!(deleted, KEYS);
+    assert!(list.is_empty());
+}
+
+#[test]
+fn drop_collects() {
+    let value = Arc::new(());
+    let list = HpSplitOrderedList::new();
+    for key in 0..16 {
+        assert_eq!(list.insert(key, value.clone()), Ok(()));
+    }
+    // Fewer deletions than the collection threshold, so the nodes are still retired.
+    for key in 0..8 {
+        drop(list.delete(key).unwrap());
+    }
+
+    drop(list);
+    assert_eq!(Arc::strong_count(&value), 1);
+}
@@ -38,6 +38,48 @@ fn smoke() {
     assert_eq!(set.remove(&3), Ok(3));
 }
 
+#[test]
+fn diff_iter() {
+    let left = OrderedListSet::new();
+    let right = OrderedListSet::new();
+    for i in 0..20 {
+        left.insert(i).unwrap();
+    }
+    for i in (0..30).step_by(3) {
+        right.insert(i).unwrap();
+    }
+
+    let diff = left.diff_iter(&right).cloned().collect::<Vec<_>>();
+    assert_eq!(diff, (0..20).filter(|i| i % 3 != 0).collect::<Vec<_>>());
+    assert_eq!(left.diff_iter(&left).count(), 0);
+}
+
+#[test]
+fn diff_iter_crossed() {
+    const STEPS: usize = 1000;
+    let left = OrderedListSet::new();
+    let right = OrderedListSet::new();
+    for i in 0..100 {
+        left.insert(i).unwrap();
+        right.insert(i + 50).unwrap();
+    }
+
+    // `a.diff_iter(&b)` and `b.diff_iter(&a)` concurrently must not deadlock.
+    thread::scope(|s| {
+        s.spawn(|_| {
+            for _ in 0..STEPS {
+                assert_eq!(left.diff_iter(&right).count(), 50);
+            }
+        });
+        s.spawn(|_| {
+            for _ in 0..STEPS {
+                assert_eq!(right.diff_iter(&left).count(), 50);
+            }
+        });
+    })
+    .unwrap();
+}
+
 #[test]
 fn parallel_iter_end() {
     let set = OrderedListSet::new();
@@ -0,0 +1,51 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::mailbox::Mailbox;
+
+#[test]
+fn smoke() {
+    let mailbox = Mailbox::new();
+    assert!(mailbox.is_empty());
+    assert_eq!(mailbox.take_all(), Vec::<i32>::new());
+
+    mailbox.post(1);
+    mailbox.post(2);
+    mailbox.post(3);
+    assert!(!mailbox.is_empty());
+    assert_eq!(mailbox.take_all(), vec![1, 2, 3]);
+    assert!(mailbox.is_empty());
+
+    // Dropped with the mailbox.
+    mailbox.post(4);
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 4096;
+
+    let mailbox = Mailbox::new();
+    let mut received = Vec::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let mailbox = &mailbox;
+            s.spawn(move |_| {
+                for i in 0..STEPS {
+                    mailbox.post((t, i));
+                }
+            });
+        }
+
+        while received.len() < THREADS * STEPS {
+            received.extend(mailbox.take_all());
+        }
+    })
+    .unwrap();
+
+    // Each producer's values arrive in posting order.
+    let mut next = vec![0; THREADS];
+    for (t, i) in received {
+        assert_eq!(i, next[t]);
+        next[t] += 1;
+    }
+    assert!(next.iter().all(|&n| n == STEPS));
+}
@@ -1,12 +1,12 @@
 use core::fmt;
 use core::hash::Hash;
 use core::marker::PhantomData;
+use cs492_concur_homework::reclaim::pin;
 use cs492_concur_homework::{ConcurrentMap, RandGen, SequentialMap};
 use std::collections::HashMap;
 
 use rand::prelude::*;
 
-use crossbeam_epoch::pin;
 use crossbeam_utils::thread;
 
 pub fn stress_sequential<
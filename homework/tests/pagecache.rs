@@ -0,0 +1,41 @@
+use cs492_concur_homework::pagecache::{MappingTable, PageHooks};
+use cs492_concur_homework::reclaim::{pin, Owned};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+#[derive(Debug, Default)]
+struct MemoryStorage {
+    pages: Mutex<HashMap<usize, String>>,
+}
+
+impl PageHooks<String> for MemoryStorage {
+    fn swap_in(&self, pid: usize) -> Option<String> {
+        self.pages.lock().unwrap().get(&pid).cloned()
+    }
+
+    fn swap_out(&self, pid: usize, page: &String) {
+        self.pages.lock().unwrap().insert(pid, page.clone());
+    }
+}
+
+#[test]
+fn smoke() {
+    let table = MappingTable::new(MemoryStorage::default());
+    let guard = pin();
+
+    let pid = table.allocate();
+    assert!(table.load(pid, &guard).is_null());
+
+    let page = table.load(pid, &guard);
+    let v1 = table
+        .compare_and_set(pid, page, Owned::new("v1".to_string()), &guard)
+        .unwrap();
+    assert!(table
+        .compare_and_set(pid, page, Owned::new("v2".to_string()), &guard)
+        .is_err());
+    assert_eq!(unsafe { v1.deref() }, "v1");
+
+    assert!(table.evict(pid, &guard));
+    assert!(!table.evict(pid, &guard));
+    assert_eq!(unsafe { table.load(pid, &guard).deref() }, "v1");
+}
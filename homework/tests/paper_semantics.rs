@@ -0,0 +1,151 @@
+//! Invariants of the split-ordered list from Shalev and Shavit, "Split-Ordered Lists: Lock-Free
+//! Extensible Hash Tables" (JACM 2006), checked against `SplitOrderedList`.
+
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::reclaim::pin;
+use cs492_concur_homework::{NonblockingMap, SplitOrderedList};
+
+/// The parent of bucket `b` is `b` with its most significant set bit cleared.
+fn parent(bucket: usize) -> usize {
+    bucket & !(1 << (63 - bucket.leading_zeros()))
+}
+
+#[test]
+fn sentinel_per_bucket() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+
+    for key in 0..1000 {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+
+    // Looking up a key initializes its bucket.
+    let size = list.bucket_count();
+    for key in 0..1000 {
+        assert_eq!(list.lookup(&key, &guard), Some(&key));
+    }
+
+    let sentinels = list.sentinels(&guard);
+    for key in 0..1000 {
+        assert!(
+            sentinels.contains(&(key % size)),
+            "no sentinel for key {}",
+            key
+        );
+    }
+}
+
+#[test]
+fn parent_initialized_before_child() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+
+    for key in (0..4096).step_by(7) {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+
+    let sentinels = list.sentinels(&guard);
+    assert!(sentinels.contains(&0));
+    for &bucket in sentinels.iter().filter(|&&b| b != 0) {
+        assert!(
+            sentinels.contains(&parent(bucket)),
+            "bucket {} is initialized but its parent {} is not",
+            bucket,
+            parent(bucket)
+        );
+    }
+}
+
+#[test]
+fn split_order() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+
+    for key in 0..1000 {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+
+    // Sentinels are sorted by their bit-reversed index and unique.
+    let sentinels = list.sentinels(&guard);
+    for pair in sentinels.windows(2) {
+        assert!(pair[0].reverse_bits() < pair[1].reverse_bits());
+    }
+
+    // Regular keys are sorted by their bit-reversed key and the sentinel of a bucket
+    // precedes the regular key equal to its index.
+    let keys: Vec<_> = list.iter_chunks(64).flatten().map(|(key, _)| key).collect();
+    assert_eq!(keys.len(), 1000);
+    for pair in keys.windows(2) {
+        assert!(pair[0].reverse_bits() < pair[1].reverse_bits());
+    }
+}
+
+#[test]
+fn bucket_count_monotonic() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 4096;
+
+    let list = SplitOrderedList::<usize>::new();
+
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                for i in 0..STEPS {
+                    let key = i * THREADS + t;
+                    assert_eq!(list.insert(&key, key, &pin()), Ok(()));
+                }
+            });
+        }
+
+        s.spawn(|_| {
+            let mut prev = list.bucket_count();
+            for _ in 0..STEPS {
+                let curr = list.bucket_count();
+                assert!(curr.is_power_of_two());
+                assert!(
+                    prev <= curr,
+                    "bucket count decreased from {} to {}",
+                    prev,
+                    curr
+                );
+                prev = curr;
+            }
+        });
+    })
+    .unwrap();
+}
+
+#[test]
+fn sparse_huge_table() {
+    const BUCKETS: usize = 1 << 40;
+
+    let list = SplitOrderedList::<usize>::builder()
+        .initial_buckets(BUCKETS)
+        .build();
+    let guard = pin();
+
+    // The bucket of the key has 40 uninitialized ancestors.
+    let key = BUCKETS - 1;
+    assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    assert_eq!(list.lookup(&key, &guard), Some(&key));
+
+    let mut chain = vec![key];
+    while *chain.last().unwrap() != 0 {
+        chain.push(parent(*chain.last().unwrap()));
+    }
+    let mut sentinels = list.sentinels(&guard);
+    sentinels.sort();
+    chain.sort();
+    assert_eq!(sentinels, chain);
+
+    // A sibling bucket is initialized from its closest initialized ancestor.
+    let key = (BUCKETS >> 1) + 5;
+    assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    assert_eq!(list.lookup(&key, &guard), Some(&key));
+    let sentinels = list.sentinels(&guard);
+    assert!(sentinels.contains(&key));
+    for &bucket in sentinels.iter().filter(|&&b| b != 0) {
+        assert!(sentinels.contains(&parent(bucket)));
+    }
+}
@@ -0,0 +1,44 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::publish::PublishTable;
+use std::sync::Arc;
+
+#[test]
+fn smoke() {
+    let table = PublishTable::new();
+    assert_eq!(table.get(3), None);
+
+    table.publish(3, Arc::new("v1"));
+    assert_eq!(table.get(3).as_deref(), Some(&"v1"));
+
+    table.publish(3, Arc::new("v2"));
+    assert_eq!(table.get(3).as_deref(), Some(&"v2"));
+
+    table.remove(3);
+    assert_eq!(table.get(3), None);
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 4096;
+
+    let table = PublishTable::new();
+    scope(|s| {
+        s.spawn(|_| {
+            for i in 0..STEPS {
+                table.publish(i % 16, Arc::new(i));
+            }
+        });
+
+        for _ in 0..THREADS {
+            s.spawn(|_| {
+                for i in 0..STEPS {
+                    if let Some(v) = table.get(i % 16) {
+                        assert_eq!(*v % 16, i % 16);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+}
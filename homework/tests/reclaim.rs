@@ -0,0 +1,29 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+use cs492_concur_homework::reclaim::maintenance_thread;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn maintenance_collects() {
+    let collected = Arc::new(AtomicBool::new(false));
+
+    {
+        let guard = crossbeam_epoch::pin();
+        let collected = collected.clone();
+        guard.defer(move || collected.store(true, Ordering::Release));
+        guard.flush();
+    }
+
+    // This thread never pins again, so only the maintenance thread can collect the garbage.
+    let maintenance = maintenance_thread(Duration::from_millis(1));
+    for _ in 0..1000 {
+        if collected.load(Ordering::Acquire) {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    drop(maintenance);
+
+    assert!(collected.load(Ordering::Acquire));
+}
@@ -0,0 +1,88 @@
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::reclaim::pin;
+use cs492_concur_homework::replica::ReplicatedMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn smoke() {
+    let map = ReplicatedMap::new(2);
+    let guard = pin();
+
+    assert_eq!(map.insert(&1, "a", &guard), Ok(()));
+    assert_eq!(map.insert(&1, "b", &guard), Err("b"));
+    assert_eq!(map.lookup_primary(&1, &guard), Some(&"a"));
+    assert_eq!(map.lookup(&1, &guard), None);
+
+    assert_eq!(map.sync(&guard), 2);
+    assert_eq!(map.sync(&guard), 0);
+    for replica in 0..map.replica_count() {
+        assert_eq!(map.lookup_in(replica, &1, &guard), Some(&"a"));
+    }
+
+    assert_eq!(map.delete(&1, &guard), Ok(&"a"));
+    assert_eq!(map.delete(&1, &guard), Err(()));
+    assert_eq!(map.lookup(&1, &guard), Some(&"a"));
+    map.sync(&guard);
+    assert_eq!(map.lookup(&1, &guard), None);
+}
+
+#[test]
+fn stress_concurrent() {
+    const WRITERS: usize = 4;
+    const READERS: usize = 4;
+    const STEPS: usize = 4096;
+
+    let map = ReplicatedMap::new(READERS);
+    let done = AtomicBool::new(false);
+    scope(|s| {
+        let mut writers = Vec::new();
+        for t in 0..WRITERS {
+            let map = &map;
+            writers.push(s.spawn(move |_| {
+                for i in 0..STEPS {
+                    let guard = pin();
+                    let key = (i * WRITERS + t) % 256;
+                    if map.insert(&key, key, &guard).is_err() {
+                        let _ = map.delete(&key, &guard);
+                    }
+                }
+            }));
+        }
+
+        s.spawn(|_| {
+            while !done.load(Ordering::Acquire) {
+                map.sync(&pin());
+            }
+        });
+
+        for _ in 0..READERS {
+            s.spawn(|_| {
+                while !done.load(Ordering::Acquire) {
+                    let guard = pin();
+                    for key in 0..256 {
+                        if let Some(value) = map.lookup(&key, &guard) {
+                            assert_eq!(*value, key);
+                        }
+                    }
+                }
+            });
+        }
+
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        done.store(true, Ordering::Release);
+    })
+    .unwrap();
+
+    let guard = pin();
+    map.sync(&guard);
+    for replica in 0..map.replica_count() {
+        for key in 0..256 {
+            assert_eq!(
+                map.lookup_in(replica, &key, &guard),
+                map.lookup_primary(&key, &guard)
+            );
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use cs492_concur_homework::reclaim::pin;
+use cs492_concur_homework::scope::StructureScope;
+use cs492_concur_homework::{NonblockingMap, SplitOrderedList};
+
+#[test]
+fn smoke() {
+    const THREADS: usize = 4;
+    const STEPS: usize = 1024;
+
+    let mut scope = StructureScope::new(SplitOrderedList::<usize>::new());
+    for t in 0..THREADS {
+        scope.spawn(move |list| {
+            for i in 0..STEPS {
+                let key = t * STEPS + i;
+                assert!(list.insert(&key, key, &pin()).is_ok());
+            }
+        });
+    }
+
+    let list = scope.join();
+    for key in 0..THREADS * STEPS {
+        assert_eq!(list.lookup(&key, &pin()), Some(&key));
+    }
+}
+
+#[test]
+fn drop_joins_workers() {
+    const THREADS: usize = 8;
+
+    let scope = {
+        let mut scope = StructureScope::new(AtomicUsize::new(0));
+        for _ in 0..THREADS {
+            scope.spawn(|count| {
+                let _ = count.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        scope
+    };
+    assert_eq!(scope.join().into_inner(), THREADS);
+
+    let mut scope = StructureScope::new(());
+    let done = std::sync::Arc::new(AtomicUsize::new(0));
+    for _ in 0..THREADS {
+        let done = done.clone();
+        scope.spawn(move |_| {
+            let _ = done.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+    drop(scope);
+    assert_eq!(done.load(Ordering::Relaxed), THREADS);
+}
+
+#[test]
+fn panic_propagates() {
+    let mut scope = StructureScope::new(AtomicUsize::new(0));
+    scope.spawn(|_| panic!("worker"));
+    scope.spawn(|count| {
+        let _ = count.fetch_add(1, Ordering::Relaxed);
+    });
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| scope.join()));
+    assert!(result.is_err());
+}
@@ -0,0 +1,218 @@
+use core::hash::{BuildHasher, BuildHasherDefault, Hasher};
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::reclaim::pin;
+use cs492_concur_homework::{NonblockingConcurrentMap, NonblockingMap, SplitOrderedHashMap};
+use std::collections::hash_map::{DefaultHasher, RandomState};
+
+pub mod map;
+
+/// Hashes every key to the same value.
+#[derive(Debug, Default)]
+struct CollidingHasher;
+
+impl Hasher for CollidingHasher {
+    fn finish(&self) -> u64 {
+        42
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {}
+}
+
+/// Builds hashers that hash the keys after a seed.
+#[derive(Debug, Clone, Copy)]
+struct Seeded(u64);
+
+impl BuildHasher for Seeded {
+    type Hasher = DefaultHasher;
+
+    fn build_hasher(&self) -> DefaultHasher {
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u64(self.0);
+        hasher
+    }
+}
+
+#[test]
+fn smoke() {
+    let map = SplitOrderedHashMap::<String, usize, _>::new();
+    let guard = pin();
+
+    assert_eq!(map.insert(&"cat".to_string(), 1, &guard), Ok(()));
+    assert_eq!(map.insert(&"cat".to_string(), 2, &guard), Err(2));
+    assert_eq!(map.lookup(&"cat".to_string(), &guard), Some(&1));
+    assert_eq!(map.lookup(&"fox".to_string(), &guard), None);
+
+    assert_eq!(map.insert_owned("fox".to_string(), 3, &guard), Ok(()));
+    assert_eq!(
+        map.insert_owned("fox".to_string(), 4, &guard),
+        Err(("fox".to_string(), 4))
+    );
+
+    assert_eq!(map.delete(&"cat".to_string(), &guard), Ok(&1));
+    assert_eq!(map.delete(&"cat".to_string(), &guard), Err(()));
+    assert_eq!(map.lookup(&"cat".to_string(), &guard), None);
+    assert_eq!(map.lookup(&"fox".to_string(), &guard), Some(&3));
+}
+
+#[test]
+fn get_key_value() {
+    let map = SplitOrderedHashMap::<String, usize, _>::new();
+    let guard = pin();
+
+    let interned = "cat".to_string();
+    let ptr = interned.as_ptr();
+    assert_eq!(map.insert_owned(interned, 1, &guard), Ok(()));
+
+    // Looks up by `&str` and returns the stored `String`.
+    let (key, value) = map.get_key_value("cat", &guard).unwrap();
+    assert_eq!((key.as_str(), *value), ("cat", 1));
+    assert_eq!(key.as_ptr(), ptr);
+    assert_eq!(
+        map.get_key_value(&"cat".to_string(), &guard),
+        Some((key, value))
+    );
+    assert_eq!(map.get_key_value("fox", &guard), None);
+
+    assert_eq!(map.delete(&"cat".to_string(), &guard), Ok(&1));
+    assert_eq!(map.get_key_value("cat", &guard), None);
+}
+
+#[test]
+fn collisions() {
+    let map = SplitOrderedHashMap::<usize, usize, BuildHasherDefault<CollidingHasher>>::default();
+    let guard = pin();
+
+    for key in 0..16 {
+        assert_eq!(map.insert(&key, key * 2, &guard), Ok(()));
+    }
+    for key in 0..16 {
+        assert_eq!(map.lookup(&key, &guard), Some(&(key * 2)));
+    }
+    for key in (0..16).step_by(2) {
+        assert_eq!(map.delete(&key, &guard), Ok(&(key * 2)));
+    }
+    for key in 0..16 {
+        let expected = if key % 2 == 0 { None } else { Some(key * 2) };
+        assert_eq!(map.lookup(&key, &guard), expected.as_ref());
+    }
+}
+
+#[test]
+fn collisions_concurrent() {
+    const THREADS: usize = 4;
+    const KEYS: usize = 64;
+
+    let map = SplitOrderedHashMap::<usize, usize, BuildHasherDefault<CollidingHasher>>::default();
+
+    // Every thread inserts every key into the same group of nodes, so exactly one of them wins
+    // each key.
+    let inserted = scope(|s| {
+        let handles = (0..THREADS)
+            .map(|t| {
+                let map = &map;
+                s.spawn(move |_| {
+                    let guard = pin();
+                    (0..KEYS)
+                        .filter(|&key| map.insert(&key, t, &guard).is_ok())
+                        .count()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .sum::<usize>()
+    })
+    .unwrap();
+    assert_eq!(inserted, KEYS);
+
+    let guard = pin();
+    for key in 0..KEYS {
+        assert!(map.delete(&key, &guard).is_ok());
+        assert_eq!(map.lookup(&key, &guard), None);
+    }
+}
+
+#[test]
+fn stress_sequential() {
+    const STEPS: usize = 4096;
+    map::stress_concurrent_sequential::<
+        String,
+        NonblockingConcurrentMap<_, _, SplitOrderedHashMap<String, usize, RandomState>>,
+    >(STEPS);
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+    map::stress_concurrent::<
+        String,
+        NonblockingConcurrentMap<_, _, SplitOrderedHashMap<String, usize, RandomState>>,
+    >(THREADS, STEPS);
+}
+
+#[test]
+fn rehash() {
+    let map = SplitOrderedHashMap::<usize, usize, _>::with_hasher(Seeded(0));
+    let guard = pin();
+
+    for key in 0..1000 {
+        assert_eq!(map.insert(&key, key * 2, &guard), Ok(()));
+    }
+    for key in (0..1000).step_by(3) {
+        assert_eq!(map.delete(&key, &guard), Ok(&(key * 2)));
+    }
+
+    for seed in 1..4 {
+        assert!(map.rehash(Seeded(seed), &guard).is_ok());
+        for key in 0..1000 {
+            let expected = if key % 3 == 0 { None } else { Some(key * 2) };
+            assert_eq!(map.lookup(&key, &guard), expected.as_ref());
+        }
+    }
+
+    assert_eq!(map.insert(&0, 0, &guard), Ok(()));
+    assert_eq!(map.insert(&1, 0, &guard), Err(0));
+    assert_eq!(map.delete(&1, &guard), Ok(&2));
+}
+
+#[test]
+fn rehash_concurrent() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 1024;
+
+    let map = SplitOrderedHashMap::<usize, usize, _>::with_hasher(Seeded(0));
+    for key in 0..KEYS * THREADS {
+        assert_eq!(map.insert(&key, key, &pin()), Ok(()));
+    }
+
+    // Each thread deletes its even keys and reinserts them while the table is rehashed.
+    scope(|s| {
+        for t in 0..THREADS {
+            let map = &map;
+            s.spawn(move |_| {
+                for key in (t * KEYS..(t + 1) * KEYS).step_by(2) {
+                    let guard = pin();
+                    assert_eq!(map.lookup(&(key + 1), &guard), Some(&(key + 1)));
+                    assert_eq!(map.delete(&key, &guard), Ok(&key));
+                    assert_eq!(map.lookup(&key, &guard), None);
+                    assert_eq!(map.insert(&key, key * 2, &guard), Ok(()));
+                }
+            });
+        }
+
+        s.spawn(|_| {
+            for seed in 1..8 {
+                assert!(map.rehash(Seeded(seed), &pin()).is_ok());
+            }
+        });
+    })
+    .unwrap();
+
+    let guard = pin();
+    for key in 0..KEYS * THREADS {
+        let expected = if key % 2 == 0 { key * 2 } else { key };
+        assert_eq!(map.lookup(&key, &guard), Some(&expected));
+    }
+}
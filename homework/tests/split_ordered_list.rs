@@ -1,5 +1,14 @@
-use crossbeam_epoch as epoch;
-use cs492_concur_homework::{NonblockingConcurrentMap, NonblockingMap, SplitOrderedList};
+use crossbeam_utils::thread::scope;
+use cs492_concur_homework::append_log::AppendLog;
+use cs492_concur_homework::budget::MemoryBudget;
+use cs492_concur_homework::reclaim::pin;
+use cs492_concur_homework::testing::scenarios;
+use cs492_concur_homework::{
+    Entry, GrowthPolicy, InsertError, LogOp, Lookup, NonblockingConcurrentMap, NonblockingMap,
+    SplitOrderedList, SplitOrderedSet, TryInsertError,
+};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 
 pub mod map;
 
@@ -7,7 +16,7 @@ pub mod map;
 pub fn smoke() {
     let list = SplitOrderedList::<usize>::new();
 
-    let guard = epoch::pin();
+    let guard = pin();
 
     assert_eq!(list.insert(&37, 37, &guard), Ok(()));
     assert_eq!(list.lookup(&42, &guard), None);
@@ -26,6 +35,1389 @@ pub fn smoke() {
     assert_eq!(list.lookup(&37, &guard), None);
 }
 
+#[test]
+fn iter_chunks() {
+    let list = SplitOrderedList::<usize>::new();
+
+    {
+        let guard = pin();
+        for i in 0..100 {
+            assert_eq!(list.insert(&i, i * 2, &guard), Ok(()));
+        }
+    }
+
+    let chunks: Vec<_> = list.iter_chunks(16).collect();
+    assert_eq!(chunks.len(), 7);
+    assert!(chunks.iter().all(|chunk| chunk.len() <= 16));
+
+    let mut entries: Vec<_> = chunks.into_iter().flatten().collect();
+    entries.sort();
+    assert_eq!(entries, (0..100).map(|i| (i, i * 2)).collect::<Vec<_>>());
+}
+
+#[test]
+fn iter() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+    assert_eq!(list.iter(&guard).count(), 0);
+
+    for i in 0..100 {
+        assert_eq!(list.insert(&i, i * 2, &guard), Ok(()));
+    }
+    for i in (0..100).step_by(3) {
+        assert_eq!(list.delete(&i, &guard), Ok(&(i * 2)));
+    }
+
+    let mut entries: Vec<_> = list
+        .iter(&guard)
+        .map(|(key, value)| (key, *value))
+        .collect();
+    entries.sort();
+    assert_eq!(
+        entries,
+        (0..100)
+            .filter(|i| i % 3 != 0)
+            .map(|i| (i, i * 2))
+            .collect::<Vec<_>>()
+    );
+
+    let keys: Vec<_> = list.keys(&guard).collect();
+    let values: Vec<_> = list.values(&guard).copied().collect();
+    assert_eq!(keys.len(), entries.len());
+    assert!(keys
+        .iter()
+        .zip(&values)
+        .all(|(key, value)| key * 2 == *value));
+}
+
+#[test]
+fn first() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+    assert_eq!(list.first(&guard), None);
+
+    for key in (100..1000).rev().step_by(7) {
+        assert_eq!(list.insert(&key, key * 2, &guard), Ok(()));
+    }
+    assert_eq!(list.insert(&usize::max_value(), 0, &guard), Ok(()));
+    let mut keys: Vec<_> = list.keys(&guard).collect();
+    keys.sort();
+
+    // Pops the entries in the order of the keys.
+    for key in keys {
+        assert_eq!(
+            list.first(&guard),
+            Some((key, list.lookup(&key, &guard).unwrap()))
+        );
+        assert!(list.delete(&key, &guard).is_ok());
+    }
+    assert_eq!(list.first(&guard), None);
+
+    // Only the initialized buckets are walked, and none is initialized on the way.
+    let list = SplitOrderedList::builder().initial_buckets(1 << 16).build();
+    for &key in [70_000, 12_345, 99].iter() {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    let buckets = list.buckets(&guard).count();
+    assert_eq!(list.first(&guard), Some((99, &99)));
+    assert_eq!(list.buckets(&guard).count(), buckets);
+}
+
+#[test]
+fn iter_key_order() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+    for i in (0..1000).rev().step_by(7) {
+        assert_eq!(list.insert(&i, i * 2, &guard), Ok(()));
+    }
+    assert_eq!(list.insert(&usize::max_value(), 0, &guard), Ok(()));
+
+    let entries: Vec<_> = list
+        .iter_key_order(&guard)
+        .map(|(key, value)| (key, *value))
+        .collect();
+    let mut expected: Vec<_> = (0..1000).rev().step_by(7).map(|i| (i, i * 2)).collect();
+    expected.reverse();
+    expected.push((usize::max_value(), 0));
+    assert_eq!(entries, expected);
+}
+
+#[test]
+fn len() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    assert!(list.is_empty());
+    assert_eq!(list.bucket_count(), 2);
+
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                let guard = pin();
+                for i in 0..STEPS {
+                    let key = t * STEPS + i;
+                    assert_eq!(list.insert(&key, key, &guard), Ok(()));
+                    if i % 2 == 0 {
+                        assert_eq!(list.delete(&key, &guard), Ok(&key));
+                    }
+                    assert!(list.len() <= THREADS * STEPS);
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    assert_eq!(list.len(), THREADS * STEPS / 2);
+    assert!(list.len_approx() >= list.len() / 2 && list.len_approx() <= list.len() * 2);
+    assert!(!list.is_empty());
+    assert!(list.bucket_count() > 2);
+}
+
+#[test]
+fn shrink_buckets() {
+    let mut list = SplitOrderedList::<usize>::new();
+
+    {
+        let guard = pin();
+        for key in 0..1024 {
+            assert_eq!(list.insert(&key, key, &guard), Ok(()));
+        }
+        let peak = list.bucket_count();
+        for key in 0..1000 {
+            assert_eq!(list.delete(&key, &guard), Ok(&key));
+        }
+        assert!(list.bucket_count() < peak);
+        assert!(list
+            .sentinels(&guard)
+            .iter()
+            .any(|&b| b >= list.bucket_count()));
+    }
+
+    {
+        let guard = pin();
+        assert!(list.compact(&guard) > 0);
+        assert_eq!(list.compact(&guard), 0);
+    }
+    let _ = list.shrink_buckets();
+
+    let guard = pin();
+    let size = list.bucket_count();
+    assert!(list.sentinels(&guard).iter().all(|&b| b < size));
+    for key in 0..1024 {
+        let expected = if key < 1000 { None } else { Some(&key) };
+        assert_eq!(list.lookup(&key, &guard), expected);
+    }
+    for key in 0..1000 {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    assert_eq!(list.len(), 1024);
+}
+
+#[test]
+fn compact_concurrent() {
+    const THREADS: usize = 4;
+    const KEYS: usize = 4096;
+
+    let list = SplitOrderedList::<usize>::new();
+    {
+        let guard = pin();
+        for key in 0..KEYS {
+            assert_eq!(list.insert(&key, key, &guard), Ok(()));
+        }
+        // Keeps every 16th key, and leaves the sentinels of the unused buckets in the list.
+        for key in (0..KEYS).filter(|key| key % 16 != 0) {
+            assert_eq!(list.delete(&key, &guard), Ok(&key));
+        }
+    }
+
+    // The operations started with the old number of buckets go through the removed sentinels.
+    let done = AtomicBool::new(false);
+    scope(|s| {
+        for t in 0..THREADS {
+            let (list, done) = (&list, &done);
+            s.spawn(move |_| {
+                while !done.load(Ordering::Acquire) {
+                    let guard = pin();
+                    for key in (0..KEYS).step_by(16) {
+                        assert_eq!(list.lookup(&key, &guard), Some(&key));
+                    }
+                    let key = KEYS + t;
+                    assert_eq!(list.insert(&key, key, &guard), Ok(()));
+                    assert_eq!(list.delete(&key, &guard), Ok(&key));
+                }
+            });
+        }
+
+        let guard = pin();
+        assert!(list.compact(&guard) > 0);
+        done.store(true, Ordering::Release);
+    })
+    .unwrap();
+
+    let guard = pin();
+    let size = list.bucket_count();
+    assert!(list.sentinels(&guard).iter().all(|&b| b < size));
+    let mut keys: Vec<_> = list.keys(&guard).collect();
+    keys.sort();
+    assert_eq!(keys, (0..KEYS).step_by(16).collect::<Vec<_>>());
+}
+
+#[test]
+fn stats() {
+    const KEYS: usize = 4096;
+
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+    let empty = list.stats(&guard);
+    assert_eq!(empty.max_probe_distance, 0);
+    assert!(empty.mean_probe_distance <= 0.0);
+
+    for key in 0..KEYS {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    let stats = list.stats(&guard);
+    assert_eq!(stats.bucket_count, list.bucket_count());
+    assert_eq!(stats.sentinels, stats.chain_lengths.len());
+    assert!(stats.sentinels <= stats.bucket_count);
+    assert_eq!(
+        stats
+            .chain_lengths
+            .iter()
+            .map(|&(_, len)| len)
+            .sum::<usize>(),
+        KEYS
+    );
+
+    // The table grew with the entries.
+    assert!(stats.load_factor < 3.0);
+    assert!(stats.mean_probe_distance >= 1.0);
+    assert!(stats.mean_probe_distance <= stats.max_probe_distance as f64);
+}
+
+#[test]
+fn builder() {
+    let list = SplitOrderedList::<usize>::builder()
+        .load_factor(4)
+        .initial_buckets(16)
+        .build();
+    let guard = pin();
+    assert_eq!(list.bucket_count(), 16);
+
+    // Grows once the average bucket has more than 4 entries. The insertions only check the count
+    // when the stripe of their key suggests so, so it may take a few more of them.
+    for key in 0..65 {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    assert_eq!(list.bucket_count(), 16);
+    for key in 65..80 {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    assert_eq!(list.bucket_count(), 32);
+
+    // Never shrinks below the initial number of buckets.
+    for key in 0..80 {
+        assert_eq!(list.delete(&key, &guard), Ok(&key));
+    }
+    assert_eq!(list.bucket_count(), 16);
+}
+
+#[test]
+fn with_capacity() {
+    let list = SplitOrderedList::<usize>::with_capacity(1000);
+    let guard = pin();
+    assert_eq!(list.bucket_count(), 512);
+    // Every bucket is initialized before the first insertion.
+    assert_eq!(list.sentinels(&guard).len(), 512);
+
+    for key in 0..1000 {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    assert_eq!(list.bucket_count(), 512);
+    assert_eq!(list.sentinels(&guard).len(), 512);
+    for key in 0..1000 {
+        assert_eq!(list.delete(&key, &guard), Ok(&key));
+    }
+    assert_eq!(list.bucket_count(), 512);
+
+    assert_eq!(
+        SplitOrderedList::<usize>::with_capacity(0).bucket_count(),
+        2
+    );
+}
+
+#[test]
+fn growth_policy() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 4096;
+
+    for &policy in [GrowthPolicy::Double, GrowthPolicy::Fit].iter() {
+        let list = SplitOrderedList::<usize>::builder()
+            .growth_policy(policy)
+            .build();
+        scope(|s| {
+            for t in 0..THREADS {
+                let list = &list;
+                s.spawn(move |_| {
+                    let guard = pin();
+                    for key in (t..KEYS).step_by(THREADS) {
+                        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+                    }
+                });
+            }
+        })
+        .unwrap();
+
+        // Always a power of two, and never more than twice what the entries need.
+        let buckets = list.bucket_count();
+        assert!(buckets.is_power_of_two());
+        assert!(buckets <= KEYS);
+        let guard = pin();
+        for key in 0..KEYS {
+            assert_eq!(list.lookup(&key, &guard), Some(&key));
+        }
+    }
+
+    // Single-threaded, each trigger only needs one doubling.
+    let list = SplitOrderedList::<usize>::builder()
+        .load_factor(4)
+        .initial_buckets(16)
+        .growth_policy(GrowthPolicy::Fit)
+        .build();
+    let guard = pin();
+    for key in 0..80 {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    assert_eq!(list.bucket_count(), 32);
+}
+
+#[test]
+fn try_insert() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 256;
+
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+    assert_eq!(list.try_insert(&1, 10, &guard), Ok(&10));
+    assert_eq!(
+        list.try_insert(&1, 20, &guard),
+        Err(TryInsertError::Occupied(20, &10))
+    );
+    assert_eq!(list.delete(&1, &guard), Ok(&10));
+    assert_eq!(list.try_insert(&1, 30, &guard), Ok(&30));
+
+    // Every loser sees the value of the single winner of its key.
+    let list = SplitOrderedList::<usize>::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                let guard = pin();
+                for key in 0..KEYS {
+                    let value = key * THREADS + t;
+                    match list.try_insert(&key, value, &guard) {
+                        Ok(inserted) => assert_eq!(*inserted, value),
+                        Err(TryInsertError::Occupied(rejected, existing)) => {
+                            assert_eq!(rejected, value);
+                            assert_eq!(existing / THREADS, key);
+                            assert_eq!(list.lookup(&key, &guard), Some(existing));
+                        }
+                        Err(e) => panic!("unexpected error {:?}", e),
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(list.len(), KEYS);
+
+    // An exhausted budget is reported, not a panic.
+    let list = SplitOrderedList::<usize>::with_budget(MemoryBudget::new(0));
+    assert_eq!(
+        list.try_insert(&1, 10, &guard),
+        Err(TryInsertError::OutOfBudget(10))
+    );
+    assert!(list.is_empty());
+}
+
+#[test]
+fn get_or_insert_with() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 256;
+
+    let list = SplitOrderedList::<usize>::new();
+    let calls = AtomicUsize::new(0);
+
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            let calls = &calls;
+            s.spawn(move |_| {
+                let guard = pin();
+                for key in 0..KEYS {
+                    let value = list
+                        .get_or_insert_with(
+                            &key,
+                            || {
+                                let _ = calls.fetch_add(1, Ordering::Relaxed);
+                                key * THREADS + t
+                            },
+                            &guard,
+                        )
+                        .unwrap();
+                    assert_eq!(value / THREADS, key);
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    // Each produced value was either inserted or lost a race.
+    assert!(calls.load(Ordering::Relaxed) >= KEYS);
+    assert_eq!(list.len(), KEYS);
+
+    let guard = pin();
+    let existing = *list.lookup(&0, &guard).unwrap();
+    assert_eq!(
+        list.get_or_insert_with(&0, || panic!("called for an existing key"), &guard),
+        Ok(&existing)
+    );
+}
+
+#[test]
+fn update() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 256;
+
+    let list = SplitOrderedList::<usize>::new();
+    {
+        let guard = pin();
+        assert_eq!(list.update(&1, 10, &guard), Err(10));
+        assert_eq!(list.insert(&1, 1, &guard), Ok(()));
+        assert_eq!(list.update(&1, 10, &guard), Ok(&1));
+        assert_eq!(list.lookup(&1, &guard), Some(&10));
+        assert_eq!(list.delete(&1, &guard), Ok(&10));
+        assert_eq!(list.update(&1, 100, &guard), Err(100));
+        assert_eq!(list.lookup(&1, &guard), None);
+
+        for key in 0..KEYS {
+            assert_eq!(list.insert(&key, THREADS, &guard), Ok(()));
+        }
+    }
+
+    // Updates racing with the deletions never resurrect a key.
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                let guard = pin();
+                for key in 0..KEYS {
+                    if let Ok(old) = list.update(&key, t, &guard) {
+                        assert!(*old <= THREADS);
+                    }
+                }
+            });
+        }
+        let list = &list;
+        s.spawn(move |_| {
+            let guard = pin();
+            for key in 0..KEYS {
+                assert!(*list.delete(&key, &guard).unwrap() <= THREADS);
+            }
+        });
+    })
+    .unwrap();
+
+    let guard = pin();
+    assert_eq!(list.len(), 0);
+    for key in 0..KEYS {
+        assert_eq!(list.lookup(&key, &guard), None);
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    assert_eq!(list.len(), KEYS);
+}
+
+#[test]
+fn delete_align_one() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 256;
+
+    // The values have no spare low bit in their address, and the deletions still linearize.
+    let list = SplitOrderedList::<u8>::new();
+    {
+        let guard = pin();
+        for key in 0..KEYS {
+            assert_eq!(list.insert(&key, 0, &guard), Ok(()));
+        }
+    }
+
+    let deleted = AtomicUsize::new(0);
+    scope(|s| {
+        for t in 0..THREADS {
+            let (list, deleted) = (&list, &deleted);
+            s.spawn(move |_| {
+                let guard = pin();
+                for key in 0..KEYS {
+                    if t % 2 == 0 {
+                        let _ = list.update(&key, t as u8, &guard);
+                    } else if list.delete(&key, &guard).is_ok() {
+                        let _ = deleted.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = pin();
+    assert_eq!(deleted.load(Ordering::Relaxed), KEYS);
+    assert_eq!(list.len(), 0);
+    for key in 0..KEYS {
+        assert_eq!(list.lookup(&key, &guard), None);
+        assert_eq!(list.update(&key, 1, &guard), Err(1));
+    }
+}
+
+#[test]
+fn compare_and_update() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    {
+        let guard = pin();
+        assert_eq!(list.compare_and_update(&1, &0, 1, &guard), Err(1));
+        assert_eq!(list.insert(&1, 0, &guard), Ok(()));
+        assert_eq!(list.compare_and_update(&1, &5, 1, &guard), Err(1));
+        assert_eq!(list.compare_and_update(&1, &0, 1, &guard), Ok(&0));
+        assert_eq!(list.update_if(&1, 2, |v| v % 2 == 0, &guard), Err(2));
+        assert_eq!(list.update_if(&1, 0, |v| v % 2 == 1, &guard), Ok(&1));
+    }
+
+    // Lock-free read-modify-write loops don't lose increments.
+    scope(|s| {
+        for _ in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                for _ in 0..STEPS {
+                    let guard = pin();
+                    loop {
+                        let current = *list.lookup(&1, &guard).unwrap();
+                        if list
+                            .compare_and_update(&1, &current, current + 1, &guard)
+                            .is_ok()
+                        {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = pin();
+    assert_eq!(list.lookup(&1, &guard), Some(&(THREADS * STEPS)));
+}
+
+#[test]
+fn upsert() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 256;
+
+    let list = SplitOrderedList::<usize>::new();
+    {
+        let guard = pin();
+        assert_eq!(list.upsert(&1, 1, &guard), Ok(None));
+        assert_eq!(list.upsert(&1, 2, &guard), Ok(Some(&1)));
+        assert_eq!(list.lookup(&1, &guard), Some(&2));
+        assert_eq!(list.delete(&1, &guard), Ok(&2));
+        assert_eq!(list.upsert(&1, 3, &guard), Ok(None));
+        assert_eq!(list.delete(&1, &guard), Ok(&3));
+    }
+
+    // Exactly one upsert per key finds it absent.
+    let inserted = AtomicUsize::new(0);
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            let inserted = &inserted;
+            s.spawn(move |_| {
+                let guard = pin();
+                for key in 0..KEYS {
+                    if list.upsert(&key, t, &guard).unwrap().is_none() {
+                        let _ = inserted.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    assert_eq!(inserted.load(Ordering::Relaxed), KEYS);
+    assert_eq!(list.len(), KEYS);
+}
+
+#[test]
+fn remove_if() {
+    const STEPS: usize = 4096;
+
+    let list = SplitOrderedList::<usize>::new();
+    {
+        let guard = pin();
+        assert_eq!(list.remove_if(&1, |_| true, &guard), Err(()));
+        assert_eq!(list.insert(&1, 1, &guard), Ok(()));
+        assert_eq!(list.remove_if(&1, |v| v % 2 == 0, &guard), Err(()));
+        assert_eq!(list.lookup(&1, &guard), Some(&1));
+        assert_eq!(list.remove_if(&1, |v| v % 2 == 1, &guard), Ok(&1));
+        assert_eq!(list.lookup(&1, &guard), None);
+    }
+
+    // An update that lands between the check and the deletion makes the removal fail, so only
+    // even values are removed.
+    assert_eq!(list.insert(&1, 0, &pin()), Ok(()));
+    scope(|s| {
+        s.spawn(|_| {
+            for step in 1..STEPS {
+                let guard = pin();
+                if list.update(&1, step, &guard).is_err() {
+                    assert_eq!(list.insert(&1, step, &guard), Ok(()));
+                }
+            }
+        });
+        s.spawn(|_| {
+            for _ in 0..STEPS {
+                if let Ok(value) = list.remove_if(&1, |v| v % 2 == 0, &pin()) {
+                    assert_eq!(value % 2, 0);
+                }
+            }
+        });
+    })
+    .unwrap();
+}
+
+#[test]
+fn remove_owned() {
+    let list = SplitOrderedList::new();
+    for key in 0..64 {
+        assert_eq!(list.insert(&key, key.to_string(), &pin()), Ok(()));
+    }
+
+    for key in (0..64).step_by(2) {
+        assert_eq!(list.remove_owned(&key), Ok(key.to_string()));
+        assert_eq!(list.remove_owned(&key), Err(()));
+    }
+    assert_eq!(list.len(), 32);
+    let guard = pin();
+    for key in 0..64 {
+        let expected = if key % 2 == 0 {
+            None
+        } else {
+            Some(key.to_string())
+        };
+        assert_eq!(list.lookup(&key, &guard), expected.as_ref());
+    }
+
+    // The key can be inserted again.
+    assert_eq!(list.insert(&0, "zero".to_string(), &guard), Ok(()));
+    assert_eq!(list.lookup(&0, &guard), Some(&"zero".to_string()));
+    drop(guard);
+
+    // Values of alignment 1.
+    let list = SplitOrderedList::<u8>::new();
+    for key in 0..64 {
+        assert_eq!(list.insert(&key, key as u8, &pin()), Ok(()));
+    }
+    for key in 0..64 {
+        assert_eq!(list.remove_owned(&key), Ok(key as u8));
+        assert_eq!(list.remove_owned(&key), Err(()));
+    }
+    assert!(list.is_empty());
+}
+
+#[test]
+fn remove_owned_waits_for_readers() {
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    let list = SplitOrderedList::new();
+    assert_eq!(list.insert(&1, "one".to_string(), &pin()), Ok(()));
+    let read = AtomicBool::new(false);
+    let released = AtomicBool::new(false);
+
+    scope(|s| {
+        s.spawn(|_| {
+            let guard = pin();
+            let value = list.lookup(&1, &guard).unwrap();
+            read.store(true, Ordering::SeqCst);
+            sleep(Duration::from_millis(100));
+            // Still readable: the value can't be moved out while the reader is pinned.
+            assert_eq!(value, "one");
+            released.store(true, Ordering::SeqCst);
+        });
+
+        while !read.load(Ordering::SeqCst) {}
+        assert_eq!(list.remove_owned(&1), Ok("one".to_string()));
+        assert!(released.load(Ordering::SeqCst));
+    })
+    .unwrap();
+}
+
+#[test]
+#[should_panic(expected = "pinned")]
+fn remove_owned_pinned() {
+    let list = SplitOrderedList::new();
+    let guard = pin();
+    assert_eq!(list.insert(&1, 1, &guard), Ok(()));
+    let _ = list.remove_owned(&1);
+}
+
+#[test]
+fn entry() {
+    const THREADS: usize = 8;
+    const STEPS: usize = 512;
+
+    let list = SplitOrderedList::<usize>::new();
+    {
+        let guard = pin();
+        match list.entry(1, &guard) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), 1);
+                assert_eq!(entry.insert(10), Ok(&10));
+            }
+            Entry::Occupied(_) => panic!("key 1 is not inserted yet"),
+        }
+        match list.entry(1, &guard) {
+            Entry::Occupied(entry) => {
+                assert_eq!(entry.get(), Some(&10));
+                assert_eq!(entry.insert(11), Ok(&10));
+                assert_eq!(entry.remove(), Ok(&11));
+            }
+            Entry::Vacant(_) => panic!("key 1 is inserted"),
+        }
+        assert_eq!(list.lookup(&1, &guard), None);
+        assert_eq!(list.entry(1 << 63, &guard).key(), 1 << 63);
+
+        for key in 0..4 {
+            assert_eq!(list.entry(key, &guard).or_insert(0), Ok(&0));
+        }
+    }
+
+    // Concurrent counters on occupied keys: every increment is counted exactly once.
+    scope(|s| {
+        for _ in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                for step in 0..STEPS {
+                    let guard = pin();
+                    let _ = list
+                        .entry(step % 4, &guard)
+                        .and_modify(|count| count + 1)
+                        .or_insert(usize::max_value())
+                        .unwrap();
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = pin();
+    for key in 0..4 {
+        assert_eq!(list.lookup(&key, &guard), Some(&(THREADS * STEPS / 4)));
+    }
+}
+
+#[test]
+fn from_iter_and_extend() {
+    const KEYS: usize = 4096;
+
+    // Later entries win, as for `HashMap`.
+    let list: SplitOrderedList<_> = (0..KEYS)
+        .map(|key| (key, key))
+        .chain((0..KEYS).step_by(2).map(|key| (key, key * 2)))
+        .collect();
+    assert_eq!(list.len(), KEYS);
+    {
+        let guard = pin();
+        for key in 0..KEYS {
+            let expected = if key % 2 == 0 { key * 2 } else { key };
+            assert_eq!(list.lookup(&key, &guard), Some(&expected));
+        }
+    }
+
+    // The replaced values are dropped once, e.g. under Miri.
+    let values = Arc::new(());
+    let dups: SplitOrderedList<_> = (0..64).map(|key| (key % 8, Arc::clone(&values))).collect();
+    assert_eq!(dups.len(), 8);
+    assert_eq!(Arc::strong_count(&values), 9);
+    drop(dups);
+    assert_eq!(Arc::strong_count(&values), 1);
+
+    // Bulk insertions racing with regular ones.
+    scope(|s| {
+        let list = &list;
+        s.spawn(move |_| {
+            let guard = pin();
+            list.upsert_many((KEYS..3 * KEYS).rev().map(|key| (key, key)), &guard);
+        });
+        s.spawn(move |_| {
+            let guard = pin();
+            for key in (KEYS..3 * KEYS).step_by(3) {
+                let _ = list.insert(&key, key, &guard);
+            }
+        });
+    })
+    .unwrap();
+
+    let guard = pin();
+    assert_eq!(list.len(), 3 * KEYS);
+    for key in KEYS..3 * KEYS {
+        assert_eq!(list.lookup(&key, &guard), Some(&key));
+    }
+}
+
+#[test]
+fn insert_many() {
+    let list = SplitOrderedList::new();
+    let guard = pin();
+    assert_eq!(list.insert(&5, 50, &guard), Ok(()));
+
+    let results = list.insert_many(vec![(7, 70), (5, 51), (1, 10), (7, 71), (0, 0)], &guard);
+    assert_eq!(
+        results,
+        vec![
+            Ok(()),
+            Err(InsertError::Occupied(51)),
+            Ok(()),
+            Err(InsertError::Occupied(71)),
+            Ok(()),
+        ]
+    );
+    assert_eq!(list.len(), 4);
+    for &(key, value) in [(0, 0), (1, 10), (5, 50), (7, 70)].iter() {
+        assert_eq!(list.lookup(&key, &guard), Some(&value));
+    }
+
+    let results = list.insert_many((8..4096).rev().map(|key| (key, key * 10)), &guard);
+    assert!(results.iter().all(Result::is_ok));
+    for key in 8..4096 {
+        assert_eq!(list.lookup(&key, &guard), Some(&(key * 10)));
+    }
+}
+
+#[test]
+fn drain() {
+    let list = SplitOrderedList::new();
+    for key in 0..256 {
+        assert_eq!(list.insert(&key, key.to_string(), &pin()), Ok(()));
+    }
+
+    let mut drained: Vec<_> = list.drain().collect();
+    drained.sort();
+    let mut expected: Vec<_> = (0..256).map(|key| (key, key.to_string())).collect();
+    expected.sort();
+    assert_eq!(drained, expected);
+    assert!(list.is_empty());
+    assert_eq!(list.lookup(&0, &pin()), None);
+
+    // The entries are removed even if the iterator is not consumed.
+    for key in 0..16 {
+        assert_eq!(list.insert(&key, key.to_string(), &pin()), Ok(()));
+    }
+    assert_eq!(list.drain().take(4).count(), 4);
+    assert!(list.is_empty());
+
+    // Values of alignment 1.
+    let list = SplitOrderedList::<bool>::new();
+    for key in 0..64 {
+        assert_eq!(list.insert(&key, key % 2 == 0, &pin()), Ok(()));
+    }
+    let mut drained: Vec<_> = list.drain().collect();
+    drained.sort();
+    assert_eq!(
+        drained,
+        (0..64).map(|key| (key, key % 2 == 0)).collect::<Vec<_>>()
+    );
+    assert!(list.is_empty());
+}
+
+#[test]
+fn into_iter() {
+    let list = SplitOrderedList::new();
+    let guard = pin();
+    for key in 0..256 {
+        assert_eq!(list.insert(&key, key.to_string(), &guard), Ok(()));
+    }
+    for key in (0..256).step_by(3) {
+        assert!(list.delete(&key, &guard).is_ok());
+    }
+
+    let mut entries: Vec<_> = list.into_iter().collect();
+    entries.sort();
+    let mut expected: Vec<_> = (0..256)
+        .filter(|key| key % 3 != 0)
+        .map(|key| (key, key.to_string()))
+        .collect();
+    expected.sort();
+    assert_eq!(entries, expected);
+
+    // The values that are not yielded are dropped with the iterator, each exactly once.
+    let value = Arc::new(());
+    let list = SplitOrderedList::new();
+    for key in 0..64 {
+        assert_eq!(list.insert(&key, value.clone(), &guard), Ok(()));
+    }
+    let taken: Vec<_> = list.into_iter().take(16).collect();
+    assert_eq!(Arc::strong_count(&value), 17);
+    drop(taken);
+    assert_eq!(Arc::strong_count(&value), 1);
+}
+
+#[test]
+fn retain() {
+    const THREADS: usize = 4;
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    {
+        let guard = pin();
+        for key in 0..KEYS {
+            assert_eq!(list.insert(&key, key * 2, &guard), Ok(()));
+        }
+    }
+
+    // Concurrent sweeps with the same predicate, racing with insertions of other keys.
+    scope(|s| {
+        for _ in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                let guard = pin();
+                list.retain(|key, value| key % 3 != 0 && *value == key * 2, &guard);
+            });
+        }
+        let list = &list;
+        s.spawn(move |_| {
+            let guard = pin();
+            for key in KEYS..2 * KEYS {
+                assert_eq!(list.insert(&key, key * 2, &guard), Ok(()));
+            }
+        });
+    })
+    .unwrap();
+
+    let guard = pin();
+    for key in 0..KEYS {
+        let expected = if key % 3 == 0 { None } else { Some(key * 2) };
+        assert_eq!(list.lookup(&key, &guard), expected.as_ref());
+    }
+    let inserted = (KEYS..2 * KEYS)
+        .filter(|key| list.lookup(key, &guard).is_some())
+        .count();
+    assert_eq!(list.len(), KEYS - (KEYS + 2) / 3 + inserted);
+}
+
+#[test]
+fn clear() {
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    {
+        let guard = pin();
+        for key in 0..KEYS {
+            assert_eq!(list.insert(&key, key, &guard), Ok(()));
+        }
+        let buckets = list.bucket_count();
+        list.clear(&guard);
+        assert!(list.is_empty());
+        assert!(list.bucket_count() < buckets);
+        assert_eq!(list.iter(&guard).next(), None);
+    }
+
+    // Clearing races with insertions: every surviving key is one of the inserted ones.
+    scope(|s| {
+        let list = &list;
+        s.spawn(move |_| {
+            let guard = pin();
+            for key in 0..KEYS {
+                assert_eq!(list.insert(&key, key, &guard), Ok(()));
+            }
+        });
+        s.spawn(move |_| {
+            let guard = pin();
+            list.clear(&guard);
+        });
+    })
+    .unwrap();
+
+    let guard = pin();
+    assert_eq!(list.len(), list.iter(&guard).count());
+    assert!(list.iter(&guard).all(|(key, value)| key == *value));
+}
+
+#[test]
+fn iter_chunks_concurrent_delete() {
+    let list = SplitOrderedList::<usize>::new();
+
+    {
+        let guard = pin();
+        for i in 0..1000 {
+            assert_eq!(list.insert(&i, i, &guard), Ok(()));
+        }
+    }
+
+    scope(|s| {
+        s.spawn(|_| {
+            for i in (1..1000).step_by(2) {
+                let guard = pin();
+                assert_eq!(list.delete(&i, &guard), Ok(&i));
+            }
+        });
+
+        // Even keys are present during the whole iteration, so each is yielded exactly once.
+        let mut keys: Vec<_> = list.iter_chunks(8).flatten().map(|(key, _)| key).collect();
+        keys.retain(|key| key % 2 == 0);
+        keys.sort();
+        assert_eq!(keys, (0..1000).step_by(2).collect::<Vec<_>>());
+    })
+    .unwrap();
+}
+
+#[test]
+fn lookup_verbose() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+
+    assert_eq!(list.lookup_verbose(&1, &guard), Lookup::Absent);
+    assert_eq!(list.insert(&1, 10, &guard), Ok(()));
+    assert_eq!(list.lookup_verbose(&1, &guard), Lookup::Found(&10));
+    assert_eq!(list.lookup_verbose(&3, &guard), Lookup::Absent);
+
+    // The node is unlinked right away by `delete`.
+    assert_eq!(list.delete(&1, &guard), Ok(&10));
+    assert_eq!(list.lookup_verbose(&1, &guard), Lookup::Absent);
+}
+
+#[test]
+fn buckets() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+
+    assert_eq!(list.buckets(&guard).collect::<Vec<_>>(), vec![(0, 0, None)]);
+
+    for &key in &[2, 4, 1] {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    // Bucket 1 is initialized by the insertion of 1. Within bucket 0, 4 comes before 2 in the
+    // split order.
+    assert_eq!(
+        list.buckets(&guard).collect::<Vec<_>>(),
+        vec![(0, 2, Some(4)), (1, 1, Some(1))]
+    );
+}
+
+#[test]
+fn full_key_range() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+    // Keys with the most significant bit set, and keys whose bit-reversal is the bit-reversal of a
+    // bucket index.
+    let keys = [
+        0,
+        1,
+        2,
+        1 << 63,
+        (1 << 63) | 1,
+        usize::max_value() - 1,
+        usize::max_value(),
+    ];
+
+    for &key in keys.iter() {
+        assert_eq!(list.insert(&key, !key, &guard), Ok(()));
+        assert_eq!(list.insert(&key, key, &guard), Err(key));
+    }
+    for key in 3..1024 {
+        assert_eq!(list.insert(&key, !key, &guard), Ok(()));
+    }
+    for &key in keys.iter() {
+        assert_eq!(list.lookup(&key, &guard), Some(&!key));
+    }
+    assert_eq!(list.iter(&guard).count(), keys.len() + 1021);
+
+    for &key in keys.iter() {
+        assert_eq!(list.delete(&key, &guard), Ok(&!key));
+        assert_eq!(list.lookup(&key, &guard), None);
+        assert_eq!(list.delete(&key, &guard), Err(()));
+    }
+    for key in 3..1024 {
+        assert_eq!(list.lookup(&key, &guard), Some(&!key));
+    }
+}
+
+#[test]
+fn set() {
+    let set = SplitOrderedSet::new();
+    let guard = pin();
+
+    assert!(!set.contains(37, &guard));
+    assert!(set.insert(37, &guard));
+    assert!(!set.insert(37, &guard));
+    assert!(set.contains(37, &guard));
+    assert!(set.remove(37, &guard));
+    assert!(!set.remove(37, &guard));
+    assert!(!set.contains(37, &guard));
+
+    for key in 0..100 {
+        assert!(set.insert(key, &guard));
+    }
+    assert_eq!(set.len(), 100);
+    let mut keys: Vec<_> = set.iter(&guard).collect();
+    keys.sort();
+    assert_eq!(keys, (0..100).collect::<Vec<_>>());
+}
+
+#[test]
+fn set_concurrent_remove() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 256;
+
+    let set = SplitOrderedSet::new();
+    {
+        let guard = pin();
+        for key in 0..KEYS {
+            assert!(set.insert(key, &guard));
+        }
+    }
+
+    // Each key is removed exactly once.
+    let removed = AtomicUsize::new(0);
+    scope(|s| {
+        for _ in 0..THREADS {
+            let (set, removed) = (&set, &removed);
+            s.spawn(move |_| {
+                let guard = pin();
+                for key in 0..KEYS {
+                    if set.remove(key, &guard) {
+                        let _ = removed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = pin();
+    assert_eq!(removed.load(Ordering::Relaxed), KEYS);
+    assert!(set.is_empty());
+    assert_eq!(set.iter(&guard).count(), 0);
+}
+
+#[test]
+fn lookup_many() {
+    const KEYS: usize = 4096;
+
+    let list = SplitOrderedList::new();
+    let guard = pin();
+    for key in (0..KEYS).step_by(2) {
+        assert_eq!(list.insert(&key, key * 10, &guard), Ok(()));
+    }
+    assert_eq!(list.delete(&0, &guard), Ok(&0));
+
+    let keys: Vec<_> = (0..KEYS)
+        .rev()
+        .chain(vec![2, 2, usize::max_value()])
+        .collect();
+    let results = list.lookup_many(&keys, &guard);
+    assert_eq!(results.len(), keys.len());
+    for (key, result) in keys.iter().zip(results) {
+        let expected = if key % 2 == 0 && *key != 0 && *key < KEYS {
+            Some(key * 10)
+        } else {
+            None
+        };
+        assert_eq!(result, expected.as_ref(), "key {}", key);
+    }
+    assert!(list.lookup_many(&[], &guard).is_empty());
+}
+
+#[test]
+fn snapshot() {
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::new();
+    {
+        let guard = pin();
+        for key in 0..KEYS {
+            assert_eq!(list.insert(&key, key, &guard), Ok(()));
+        }
+    }
+
+    // The even keys stay, the odd keys churn, and the keys above `KEYS` are inserted.
+    scope(|s| {
+        let list = &list;
+        s.spawn(move |_| {
+            for round in 0..8 {
+                let guard = pin();
+                for key in (1..KEYS).step_by(2) {
+                    let _ = list.delete(&key, &guard);
+                    let _ = list.insert(&key, key, &guard);
+                }
+                for key in (KEYS + round * 64..KEYS + (round + 1) * 64).step_by(2) {
+                    let _ = list.insert(&key, key, &guard);
+                }
+            }
+        });
+        for _ in 0..8 {
+            let guard = pin();
+            let snapshot = list.snapshot(&guard);
+            let mut keys: Vec<_> = snapshot.iter().map(|&(key, _)| key).collect();
+            keys.sort();
+            keys.dedup();
+            assert_eq!(keys.len(), snapshot.len());
+            assert!(snapshot.iter().all(|&(key, value)| key == value));
+            assert!(snapshot.iter().all(|&(key, _)| key < KEYS || key % 2 == 0));
+            for key in (0..KEYS).step_by(2) {
+                assert!(keys.binary_search(&key).is_ok(), "key {} is missing", key);
+            }
+        }
+    })
+    .unwrap();
+}
+
+#[test]
+fn clone_snapshot() {
+    let list = SplitOrderedList::<String>::builder()
+        .load_factor(4)
+        .initial_buckets(8)
+        .build();
+    let guard = pin();
+    for key in 0..1000 {
+        assert_eq!(list.insert(&key, key.to_string(), &guard), Ok(()));
+    }
+    let checkpoint = list.clone_snapshot(&guard);
+    assert_eq!(checkpoint.bucket_count(), list.bucket_count());
+    assert_eq!(checkpoint.len(), 1000);
+
+    // The copy is independent of the original.
+    for key in (0..1000).step_by(2) {
+        assert!(list.delete(&key, &guard).is_ok());
+    }
+    assert_eq!(
+        list.update(&1, "one".to_string(), &guard),
+        Ok(&"1".to_string())
+    );
+    let mut diff: Vec<_> = checkpoint
+        .iter(&guard)
+        .filter(|&(key, value)| list.lookup(&key, &guard) != Some(value))
+        .map(|(key, _)| key)
+        .collect();
+    diff.sort();
+    assert_eq!(diff.len(), 501);
+    assert_eq!(diff[..2], [0, 1]);
+    assert_eq!(checkpoint.lookup(&1, &guard), Some(&"1".to_string()));
+
+    // Grows and shrinks with the same policy.
+    for key in 0..1000 {
+        assert!(checkpoint.delete(&key, &guard).is_ok());
+    }
+    assert_eq!(checkpoint.bucket_count(), 8);
+}
+
+#[test]
+fn pinned() {
+    const THREADS: usize = 4;
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::new();
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                for key in (t..KEYS).step_by(THREADS) {
+                    assert_eq!(list.insert_pinned(&key, key.to_string()), Ok(()));
+                    let value = list.lookup_pinned(&key).unwrap();
+                    // The value outlives its deletion.
+                    assert_eq!(list.delete_pinned(&key).as_deref(), Ok(&key.to_string()));
+                    assert_eq!(*value, key.to_string());
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert!(list.is_empty());
+}
+
+#[test]
+fn lookup_project() {
+    let list = SplitOrderedList::<(usize, String)>::new();
+    let guard = pin();
+
+    assert_eq!(list.insert(&1, (1, "one".to_string()), &guard), Ok(()));
+    assert_eq!(
+        list.lookup_project(&1, |v| &v.1, &guard)
+            .map(String::as_str),
+        Some("one")
+    );
+    assert_eq!(list.lookup_project(&2, |v| &v.1, &guard), None);
+}
+
+#[test]
+fn memory_budget() {
+    let budget = MemoryBudget::new(1 << 20);
+    let list = SplitOrderedList::<usize>::with_budget(budget.clone());
+    let guard = pin();
+
+    let mut inserted = 0;
+    loop {
+        match list.insert_budgeted(&inserted, inserted, &guard) {
+            Ok(()) => inserted += 1,
+            Err(InsertError::OutOfBudget(v)) => {
+                assert_eq!(v, inserted);
+                break;
+            }
+            Err(e) => panic!("unexpected error {:?}", e),
+        }
+    }
+    assert!(inserted > 0);
+    assert_eq!(
+        list.insert_budgeted(&0, 0, &guard),
+        Err(InsertError::OutOfBudget(0))
+    );
+
+    let used = budget.used();
+    assert_eq!(list.delete(&0, &guard), Ok(&0));
+    assert!(budget.used() < used);
+}
+
+#[test]
+fn bucket_txn() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+
+    assert_eq!(list.insert(&1, 1, &guard), Ok(()));
+    list.bucket_txn(&1, &guard, |txn| {
+        assert_eq!(txn.delete(&1), Ok(&1));
+        assert_eq!(txn.insert(&65, 65), Ok(()));
+    });
+    assert_eq!(list.lookup(&1, &guard), None);
+    assert_eq!(list.lookup(&65, &guard), Some(&65));
+
+    // The keys of the bucket of 65 in the table of 2 buckets, in different stripes.
+    assert_eq!(list.bucket_count(), 2);
+    list.bucket_txn(&65, &guard, |txn| {
+        assert_eq!(txn.insert(&3, 3), Ok(()));
+        assert_eq!(txn.lookup(&3), Some(&3));
+    });
+}
+
+#[test]
+#[should_panic(expected = "not in the transaction's bucket")]
+fn bucket_txn_other_bucket() {
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+    list.bucket_txn(&1, &guard, |txn| txn.lookup(&2).is_some());
+}
+
 #[test]
 fn stress_sequential() {
     const STEPS: usize = 4096;
@@ -52,3 +1444,327 @@ fn log_concurrent() {
         THREADS, STEPS,
     );
 }
+
+#[test]
+fn counter_increment() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 64;
+    const STEPS: usize = 256;
+    scenarios::counter_increment_atomic::<SplitOrderedList<_>>(THREADS, KEYS, STEPS);
+    scenarios::counter_increment_update(THREADS, KEYS, STEPS);
+}
+
+#[test]
+fn op_log() {
+    const THREADS: usize = 4;
+    const STEPS: usize = 1024;
+
+    let log = Arc::new(AppendLog::new());
+    let list = SplitOrderedList::with_op_log(log.clone());
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                for i in 0..STEPS {
+                    let guard = pin();
+                    let key = (i * 7 + t) % 64;
+                    if list.insert(&key, key + t, &guard).is_err() {
+                        let _ = list.delete(&key, &guard);
+                    }
+                }
+            });
+        }
+    })
+    .unwrap();
+
+    let guard = pin();
+    let rebuilt = SplitOrderedList::rebuild_from_log(log.clone(), &guard);
+    for key in 0..64 {
+        assert_eq!(rebuilt.lookup(&key, &guard), list.lookup(&key, &guard));
+    }
+
+    // The rebuilt map keeps logging.
+    let len = log.len();
+    assert_eq!(rebuilt.insert(&1000, 1, &guard), Ok(()));
+    assert_eq!(log.get(len, &guard), Some(&LogOp::Insert(1000, 1)));
+}
+
+#[cfg(feature = "access-time")]
+#[test]
+fn idle_entries() {
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    let list = SplitOrderedList::new();
+    let guard = pin();
+    assert_eq!(list.insert(&1, "a", &guard), Ok(()));
+    assert_eq!(list.insert(&2, "b", &guard), Ok(()));
+
+    sleep(Duration::from_millis(50));
+    assert_eq!(list.lookup(&1, &guard), Some(&"a"));
+
+    let idle: Vec<_> = list
+        .iter_idle_longer_than(Duration::from_millis(25), &guard)
+        .collect();
+    assert_eq!(idle, vec![(2, &"b")]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_snapshot() {
+    let list = SplitOrderedList::new();
+    let guard = pin();
+    for key in 0..100 {
+        assert_eq!(list.insert(&key, format!("value {}", key), &guard), Ok(()));
+    }
+    for key in (0..100).step_by(3) {
+        assert!(list.delete(&key, &guard).is_ok());
+    }
+
+    let json = serde_json::to_string(&list).unwrap();
+    let restored: SplitOrderedList<String> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.len(), list.len());
+    for key in 0..100 {
+        assert_eq!(restored.lookup(&key, &guard), list.lookup(&key, &guard));
+    }
+
+    let json = format!("{{\"{}\": \"value\"}}", usize::max_value());
+    let restored: SplitOrderedList<String> = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        restored.lookup(&usize::max_value(), &guard),
+        Some(&"value".to_string())
+    );
+}
+
+#[cfg(feature = "pmem")]
+#[test]
+fn persist_hooks() {
+    use cs492_concur_homework::pmem::PersistHooks;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Event {
+        Flush(usize),
+        Fence,
+    }
+
+    #[derive(Debug, Default, Clone)]
+    struct Recorder(Arc<Mutex<Vec<Event>>>);
+
+    impl PersistHooks for Recorder {
+        fn flush(&self, addr: *const u8, len: usize) {
+            assert!(len > 0);
+            self.0.lock().unwrap().push(Event::Flush(addr as usize));
+        }
+
+        fn fence(&self) {
+            self.0.lock().unwrap().push(Event::Fence);
+        }
+    }
+
+    let recorder = Recorder::default();
+    let list = SplitOrderedList::with_persist_hooks(Box::new(recorder.clone()));
+    let guard = pin();
+    let events = || recorder.0.lock().unwrap().clone();
+
+    // Publication of a node in the initialized bucket 0: the node and its value before the link.
+    assert_eq!(list.insert(&0, 0, &guard), Ok(()));
+    let value = list.lookup(&0, &guard).unwrap() as *const i32 as usize;
+    let (node, link) = match events().as_slice() {
+        [Event::Flush(node), Event::Flush(v), Event::Fence, Event::Flush(link), Event::Fence]
+            if *v == value =>
+        {
+            (*node, *link)
+        }
+        events => panic!("unexpected events {:?}", events),
+    };
+    assert_ne!(node, link);
+
+    // Unlink of the same node: the marked node and the same link.
+    assert_eq!(list.delete(&0, &guard), Ok(&0));
+    assert_eq!(
+        events()[5..],
+        [Event::Flush(node), Event::Flush(link), Event::Fence]
+    );
+
+    // Publication of the sentinel of bucket 1, then of the node.
+    assert_eq!(list.insert(&1, 1, &guard), Ok(()));
+    let events = events();
+    assert_eq!(events.len(), 8 + 4 + 5);
+    assert!(matches!(
+        events[8..12],
+        [Event::Flush(_), Event::Fence, Event::Flush(_), Event::Fence]
+    ));
+    assert!(matches!(
+        events[12..],
+        [
+            Event::Flush(_),
+            Event::Flush(_),
+            Event::Fence,
+            Event::Flush(_),
+            Event::Fence
+        ]
+    ));
+}
+
+#[cfg(feature = "teaching")]
+#[test]
+fn visualize() {
+    let list = SplitOrderedList::new();
+    let guard = pin();
+    assert_eq!(list.insert(&1, 10, &guard), Ok(()));
+    assert_eq!(list.insert(&2, 20, &guard), Ok(()));
+    assert_eq!(list.delete(&2, &guard), Ok(&20));
+
+    let out = list.visualize(&guard);
+    assert!(out.starts_with("size: 2, count: 1\nhead"));
+    assert!(out.contains("[bucket 0]"));
+    assert!(out.contains("[bucket 1]"));
+    assert!(out.contains("1: 10"));
+    assert!(!out.contains("2: 20"));
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_map() {
+    use rayon::prelude::*;
+
+    let list = SplitOrderedList::new();
+    let guard = pin();
+    for key in 0..10_000 {
+        assert_eq!(list.insert(&key, key * 2, &guard), Ok(()));
+    }
+    for key in (0..10_000).step_by(3) {
+        assert!(list.delete(&key, &guard).is_ok());
+    }
+
+    let mut entries: Vec<_> = list.par_map(|key, value| (key, *value)).collect();
+    entries.sort();
+    let mut expected: Vec<_> = list
+        .iter(&guard)
+        .map(|(key, value)| (key, *value))
+        .collect();
+    expected.sort();
+    assert_eq!(entries, expected);
+    assert_eq!(
+        list.par_map(|_, value| *value).sum::<usize>(),
+        expected.iter().map(|(_, value)| *value).sum::<usize>()
+    );
+
+    // Concurrent inserts don't make the iteration yield a key twice, or miss one present
+    // throughout, even when they initialize buckets in the middle of the chains.
+    scope(|s| {
+        s.spawn(|_| {
+            for key in 10_000..20_000 {
+                assert_eq!(list.insert(&key, key * 2, &pin()), Ok(()));
+            }
+        });
+        let mut keys: Vec<_> = list.par_map(|key, _| key).collect();
+        let len = keys.len();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), len);
+        assert!(expected
+            .iter()
+            .all(|(key, _)| keys.binary_search(key).is_ok()));
+    })
+    .unwrap();
+
+    // Only the initialized buckets are walked, and none is initialized on the way.
+    let list = SplitOrderedList::builder().initial_buckets(1 << 16).build();
+    assert_eq!(list.insert(&12_345, 1, &guard), Ok(()));
+    let buckets = list.buckets(&guard).count();
+    assert_eq!(
+        list.par_map(|key, value| (key, *value)).collect::<Vec<_>>(),
+        vec![(12_345, 1)]
+    );
+    assert_eq!(list.buckets(&guard).count(), buckets);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_iter() {
+    use rayon::prelude::*;
+
+    let list = SplitOrderedList::new();
+    let guard = pin();
+    for key in 0..10_000 {
+        assert_eq!(list.insert(&key, key * 2, &guard), Ok(()));
+    }
+    for key in (0..10_000).step_by(3) {
+        assert!(list.delete(&key, &guard).is_ok());
+    }
+
+    let mut entries: Vec<_> = list.par_iter(&guard).collect();
+    entries.sort();
+    let mut expected: Vec<_> = list.iter(&guard).collect();
+    expected.sort();
+    assert_eq!(entries, expected);
+
+    // The values stay valid under the caller's guard after they're deleted.
+    for &(key, value) in &entries {
+        assert_eq!(list.delete(&key, &guard), Ok(value));
+    }
+    assert!(entries.iter().all(|&(key, value)| *value == key * 2));
+}
+
+#[test]
+fn non_debug_values() {
+    /// A handle without a `Debug` impl.
+    struct Handle(usize);
+
+    let list = SplitOrderedList::new();
+    let guard = pin();
+    for key in 0..100 {
+        assert!(list.insert(&key, Handle(key), &guard).is_ok());
+    }
+    for key in 0..100 {
+        assert_eq!(list.lookup(&key, &guard).map(|handle| handle.0), Some(key));
+    }
+    assert_eq!(
+        list.iter(&guard).map(|(_, handle)| handle.0).sum::<usize>(),
+        4950
+    );
+    for key in 0..100 {
+        assert!(list.delete(&key, &guard).is_ok());
+    }
+    assert!(list.is_empty());
+}
+
+#[cfg(feature = "instrument")]
+#[test]
+fn metrics() {
+    const THREADS: usize = 8;
+    const KEYS: usize = 1024;
+
+    let list = SplitOrderedList::<usize>::new();
+    let guard = pin();
+    for key in 0..KEYS {
+        assert_eq!(list.insert(&key, key, &guard), Ok(()));
+    }
+    // Uncontended: only the resizes are counted.
+    let metrics = list.metrics();
+    assert_eq!(metrics.find_retries, 0);
+    assert_eq!(metrics.insert_cas_failures, 0);
+    assert_eq!(metrics.bucket_init_races, 0);
+    assert!(metrics.resize_triggers >= 8);
+
+    scope(|s| {
+        for t in 0..THREADS {
+            let list = &list;
+            s.spawn(move |_| {
+                let guard = pin();
+                for key in (0..KEYS).filter(|key| key % THREADS == t) {
+                    assert_eq!(list.delete(&key, &guard), Ok(&key));
+                    assert_eq!(list.insert(&(key + KEYS), key, &guard), Ok(()));
+                }
+            });
+        }
+    })
+    .unwrap();
+    let after = list.metrics();
+    assert!(after.find_retries >= metrics.find_retries);
+    assert!(after.insert_cas_failures >= metrics.insert_cas_failures);
+    assert!(after.bucket_init_races >= metrics.bucket_init_races);
+    assert!(after.resize_triggers >= metrics.resize_triggers);
+}
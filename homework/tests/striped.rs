@@ -0,0 +1,62 @@
+use cs492_concur_homework::reclaim::pin;
+use cs492_concur_homework::wrapper::Striped;
+use cs492_concur_homework::{ConcurrentMap, SequentialMap};
+use std::collections::HashMap;
+
+pub mod map;
+
+/// Sequential map backed by the standard `HashMap`.
+#[derive(Default, Debug)]
+struct StdMap {
+    inner: HashMap<String, usize>,
+}
+
+impl SequentialMap<String, usize> for StdMap {
+    fn lookup<'a>(&'a self, key: &'a String) -> Option<&'a usize> {
+        self.inner.get(key)
+    }
+
+    fn insert<'a>(
+        &'a mut self,
+        key: &'a String,
+        value: usize,
+    ) -> Result<&'a mut usize, (&'a mut usize, usize)> {
+        use std::collections::hash_map::Entry;
+        match self.inner.entry(key.clone()) {
+            Entry::Occupied(e) => Err((e.into_mut(), value)),
+            Entry::Vacant(e) => Ok(e.insert(value)),
+        }
+    }
+
+    fn delete(&mut self, key: &String) -> Result<usize, ()> {
+        self.inner.remove(key).ok_or(())
+    }
+}
+
+#[test]
+fn smoke() {
+    let map = Striped::with_shards(4, StdMap::default);
+    let guard = pin();
+    assert_eq!(map.shard_count(), 4);
+
+    let key = String::from("aa");
+    assert_eq!(map.insert(&key, 42, &guard), Ok(()));
+    assert_eq!(map.insert(&key, 37, &guard), Err(37));
+    assert_eq!(map.lookup(&key, &guard, |v| v.cloned()), Some(42));
+    assert_eq!(map.delete(&key, &guard), Ok(42));
+    assert_eq!(map.delete(&key, &guard), Err(()));
+}
+
+#[test]
+fn stress_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096;
+    map::stress_concurrent::<String, Striped<StdMap>>(THREADS, STEPS);
+}
+
+#[test]
+fn log_concurrent() {
+    const THREADS: usize = 16;
+    const STEPS: usize = 4096 * 12;
+    map::log_concurrent::<String, Striped<StdMap>>(THREADS, STEPS);
+}
@@ -1,17 +1,27 @@
 //! Lock-free data structures.
+//!
+//! Without the default `std` feature, the crate is `no_std` and `Stack` is unavailable, since it
+//! pins the default collector.
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
 #![warn(missing_debug_implementations)]
 
+#[cfg(feature = "pmem")]
+extern crate alloc;
 extern crate crossbeam_epoch;
 extern crate crossbeam_utils;
 
 #[macro_use]
 mod utils;
 pub mod list;
+#[cfg(feature = "pmem")]
+pub mod pmem;
 mod queue;
+#[cfg(feature = "std")]
 mod stack;
 
 pub use list::List;
 pub use queue::Queue;
+#[cfg(feature = "std")]
 pub use stack::Stack;
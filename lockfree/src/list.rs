@@ -2,8 +2,12 @@
 
 use crossbeam_epoch::{unprotected, Atomic, Guard, Owned, Pointer, Shared};
 
-use std::cmp::Ordering::{Equal, Greater, Less};
-use std::sync::atomic::Ordering;
+use core::cmp::Ordering::{Equal, Greater, Less};
+#[cfg(feature = "node-meta")]
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+use crate::utils::prefetch;
 
 /// Linked list node.
 #[derive(Debug)]
@@ -12,6 +16,9 @@ pub struct Node<K, V> {
     next: Atomic<Node<K, V>>,
     key: K,
     value: V,
+    /// Metadata word for the users of the list, e.g. an access timestamp. Initially 0.
+    #[cfg(feature = "node-meta")]
+    meta: AtomicUsize,
 }
 
 /// Sorted singly linked list.
@@ -66,6 +73,8 @@ impl<K, V> Node<K, V> {
             next: Atomic::null(),
             key,
             value,
+            #[cfg(feature = "node-meta")]
+            meta: AtomicUsize::new(0),
         }
     }
 
@@ -73,6 +82,28 @@ impl<K, V> Node<K, V> {
     pub fn into_value(self) -> V {
         self.value
     }
+
+    /// Returns the key.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns the value.
+    pub fn value(&self) -> &V {
+        &self.value
+    }
+
+    /// Returns the pointer to the next node. The pointer is tagged if this node is logically
+    /// deleted.
+    pub fn next(&self) -> &Atomic<Node<K, V>> {
+        &self.next
+    }
+
+    /// Returns the metadata word. The list itself never reads or writes it.
+    #[cfg(feature = "node-meta")]
+    pub fn meta(&self) -> &AtomicUsize {
+        &self.meta
+    }
 }
 
 impl<'g, K, V> Cursor<'g, K, V>
@@ -91,6 +122,11 @@ where
         }
     }
 
+    /// Returns the `next` pointer of the previous node, i.e. the link to the current node.
+    pub fn prev(&self) -> &'g Atomic<Node<K, V>> {
+        self.prev
+    }
+
     /// Returns the current node.
     pub fn curr(&self) -> Shared<'g, Node<K, V>> {
         self.curr
@@ -107,6 +143,8 @@ where
         let found = loop {
             let curr_node = some_or!(unsafe { self.curr.as_ref() }, break false);
             let next = curr_node.next.load(Ordering::Acquire, guard);
+            // Fetch the next node while this one's key is compared.
+            prefetch(next.as_raw());
 
             // - finding stage is done if cursor.curr advancement stops
             // - advance cursor.curr if (.next is marked) || (cursor.curr < key)
@@ -160,6 +198,7 @@ where
 
             let curr_node = some_or!(unsafe { self.curr.as_ref() }, return Ok(false));
             let mut next = curr_node.next.load(Ordering::Acquire, guard);
+            prefetch(next.as_raw());
 
             if next.tag() != 0 {
                 next = next.with_tag(0);
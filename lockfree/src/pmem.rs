@@ -0,0 +1,21 @@
+//! Persistent-memory hooks.
+//!
+//! The data structures in this crate are volatile. To adapt them to a persistent-memory durability
+//! protocol, a downstream user provides [`PersistHooks`] that write back cache lines and order the
+//! write-backs (e.g. `clwb` followed by `sfence` on x86).
+
+use core::fmt::Debug;
+
+/// Flush/fence callbacks invoked by the data structures.
+///
+/// Before a node is published, the structure flushes the node's memory and fences, so that the
+/// node is persisted before any link to it. After the node is linked into or unlinked from the
+/// structure, it flushes the link that was written, i.e. the predecessor's `next` pointer or the
+/// structure's head, and fences again.
+pub trait PersistHooks: Debug + Send + Sync {
+    /// Writes back `len` bytes starting at `addr` to persistent memory.
+    fn flush(&self, addr: *const u8, len: usize);
+
+    /// Orders all previous `flush`es before subsequent stores.
+    fn fence(&self);
+}
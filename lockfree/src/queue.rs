@@ -5,13 +5,18 @@
 //! Michael and Scott.  Simple, Fast, and Practical Non-Blocking and Blocking Concurrent Queue
 //! Algorithms.  PODC 1996.  http://dl.acm.org/citation.cfm?id=248106
 
-use core::mem::MaybeUninit;
+use core::mem::{self, MaybeUninit};
 use core::ptr;
 use core::sync::atomic::Ordering;
 
 use crossbeam_epoch::{unprotected, Atomic, Guard, Owned, Shared};
 use crossbeam_utils::CachePadded;
 
+#[cfg(feature = "pmem")]
+use crate::pmem::PersistHooks;
+#[cfg(feature = "pmem")]
+use alloc::boxed::Box;
+
 /// Michael-Scott queue.
 // The representation here is a singly-linked list, with a sentinel node at the front. In general
 // the `tail` pointer may lag behind the actual tail. Non-sentinel nodes are either all `Data` or
@@ -20,6 +25,8 @@ use crossbeam_utils::CachePadded;
 pub struct Queue<T> {
     head: CachePadded<Atomic<Node<T>>>,
     tail: CachePadded<Atomic<Node<T>>>,
+    #[cfg(feature = "pmem")]
+    persist: Option<Box<dyn PersistHooks>>,
 }
 
 #[derive(Debug)]
@@ -44,6 +51,8 @@ impl<T> Default for Queue<T> {
         let q = Self {
             head: CachePadded::new(Atomic::null()),
             tail: CachePadded::new(Atomic::null()),
+            #[cfg(feature = "pmem")]
+            persist: None,
         };
         // TODO(taiki-e): when the minimum supported Rust version is bumped to 1.36+,
         // replace this with `mem::MaybeUninit`.
@@ -68,6 +77,30 @@ impl<T> Queue<T> {
         Self::default()
     }
 
+    /// Creates a new, empty queue that calls `hooks` on each push and pop.
+    ///
+    /// A pushed node is persisted before it is linked, and the link is persisted afterwards. A pop
+    /// persists the head pointer. The tail pointer is only a hint and is not persisted: a recovery
+    /// finds the tail by following the links from the head.
+    #[cfg(feature = "pmem")]
+    pub fn with_persist_hooks(hooks: Box<dyn PersistHooks>) -> Self {
+        let mut q = Self::default();
+        q.persist = Some(hooks);
+        q
+    }
+
+    /// Writes back `len` bytes at `addr` and fences, if the queue has persistence hooks.
+    #[inline]
+    fn persist(&self, _addr: *const u8, _len: usize) {
+        #[cfg(feature = "pmem")]
+        {
+            if let Some(hooks) = &self.persist {
+                hooks.flush(_addr, _len);
+                hooks.fence();
+            }
+        }
+    }
+
     /// Adds `t` to the back of the queue, possibly waking up threads blocked on `pop`.
     pub fn push(&self, t: T, guard: &Guard) {
         let new = Owned::new(Node {
@@ -75,6 +108,7 @@ impl<T> Queue<T> {
             next: Atomic::null(),
         });
         let new = Owned::into_shared(new, guard);
+        self.persist(new.as_raw() as *const u8, mem::size_of::<Node<T>>());
 
         loop {
             // We push onto the tail, so we'll start optimistically by looking there first.
@@ -98,6 +132,10 @@ impl<T> Queue<T> {
                 .compare_and_set(Shared::null(), new, Ordering::Release, guard)
                 .is_ok()
             {
+                self.persist(
+                    &tail_ref.next as *const _ as *const u8,
+                    mem::size_of::<Atomic<Node<T>>>(),
+                );
                 // try to move the tail pointer forward.
                 let _ = self
                     .tail
@@ -131,6 +169,10 @@ impl<T> Queue<T> {
                 .compare_and_set(head, next, Ordering::Release, guard)
                 .is_ok()
             {
+                self.persist(
+                    &*self.head as *const _ as *const u8,
+                    mem::size_of::<Atomic<Node<T>>>(),
+                );
                 unsafe {
                     guard.defer_destroy(head);
                     return Some(ptr::read(&next_ref.data).assume_init());
@@ -393,6 +435,34 @@ mod test {
         assert!(q.is_empty());
     }
 
+    #[cfg(feature = "pmem")]
+    #[test]
+    fn persist_hooks() {
+        use crate::pmem::PersistHooks;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Debug, Default, Clone)]
+        struct Recorder(Arc<Mutex<Vec<usize>>>);
+
+        impl PersistHooks for Recorder {
+            fn flush(&self, _addr: *const u8, len: usize) {
+                self.0.lock().unwrap().push(len);
+            }
+
+            fn fence(&self) {}
+        }
+
+        let recorder = Recorder::default();
+        let q = super::Queue::with_persist_hooks(Box::new(recorder.clone()));
+        let guard = &pin();
+        q.push(37i64, guard);
+        let node = mem::size_of::<Node<i64>>();
+        let link = mem::size_of::<Atomic<Node<i64>>>();
+        assert_eq!(*recorder.0.lock().unwrap(), vec![node, link]);
+        assert_eq!(q.try_pop(guard), Some(37));
+        assert_eq!(*recorder.0.lock().unwrap(), vec![node, link, link]);
+    }
+
     #[test]
     fn is_empty_dont_pop() {
         let q: Queue<i64> = Queue::new();
@@ -19,3 +19,16 @@ macro_rules! some_or {
         }
     }};
 }
+
+/// Hints the CPU to fetch the cache line at `ptr` into all cache levels. A no-op without the
+/// `prefetch` feature or on targets other than x86-64. Never faults, even on a null pointer.
+#[inline(always)]
+pub(crate) fn prefetch<T>(ptr: *const T) {
+    #[cfg(all(feature = "prefetch", target_arch = "x86_64"))]
+    unsafe {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch(ptr as *const i8, _MM_HINT_T0);
+    }
+    #[cfg(not(all(feature = "prefetch", target_arch = "x86_64")))]
+    let _ = ptr;
+}